@@ -1,6 +1,7 @@
+use crate::constants::{OTTR_IRI, XSD_PREFIX_IRI};
 #[cfg(test)]
 use crate::constants::OTTR_TRIPLE;
-use oxrdf::vocab::xsd;
+use oxrdf::vocab::{rdf, rdfs, xsd};
 use oxrdf::{BlankNode, NamedNode};
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
@@ -111,6 +112,76 @@ pub enum PType {
     NEListType(Box<PType>),
 }
 
+impl PType {
+    //Subtyping following the OTTR type system, LUB<T> is a subtype of T but only equal to itself
+    pub fn is_subtype_of(&self, other: &PType) -> bool {
+        match (self, other) {
+            (PType::BasicType(left, _), PType::BasicType(right, _)) => {
+                is_basic_subtype(left.as_str(), right.as_str())
+            }
+            (PType::LUBType(left), PType::LUBType(right)) => left.is_same_type(right),
+            (PType::LUBType(left), _) => left.is_subtype_of(other),
+            (PType::ListType(left), PType::ListType(right))
+            | (PType::NEListType(left), PType::ListType(right))
+            | (PType::NEListType(left), PType::NEListType(right)) => left.is_subtype_of(right),
+            _ => false,
+        }
+    }
+
+    //Prefixed names of basic types may differ even though the types are the same
+    pub fn is_same_type(&self, other: &PType) -> bool {
+        self.is_subtype_of(other) && other.is_subtype_of(self)
+    }
+}
+
+fn is_basic_subtype(left: &str, right: &str) -> bool {
+    if right == rdfs::RESOURCE.as_str() {
+        return true;
+    }
+    let mut current = Some(left);
+    while let Some(c) = current {
+        if c == right {
+            return true;
+        }
+        current = basic_supertype(c);
+    }
+    false
+}
+
+fn basic_supertype(iri: &str) -> Option<&'static str> {
+    let supertype = match iri {
+        x if x == xsd::BYTE.as_str() => xsd::SHORT,
+        x if x == xsd::SHORT.as_str() => xsd::INT,
+        x if x == xsd::INT.as_str() => xsd::LONG,
+        x if x == xsd::LONG.as_str() => xsd::INTEGER,
+        x if x == xsd::UNSIGNED_BYTE.as_str() => xsd::UNSIGNED_SHORT,
+        x if x == xsd::UNSIGNED_SHORT.as_str() => xsd::UNSIGNED_INT,
+        x if x == xsd::UNSIGNED_INT.as_str() => xsd::UNSIGNED_LONG,
+        x if x == xsd::UNSIGNED_LONG.as_str() => xsd::NON_NEGATIVE_INTEGER,
+        x if x == xsd::POSITIVE_INTEGER.as_str() => xsd::NON_NEGATIVE_INTEGER,
+        x if x == xsd::NON_NEGATIVE_INTEGER.as_str() => xsd::INTEGER,
+        x if x == xsd::NEGATIVE_INTEGER.as_str() => xsd::NON_POSITIVE_INTEGER,
+        x if x == xsd::NON_POSITIVE_INTEGER.as_str() => xsd::INTEGER,
+        x if x == xsd::INTEGER.as_str() => xsd::DECIMAL,
+        x if x == xsd::LANGUAGE.as_str() => xsd::TOKEN,
+        x if x == xsd::NAME.as_str() => xsd::TOKEN,
+        x if x == xsd::NMTOKEN.as_str() => xsd::TOKEN,
+        x if x == xsd::TOKEN.as_str() => xsd::NORMALIZED_STRING,
+        x if x == xsd::NORMALIZED_STRING.as_str() => xsd::STRING,
+        x if x == xsd::DATE_TIME_STAMP.as_str() => xsd::DATE_TIME,
+        x if x == xsd::DAY_TIME_DURATION.as_str() => xsd::DURATION,
+        x if x == xsd::YEAR_MONTH_DURATION.as_str() => xsd::DURATION,
+        x if x == xsd::ANY_URI.as_str() || x == OTTR_IRI || x == rdfs::LITERAL.as_str() => {
+            rdfs::RESOURCE
+        }
+        x if x.starts_with(XSD_PREFIX_IRI) || x == rdf::LANG_STRING.as_str() => rdfs::LITERAL,
+        _ => {
+            return None;
+        }
+    };
+    Some(supertype.as_str())
+}
+
 impl Display for PType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,4 +1,5 @@
 pub const OTTR_TRIPLE: &str = "http://ns.ottr.xyz/0.4/Triple";
+pub const OTTR_IRI: &str = "http://ns.ottr.xyz/0.4/IRI";
 pub const OTTR_PREFIX: &str = "ottr";
 pub const OTTR_PREFIX_IRI: &str = "http://ns.ottr.xyz/0.4/";
 pub const RDF_PREFIX: &str = "rdf";
@@ -9,5 +10,13 @@ pub const XSD_PREFIX: &str = "xsd";
 pub const XSD_PREFIX_IRI: &str = "http://www.w3.org/2001/XMLSchema#";
 pub const BLANK_NODE_IRI: &str = "BLANK_NODE_IRI";
 pub const NONE_IRI: &str = "NONE_IRI";
+pub const ANONYMOUS_BLANK_NODE: &str = "AnonymousBlankNode";
+pub const ZIP_ROW_INDEX_COLUMN: &str = "zip_row_index";
+pub const ZIP_POSITION_COLUMN: &str = "zip_position";
+pub const ZIP_LIST_LENGTH_COLUMN: &str = "zip_list_length";
+pub const REJECTION_REASON_COLUMN: &str = "rejection_reason";
+pub const ROW_INDEX_COLUMN: &str = "__row_index";
+pub const LANGUAGE_TAG_COLUMN_SUFFIX: &str = "__language_tag";
+pub const DATATYPE_COLUMN_SUFFIX: &str = "__datatype";
 pub const DEFAULT_PREDICATE_URI_PREFIX: &str = "https://github.com/magbak/maplib/Predicates#";
 pub const DEFAULT_TEMPLATE_PREFIX: &str = "default:";
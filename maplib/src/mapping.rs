@@ -1,43 +1,79 @@
 mod constant_terms;
 pub mod default;
 pub mod errors;
+mod lowering;
+pub mod minting;
+mod streaming;
 mod validation_inference;
 
 use crate::ast::{
-    ConstantLiteral, ConstantTerm, Instance, ListExpanderType, PType, Signature, StottrTerm,
-    Template,
+    ConstantLiteral, ConstantTerm, Instance, ListExpanderType, PType, Signature, StottrDocument,
+    StottrTerm, Template,
+};
+use crate::constants::{
+    DATATYPE_COLUMN_SUFFIX, LANGUAGE_TAG_COLUMN_SUFFIX, OTTR_TRIPLE, ROW_INDEX_COLUMN,
+    ZIP_LIST_LENGTH_COLUMN, ZIP_POSITION_COLUMN, ZIP_ROW_INDEX_COLUMN,
 };
-use crate::constants::OTTR_TRIPLE;
 use crate::document::document_from_str;
 use crate::errors::MaplibError;
 use crate::mapping::constant_terms::constant_to_expr;
 use crate::mapping::errors::MappingError;
+use crate::mapping::minting::MintingOptions;
+use crate::mapping::validation_inference::{
+    coerce_series, validate_non_empty_list_parameter, xsd_datatype_to_polars_datatype,
+};
 use crate::templates::TemplateDataset;
 use log::debug;
-use oxrdf::Triple;
-use polars::lazy::prelude::{col, Expr};
-use polars::prelude::{DataFrame, IntoLazy};
-use polars_core::series::Series;
+use oxrdf::vocab::{rdf, xsd};
+use oxrdf::{BlankNode, NamedNode, Triple};
+use polars::lazy::prelude::{col, concat, concat_list, Expr, LazyFrame};
+use polars::prelude::{DataFrame, IntoLazy, JoinType, UnionArgs, UniqueKeepStrategy};
+use polars_core::prelude::{AnyValue, BooleanChunked, DataType, NamedFrom, Series};
 use rayon::iter::ParallelDrainRange;
 use rayon::iter::ParallelIterator;
 use representation::RDFNodeType;
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::path::Path;
 use std::time::Instant;
-use triplestore::{TriplesToAdd, Triplestore};
+use triplestore::native_parquet_write::NativeParquetWriteOptions;
+use triplestore::{AddTriplesReport, TriplesToAdd, Triplestore};
 use uuid::Uuid;
 
+//Triple instances, and the row indices of the input rows dropped during expansion
+type ExpandedInstances = (Vec<OTTRTripleInstance>, Vec<Series>);
+//Triples to add, the row indices of the input rows of triples skipped due to nulls, and the
+//predicates of triples with datatypes given per row
+type CreatedTriples = (Vec<TriplesToAdd>, Option<Series>, HashSet<String>);
+
 pub struct Mapping {
     template_dataset: TemplateDataset,
     pub triplestore: Triplestore,
     use_caching: bool,
 }
 
+#[derive(Clone)]
 pub struct ExpandOptions {
     pub language_tags: Option<HashMap<String, String>>,
     pub unique_subsets: Option<Vec<Vec<String>>>,
+    pub minting: Option<HashMap<String, MintingOptions>>,
+    pub coerce_datatypes: bool,
+    pub quarantine_invalid_rows: bool,
+    //Maps parameters to columns containing a language tag per row
+    pub language_tag_columns: Option<HashMap<String, String>>,
+    //Maps parameters to columns containing a datatype IRI per row
+    pub datatype_columns: Option<HashMap<String, String>>,
+    //Number of rows expanded at a time by expand_lazy and expand_from_files
+    pub batch_size: Option<usize>,
+    //Retract triples previously produced by the template for the IRIs and blank nodes in the
+    //unique subsets, and the blank nodes the template created for them
+    pub upsert: bool,
+    //The named graph the triples are added to, by default the default graph
+    pub graph: Option<String>,
+    //Maps parameters without a type to the RDF node types of their columns, e.g. IRIs given as
+    //strings. Mapping::query_template returns these for its columns.
+    pub rdf_node_types: Option<HashMap<String, RDFNodeType>>,
 }
 
 struct OTTRTripleInstance {
@@ -58,6 +94,15 @@ impl Default for ExpandOptions {
         ExpandOptions {
             language_tags: None,
             unique_subsets: None,
+            minting: None,
+            coerce_datatypes: false,
+            quarantine_invalid_rows: false,
+            language_tag_columns: None,
+            datatype_columns: None,
+            batch_size: None,
+            upsert: false,
+            graph: None,
+            rdf_node_types: None,
         }
     }
 }
@@ -66,10 +111,50 @@ impl Default for ExpandOptions {
 pub struct PrimitiveColumn {
     pub rdf_node_type: RDFNodeType,
     pub language_tag: Option<String>,
+    //Language tags and datatypes given per row are kept in companion columns, see language_tag_column_name
+    pub language_tag_from_column: bool,
+    pub datatype_from_column: bool,
 }
 
-#[derive(Debug, PartialEq)]
-pub struct MappingReport {}
+#[derive(Debug, Default, PartialEq)]
+pub struct MappingReport {
+    //Identifies the expand call, see Mapping::retract
+    pub call_uuid: String,
+    //Triples added per predicate and object type, after nulls and duplicates are removed
+    pub triples_added: HashMap<String, HashMap<RDFNodeType, usize>>,
+    //Input rows for which an instance was skipped due to none for a non-optional parameter, or a
+    //triple was skipped due to a null. Each input row is counted once.
+    pub rows_dropped_null: usize,
+    //Duplicate triples within the call. Triples also added by other calls are not counted, as
+    //they are kept until every call that added them is retracted, see Mapping::retract.
+    pub duplicates_removed: usize,
+    pub validation_seconds: f32,
+    pub expansion_seconds: f32,
+    pub storage_seconds: f32,
+    pub used_caching: bool,
+    //Rows removed before expansion when quarantining invalid rows, with a rejection reason column
+    pub rejected_rows: Option<DataFrame>,
+    //Triples from previous expansions removed when upserting
+    pub triples_retracted: usize,
+}
+
+impl MappingReport {
+    fn add_triples_report(&mut self, add_triples_report: AddTriplesReport) {
+        let AddTriplesReport {
+            triples_added,
+            //Triples with nulls are skipped in create_triples, where the input rows are known
+            rows_dropped_null: _,
+            duplicates_removed,
+        } = add_triples_report;
+        for (predicate, m) in triples_added {
+            let self_m = self.triples_added.entry(predicate).or_default();
+            for (object_type, n) in m {
+                *self_m.entry(object_type).or_insert(0) += n;
+            }
+        }
+        self.duplicates_removed += duplicates_removed;
+    }
+}
 
 impl Mapping {
     pub fn new(
@@ -80,11 +165,32 @@ impl Mapping {
             _ => {}
         }
         let use_caching = caching_folder.is_some();
-        Ok(Mapping {
+        let mut mapping = Mapping {
             template_dataset: template_dataset.clone(),
             triplestore: Triplestore::new(caching_folder)
                 .map_err(|x| MappingError::TriplestoreError(x))?,
             use_caching,
+        };
+        if !mapping.template_dataset.ground_instances.is_empty() {
+            mapping.expand_ground_instances()?;
+        }
+        Ok(mapping)
+    }
+
+    //Opens a mapping with the triples previously expanded into the caching folder.
+    //Ground instances of the templates are not expanded again, they are already in the folder.
+    pub fn open(
+        template_dataset: &TemplateDataset,
+        caching_folder: String,
+    ) -> Result<Mapping, MaplibError> {
+        match env_logger::try_init() {
+            _ => {}
+        }
+        Ok(Mapping {
+            template_dataset: template_dataset.clone(),
+            triplestore: Triplestore::open(caching_folder)
+                .map_err(|x| MappingError::TriplestoreError(x))?,
+            use_caching: true,
         })
     }
 
@@ -132,9 +238,19 @@ impl Mapping {
         Ok(())
     }
 
-    pub fn write_native_parquet(&mut self, path: &str) -> Result<(), MappingError> {
+    pub fn write_native_parquet(
+        &mut self,
+        path: &str,
+        options: &NativeParquetWriteOptions,
+    ) -> Result<(), MappingError> {
+        self.triplestore
+            .write_native_parquet(Path::new(path), options)
+            .map_err(|x| MappingError::TriplestoreError(x))
+    }
+
+    pub fn read_native_parquet(&mut self, path: &str) -> Result<(), MappingError> {
         self.triplestore
-            .write_native_parquet(Path::new(path))
+            .read_native_parquet(Path::new(path))
             .map_err(|x| MappingError::TriplestoreError(x))
     }
 
@@ -144,6 +260,33 @@ impl Mapping {
             .map_err(|x| MappingError::TriplestoreError(x))
     }
 
+    //Adds templates, ground instances and prefixes, keeping the triples already in the triplestore.
+    //Ground instances in the new documents are expanded. If the documents or the expansion fail,
+    //the templates are left as they were.
+    pub fn add_documents(&mut self, documents: Vec<StottrDocument>) -> Result<(), MaplibError> {
+        let n_ground_instances = self.template_dataset.ground_instances.len();
+        let mut template_dataset = self.template_dataset.clone();
+        template_dataset
+            .add_documents(documents)
+            .map_err(|x| MaplibError::TemplateError(x))?;
+        let has_new_ground_instances = template_dataset.ground_instances.len() > n_ground_instances;
+        let previous_template_dataset =
+            std::mem::replace(&mut self.template_dataset, template_dataset);
+        if has_new_ground_instances {
+            if let Err(e) = self.expand_ground_instances_from(n_ground_instances) {
+                self.template_dataset = previous_template_dataset;
+                return Err(MaplibError::MappingError(e));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_template(&mut self, template: Template) -> Result<(), MaplibError> {
+        self.template_dataset
+            .add_template(template)
+            .map_err(|x| MaplibError::TemplateError(x))
+    }
+
     fn resolve_template(&self, s: &str) -> Result<&Template, MappingError> {
         if let Some(t) = self.template_dataset.get(s) {
             return Ok(t);
@@ -173,161 +316,389 @@ impl Mapping {
         df: DataFrame,
         options: ExpandOptions,
     ) -> Result<MappingReport, MappingError> {
+        let call_uuid = Uuid::new_v4().to_string();
+        let mut report = MappingReport {
+            call_uuid: call_uuid.clone(),
+            used_caching: self.use_caching,
+            ..Default::default()
+        };
+        self.expand_batch(template, df, &options, &call_uuid, &mut report)?;
+        Ok(report)
+    }
+
+    //Batches of the same call share the call uuid and the report
+    fn expand_batch(
+        &mut self,
+        template: &str,
+        df: DataFrame,
+        options: &ExpandOptions,
+        call_uuid: &String,
+        report: &mut MappingReport,
+    ) -> Result<(), MappingError> {
         let now = Instant::now();
+        if let Some(graph) = &options.graph {
+            NamedNode::new(graph).map_err(|x| MappingError::InvalidGraphIRI(graph.clone(), x))?;
+        }
         let target_template = self.resolve_template(template)?.clone();
         let target_template_name = target_template.signature.template_name.as_str().to_string();
+        //Rejected rows are returned as they were given
+        let input_df = if options.quarantine_invalid_rows {
+            Some(df.clone())
+        } else {
+            None
+        };
+        let df = if let Some(minting) = &options.minting {
+            self.mint_iris(df, minting)?
+        } else {
+            df
+        };
+        let df =
+            self.add_language_tag_and_datatype_columns(&target_template.signature, df, options)?;
+        let df = if options.coerce_datatypes {
+            self.coerce_dataframe_columns(&target_template.signature, df)?
+        } else {
+            df
+        };
+        let df = if let Some(input_df) = input_df {
+            let (df, rejected) =
+                self.quarantine_invalid_rows(&target_template.signature, input_df, df);
+            if let Some(rejected_rows) = &mut report.rejected_rows {
+                rejected_rows.vstack_mut(&rejected).unwrap();
+            } else {
+                report.rejected_rows = Some(rejected);
+            }
+            df
+        } else {
+            df
+        };
         let columns =
-            self.validate_infer_dataframe_columns(&target_template.signature, &df, &options)?;
-        let ExpandOptions {
-            language_tags: _,
-            unique_subsets: unique_subsets_opt,
-        } = options;
-        let unique_subsets = if let Some(unique_subsets) = unique_subsets_opt {
-            unique_subsets
+            self.validate_infer_dataframe_columns(&target_template.signature, &df, options)?;
+        let df = self.cast_subtype_columns(&target_template.signature, df);
+        //Rows are tracked through the expansion, so that a row dropped in several places is
+        //counted once
+        let df = df.with_row_count(ROW_INDEX_COLUMN, None).unwrap();
+        report.validation_seconds += now.elapsed().as_secs_f32();
+        let unique_subsets = if let Some(unique_subsets) = &options.unique_subsets {
+            unique_subsets.clone()
         } else {
             vec![]
         };
-        let call_uuid = Uuid::new_v4().to_string();
+        if options.upsert && unique_subsets.is_empty() {
+            return Err(MappingError::UpsertRequiresUniqueSubsets(
+                target_template_name,
+            ));
+        }
+        //Previous triples are retracted before the triples of the first chunk are added
+        let mut upsert_subjects = if options.upsert {
+            Some(unique_subset_subjects(&df, &unique_subsets, &columns))
+        } else {
+            None
+        };
 
         if self.use_caching {
             let n_50_mb = (df.estimated_size() / 50_000_000) + 1;
-            let chunk_size = df.height() / n_50_mb;
+            let chunk_size = max(df.height() / n_50_mb, 1);
             let mut offset = 0i64;
             loop {
                 let to_row = min(df.height(), offset as usize + chunk_size);
-                let df_slice = df.slice_par(offset, to_row);
+                let df_slice = df.slice_par(offset, to_row - offset as usize);
                 offset += chunk_size as i64;
-                let result_vec = self._expand(
+                let expansion_now = Instant::now();
+                let (result_vec, dropped_rows) = self._expand(
                     &target_template_name,
                     df_slice,
                     columns.clone(),
                     HashMap::new(),
                     unique_subsets.clone(),
                 )?;
-                self.process_results(result_vec, &call_uuid)?;
+                report.expansion_seconds += expansion_now.elapsed().as_secs_f32();
+                self.process_results(
+                    result_vec,
+                    dropped_rows,
+                    call_uuid,
+                    Some(&target_template_name),
+                    upsert_subjects.take(),
+                    &options.graph,
+                    report,
+                )?;
                 debug!("Finished processing {} rows", to_row);
                 if offset >= df.height() as i64 {
                     break;
                 }
             }
         } else {
-            let result_vec = self._expand(
+            let expansion_now = Instant::now();
+            let (result_vec, dropped_rows) = self._expand(
                 &target_template_name,
                 df,
                 columns,
                 HashMap::new(),
                 unique_subsets,
             )?;
-            self.process_results(result_vec, &call_uuid)?;
+            report.expansion_seconds += expansion_now.elapsed().as_secs_f32();
+            self.process_results(
+                result_vec,
+                dropped_rows,
+                call_uuid,
+                Some(&target_template_name),
+                upsert_subjects,
+                &options.graph,
+                report,
+            )?;
             debug!("Expansion took {} seconds", now.elapsed().as_secs_f32());
         }
-        Ok(MappingReport {})
+        Ok(())
+    }
+
+    //Removes the triples added by the expand call with the given call_uuid from the triplestore.
+    //Triples that other calls also added are kept. Returns the number of triples removed.
+    pub fn retract(&mut self, call_uuid: &str) -> Result<usize, MappingError> {
+        self.triplestore
+            .retract_call(&call_uuid.to_string())
+            .map_err(|x| MappingError::TriplestoreError(x))
+    }
+
+    pub fn expand_ground_instances(&mut self) -> Result<MappingReport, MappingError> {
+        self.expand_ground_instances_from(0)
+    }
+
+    //Ground instances before from were expanded when their documents were added
+    fn expand_ground_instances_from(&mut self, from: usize) -> Result<MappingReport, MappingError> {
+        let now = Instant::now();
+        let call_uuid = Uuid::new_v4().to_string();
+        let mut report = MappingReport {
+            call_uuid: call_uuid.clone(),
+            used_caching: self.use_caching,
+            ..Default::default()
+        };
+        //Ground instances have only constant arguments, so they are expanded from a frame with
+        //only a row index. Each ground instance counts as a row.
+        let mut result_vec = vec![];
+        let mut dropped_rows = vec![];
+        for (row, i) in self.template_dataset.ground_instances[from..]
+            .iter()
+            .enumerate()
+        {
+            let df = DataFrame::new(vec![Series::new(ROW_INDEX_COLUMN, [row as u32])]).unwrap();
+            let (instance_results, instance_dropped_rows) = self._expand_instances(
+                &vec![i.clone()],
+                df,
+                HashMap::new(),
+                HashMap::new(),
+                vec![],
+            )?;
+            result_vec.extend(instance_results);
+            dropped_rows.extend(instance_dropped_rows);
+        }
+        report.expansion_seconds += now.elapsed().as_secs_f32();
+        self.process_results(
+            result_vec,
+            dropped_rows,
+            &call_uuid,
+            None,
+            None,
+            &None,
+            &mut report,
+        )?;
+        debug!(
+            "Expanding ground instances took {} seconds",
+            now.elapsed().as_secs_f32()
+        );
+        Ok(report)
     }
 
     fn _expand(
         &self,
         name: &str,
-        mut df: DataFrame,
+        df: DataFrame,
         dynamic_columns: HashMap<String, PrimitiveColumn>,
         static_columns: HashMap<String, StaticColumn>,
         unique_subsets: Vec<Vec<String>>,
-    ) -> Result<Vec<OTTRTripleInstance>, MappingError> {
+    ) -> Result<ExpandedInstances, MappingError> {
         //At this point, the lf should have columns with names appropriate for the template to be instantiated (named_node).
         if let Some(template) = self.template_dataset.get(name) {
             if template.signature.template_name.as_str() == OTTR_TRIPLE {
-                Ok(vec![OTTRTripleInstance {
+                Ok((
+                    vec![OTTRTripleInstance {
+                        df,
+                        dynamic_columns,
+                        static_columns,
+                        has_unique_subset: !unique_subsets.is_empty(),
+                    }],
+                    vec![],
+                ))
+            } else {
+                self._expand_instances(
+                    &template.pattern_list,
                     df,
                     dynamic_columns,
                     static_columns,
-                    has_unique_subset: !unique_subsets.is_empty(),
-                }])
-            } else {
-                let now = Instant::now();
-
-                let mut expand_params_vec = vec![];
-                let colnames:HashSet<_> = df.get_column_names().iter().map(|x|x.to_string()).collect();
-                for i in &template.pattern_list {
-                    let mut instance_series = vec![];
-                    let vs = get_variable_names(i);
-                    for v in vs {
-                        if colnames.contains(v) {
-                            instance_series.push(df.column(v).unwrap().clone());
-                        }
+                    unique_subsets,
+                )
+            }
+        } else {
+            Err(MappingError::TemplateNotFound(name.to_string()))
+        }
+    }
+
+    fn _expand_instances(
+        &self,
+        instances: &Vec<Instance>,
+        mut df: DataFrame,
+        mut dynamic_columns: HashMap<String, PrimitiveColumn>,
+        static_columns: HashMap<String, StaticColumn>,
+        unique_subsets: Vec<Vec<String>>,
+    ) -> Result<ExpandedInstances, MappingError> {
+        let now = Instant::now();
+
+        //Blank nodes in the pattern list are fresh for each instance, i.e. for each row.
+        let blank_nodes = get_blank_nodes(instances);
+        if !blank_nodes.is_empty() {
+            let height = if df.width() == 0 { 1 } else { df.height() };
+            let instance_uuid = Uuid::new_v4().simple().to_string();
+            for bn in blank_nodes {
+                let column_name = bn.to_string();
+                let minted: Vec<String> = (0..height)
+                    .map(|i| format!("{}_{}_{}", column_name, instance_uuid, i))
+                    .collect();
+                df.with_column(Series::new(&column_name, minted)).unwrap();
+                dynamic_columns.insert(
+                    column_name,
+                    PrimitiveColumn {
+                        rdf_node_type: RDFNodeType::BlankNode,
+                        language_tag: None,
+                        language_tag_from_column: false,
+                        datatype_from_column: false,
+                    },
+                );
+            }
+        }
+
+        let mut expand_params_vec = vec![];
+        let colnames: HashSet<_> = df
+            .get_column_names()
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let row_index = df.column(ROW_INDEX_COLUMN).ok().cloned();
+        for i in instances {
+            let mut instance_series = vec![];
+            let vs = get_variable_names(i);
+            for v in vs {
+                for c in [language_tag_column_name(&v), datatype_column_name(&v), v] {
+                    if colnames.contains(&c) {
+                        instance_series.push(df.column(&c).unwrap().clone());
                     }
-                    expand_params_vec.push((i, instance_series));
                 }
+            }
+            if let Some(row_index) = &row_index {
+                instance_series.push(row_index.clone());
+            }
+            expand_params_vec.push((i, instance_series));
+        }
 
-                debug!("Cloning args took {} seconds", now.elapsed().as_secs_f64());
-
-                let results: Vec<Result<Vec<OTTRTripleInstance>, MappingError>> = expand_params_vec
-                    .par_drain(..)
-                    .map(|(i, series_vec)| {
-                        let target_template =
-                            self.template_dataset.get(i.template_name.as_str()).unwrap();
-                        let (
-                            instance_df,
-                            instance_dynamic_columns,
-                            instance_static_columns,
-                            new_unique_subsets,
-                        ) = create_remapped(
-                            i,
-                            &target_template.signature,
-                            series_vec,
-                            &dynamic_columns,
-                            &static_columns,
-                            &unique_subsets,
-                        )?;
-
-                        self._expand(
-                            i.template_name.as_str(),
-                            instance_df,
-                            instance_dynamic_columns,
-                            instance_static_columns,
-                            new_unique_subsets,
-                        )
-                    })
-                    .collect();
-                let mut results_ok = vec![];
-                for r in results {
-                    results_ok.push(r?)
+        debug!("Cloning args took {} seconds", now.elapsed().as_secs_f64());
+
+        let results: Vec<Result<ExpandedInstances, MappingError>> = expand_params_vec
+            .par_drain(..)
+            .map(|(i, series_vec)| {
+                let target_template = self.template_dataset.get(i.template_name.as_str()).unwrap();
+                let (
+                    instance_df,
+                    instance_dynamic_columns,
+                    instance_static_columns,
+                    new_unique_subsets,
+                    mut dropped_rows,
+                ) = create_remapped(
+                    i,
+                    &target_template.signature,
+                    series_vec,
+                    &dynamic_columns,
+                    &static_columns,
+                    &unique_subsets,
+                )?;
+                if has_none_for_non_optional(&target_template.signature, &instance_static_columns) {
+                    //The instance is ignored entirely, as none is given for a non-optional parameter
+                    if let Ok(instance_row_index) = instance_df.column(ROW_INDEX_COLUMN) {
+                        dropped_rows.push(instance_row_index.clone());
+                    } else if let Some(row_index) = &row_index {
+                        dropped_rows.push(row_index.clone());
+                    }
+                    return Ok((vec![], dropped_rows));
                 }
 
-                Ok(flatten(results_ok))
-            }
-        } else {
-            Err(MappingError::TemplateNotFound(name.to_string()))
+                let (instances, child_dropped_rows) = self._expand(
+                    i.template_name.as_str(),
+                    instance_df,
+                    instance_dynamic_columns,
+                    instance_static_columns,
+                    new_unique_subsets,
+                )?;
+                dropped_rows.extend(child_dropped_rows);
+                Ok((instances, dropped_rows))
+            })
+            .collect();
+        let mut results_ok = vec![];
+        let mut dropped_rows = vec![];
+        for r in results {
+            let (instances, instance_dropped_rows) = r?;
+            results_ok.push(instances);
+            dropped_rows.extend(instance_dropped_rows);
         }
+
+        Ok((flatten(results_ok), dropped_rows))
     }
 
+    //dropped_rows are the row indices of the input rows that were dropped during expansion.
+    //When upserting, the triples of previous calls of the template with the upsert subjects are
+    //retracted first.
     fn process_results(
         &mut self,
         mut result_vec: Vec<OTTRTripleInstance>,
+        mut dropped_rows: Vec<Series>,
         call_uuid: &String,
+        template_name: Option<&str>,
+        upsert_subjects: Option<Series>,
+        graph: &Option<String>,
+        report: &mut MappingReport,
     ) -> Result<(), MappingError> {
         let now = Instant::now();
-        let triples: Vec<
-            Result<(DataFrame, RDFNodeType, Option<String>, Option<String>, bool), MappingError>,
-        > = result_vec
+        let triples: Vec<Result<CreatedTriples, MappingError>> = result_vec
             .par_drain(..)
             .map(|i| create_triples(i))
             .collect();
-        let mut ok_triples = vec![];
+        let mut all_triples_to_add = vec![];
+        let mut datatype_column_predicates = HashSet::new();
         for t in triples {
-            ok_triples.push(t?);
+            let (triples_to_add, triple_dropped_rows, predicates) = t?;
+            all_triples_to_add.extend(triples_to_add);
+            dropped_rows.extend(triple_dropped_rows);
+            datatype_column_predicates.extend(predicates);
         }
-        let mut all_triples_to_add = vec![];
-        for (df, rdf_node_type, language_tag, verb, has_unique_subset) in ok_triples {
-            all_triples_to_add.push(TriplesToAdd {
-                df,
-                object_type: rdf_node_type,
-                language_tag,
-                static_verb_column: verb,
-                has_unique_subset,
-            });
+        report.rows_dropped_null += count_rows(dropped_rows);
+        for t in &mut all_triples_to_add {
+            t.graph = graph.clone();
         }
-        self.triplestore
+        self.validate_single_object_type(&all_triples_to_add, &datatype_column_predicates, graph)?;
+        report.expansion_seconds += now.elapsed().as_secs_f32();
+        let storage_now = Instant::now();
+        if let Some(template_name) = template_name {
+            if let Some(subjects) = upsert_subjects {
+                let mut previous_calls = self.triplestore.calls_with_source(template_name);
+                previous_calls.remove(call_uuid);
+                report.triples_retracted += self
+                    .triplestore
+                    .retract_subjects(&subjects, &previous_calls, graph)
+                    .map_err(|x| MappingError::TriplestoreError(x))?;
+            }
+            self.triplestore.set_call_source(call_uuid, template_name);
+        }
+        let add_triples_report = self
+            .triplestore
             .add_triples_vec(all_triples_to_add, call_uuid)
             .map_err(|x| MappingError::TriplestoreError(x))?;
+        report.add_triples_report(add_triples_report);
+        report.storage_seconds += storage_now.elapsed().as_secs_f32();
 
         debug!(
             "Result processing took {} seconds",
@@ -335,23 +706,118 @@ impl Mapping {
         );
         Ok(())
     }
+
+    //Queries require the objects of a predicate to have a single type, so datatypes given per
+    //row must agree with each other and with the triples of the predicate already in the graph
+    fn validate_single_object_type(
+        &self,
+        triples: &[TriplesToAdd],
+        predicates: &HashSet<String>,
+        graph: &Option<String>,
+    ) -> Result<(), MappingError> {
+        for predicate in predicates {
+            let mut object_types = self.triplestore.predicate_object_types(predicate, graph);
+            for t in triples {
+                let has_predicate = if t.df.height() == 0 {
+                    false
+                } else if let Some(verb) = &t.static_verb_column {
+                    verb == predicate
+                } else {
+                    let verbs = t.df.column("verb").unwrap().utf8().unwrap();
+                    verbs.into_iter().any(|x| x == Some(predicate.as_str()))
+                };
+                if has_predicate && !object_types.contains(&t.object_type) {
+                    object_types.push(t.object_type.clone());
+                }
+            }
+            if object_types.len() > 1 {
+                return Err(MappingError::MultipleObjectTypesForPredicate(
+                    predicate.clone(),
+                    object_types,
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
-fn get_variable_names(i: &Instance) -> Vec<&String> {
+//The IRIs and blank nodes in the columns of the unique subsets
+fn unique_subset_subjects(
+    df: &DataFrame,
+    unique_subsets: &[Vec<String>],
+    columns: &HashMap<String, PrimitiveColumn>,
+) -> Series {
+    let mut subjects = Series::new_empty("subject", &DataType::Utf8);
+    for c in unique_subsets.iter().flatten() {
+        let is_resource = matches!(
+            columns.get(c).map(|x| &x.rdf_node_type),
+            Some(RDFNodeType::IRI | RDFNodeType::BlankNode)
+        );
+        if let (true, Ok(series)) = (is_resource, df.column(c)) {
+            if let DataType::List(_) = series.dtype() {
+                continue;
+            }
+            subjects
+                .append(&series.cast(&DataType::Utf8).unwrap())
+                .unwrap();
+        }
+    }
+    subjects.unique().unwrap()
+}
+
+fn get_variable_names(i: &Instance) -> Vec<String> {
     let mut out_vars = vec![];
     for a in &i.argument_list {
-        if let StottrTerm::Variable(v) = &a.term {
-            out_vars.push(&v.name);
-        } else if let StottrTerm::List(..) = &a.term {
-            todo!();
-        }
+        add_term_variable_names(&a.term, &mut out_vars);
     }
     out_vars
 }
 
-fn create_triples(
-    i: OTTRTripleInstance,
-) -> Result<(DataFrame, RDFNodeType, Option<String>, Option<String>, bool), MappingError> {
+fn add_term_variable_names(term: &StottrTerm, out_vars: &mut Vec<String>) {
+    match term {
+        StottrTerm::Variable(v) => {
+            out_vars.push(v.name.clone());
+        }
+        StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::BlankNode(bn))) => {
+            out_vars.push(bn.to_string());
+        }
+        StottrTerm::ConstantTerm(_) => {}
+        StottrTerm::List(l) => {
+            for t in l {
+                add_term_variable_names(t, out_vars);
+            }
+        }
+    }
+}
+
+fn get_blank_nodes(instances: &Vec<Instance>) -> Vec<&BlankNode> {
+    let mut blank_nodes = vec![];
+    for i in instances {
+        for a in &i.argument_list {
+            add_term_blank_nodes(&a.term, &mut blank_nodes);
+        }
+    }
+    blank_nodes
+}
+
+fn add_term_blank_nodes<'a>(term: &'a StottrTerm, blank_nodes: &mut Vec<&'a BlankNode>) {
+    match term {
+        StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::BlankNode(bn)))
+            if !blank_nodes.contains(&bn) =>
+        {
+            blank_nodes.push(bn);
+        }
+        StottrTerm::List(l) => {
+            for t in l {
+                add_term_blank_nodes(t, blank_nodes);
+            }
+        }
+        _ => {}
+    }
+}
+
+//Triples with nulls are skipped, the row indices of the rows they come from are returned
+fn create_triples(i: OTTRTripleInstance) -> Result<CreatedTriples, MappingError> {
     let OTTRTripleInstance {
         df,
         mut dynamic_columns,
@@ -384,22 +850,259 @@ fn create_triples(
             dynamic_columns.insert(k, mapped_column);
         }
     }
+    let df_has_row_index = df.get_column_names().contains(&ROW_INDEX_COLUMN);
     let mut lf = df.lazy();
     for e in expressions {
         lf = lf.with_column(e);
     }
 
+    let PrimitiveColumn {
+        rdf_node_type,
+        language_tag,
+        language_tag_from_column,
+        datatype_from_column,
+    } = dynamic_columns.remove("object").unwrap();
+    let language_tag_column = language_tag_column_name("object");
+    let datatype_column = datatype_column_name("object");
+
     let mut keep_cols = vec![col("subject"), col("object")];
     if verb.is_none() {
         keep_cols.push(col("verb"));
     }
+    if language_tag_from_column {
+        keep_cols.push(col(&language_tag_column));
+    }
+    if datatype_from_column {
+        keep_cols.push(col(&datatype_column));
+    }
+    let has_row_index = df_has_row_index;
+    if has_row_index {
+        keep_cols.push(col(ROW_INDEX_COLUMN));
+    }
     lf = lf.select(keep_cols.as_slice());
-    let df = lf.collect().expect("Collect problem");
-    let PrimitiveColumn {
-        rdf_node_type,
-        language_tag,
-    } = dynamic_columns.remove("object").unwrap();
-    Ok((df, rdf_node_type, language_tag, verb, has_unique_subset))
+    let mut df = lf.collect().expect("Collect problem");
+    let mut dropped_rows = None;
+    if has_row_index {
+        let mut has_null =
+            df.column("subject").unwrap().is_null() | df.column("object").unwrap().is_null();
+        if verb.is_none() {
+            has_null = has_null | df.column("verb").unwrap().is_null();
+        }
+        if has_null.any() {
+            dropped_rows = Some(
+                df.column(ROW_INDEX_COLUMN)
+                    .unwrap()
+                    .filter(&has_null)
+                    .unwrap(),
+            );
+            df = df.filter(&!has_null).unwrap();
+        }
+        df = df.drop(ROW_INDEX_COLUMN).unwrap();
+    }
+
+    //Lists that are not expanded become RDF collections
+    let mut triple_dfs = vec![];
+    if let DataType::List(_) = df.column("object").unwrap().dtype() {
+        add_collection_triples(df, &rdf_node_type, verb, &mut triple_dfs);
+    } else {
+        triple_dfs.push((df, rdf_node_type, verb));
+    }
+    let mut triples_to_add = vec![];
+    let mut datatype_column_predicates = HashSet::new();
+    for (df, rdf_node_type, verb) in triple_dfs {
+        let column_names = df.get_column_names();
+        let has_language_tag_column = column_names.contains(&language_tag_column.as_str());
+        let dfs = if column_names.contains(&datatype_column.as_str()) {
+            if let Some(verb) = &verb {
+                datatype_column_predicates.insert(verb.clone());
+            } else {
+                let verbs = df.column("verb").unwrap().utf8().unwrap();
+                datatype_column_predicates
+                    .extend(verbs.into_iter().flatten().map(|x| x.to_string()));
+            }
+            split_by_datatype(df, &datatype_column, &rdf_node_type)?
+        } else {
+            vec![(df, rdf_node_type)]
+        };
+        triples_to_add.extend(dfs.into_iter().map(|(df, object_type)| TriplesToAdd {
+            df,
+            object_type,
+            language_tag: language_tag.clone(),
+            language_tag_column: if has_language_tag_column {
+                Some(language_tag_column.clone())
+            } else {
+                None
+            },
+            static_verb_column: verb.clone(),
+            has_unique_subset,
+            graph: None,
+        }));
+    }
+    Ok((triples_to_add, dropped_rows, datatype_column_predicates))
+}
+
+fn count_rows(row_indices: Vec<Series>) -> usize {
+    let mut row_indices = row_indices.into_iter();
+    if let Some(mut first) = row_indices.next() {
+        for s in row_indices {
+            first.append(&s).unwrap();
+        }
+        first.n_unique().unwrap()
+    } else {
+        0
+    }
+}
+
+//Each list becomes a chain of fresh blank nodes linked by rdf:rest and ending in rdf:nil, with the
+//elements given by rdf:first. The object of the original triple is the first blank node.
+fn add_collection_triples(
+    df: DataFrame,
+    element_rdf_node_type: &RDFNodeType,
+    verb: Option<String>,
+    out: &mut Vec<(DataFrame, RDFNodeType, Option<String>)>,
+) {
+    let list_uuid = Uuid::new_v4().simple().to_string();
+    let node_name =
+        |row: usize, position: u32| format!("_:list_{}_{}_{}", list_uuid, row, position);
+    let lengths: Vec<Option<u32>> = df
+        .column("object")
+        .unwrap()
+        .list()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.map(|l| l.len() as u32))
+        .collect();
+
+    let heads: Vec<Option<String>> = lengths
+        .iter()
+        .enumerate()
+        .map(|(row, length)| match length {
+            Some(0) => Some(rdf::NIL.as_str().to_string()),
+            Some(_) => Some(node_name(row, 0)),
+            None => None,
+        })
+        .collect();
+    let is_empty: BooleanChunked = lengths.iter().map(|x| x == &Some(0)).collect();
+    let mut head_cols = vec!["subject"];
+    if verb.is_none() {
+        head_cols.push("verb");
+    }
+    let mut head_df = df.select(head_cols).unwrap();
+    head_df.with_column(Series::new("object", heads)).unwrap();
+    out.push((
+        head_df.filter(&!&is_empty).unwrap(),
+        RDFNodeType::BlankNode,
+        verb.clone(),
+    ));
+    out.push((head_df.filter(&is_empty).unwrap(), RDFNodeType::IRI, verb));
+
+    let non_empty_rows: Vec<usize> = lengths
+        .iter()
+        .enumerate()
+        .filter(|(_, length)| length.unwrap_or(0) > 0)
+        .map(|(row, _)| row)
+        .collect();
+    let is_non_empty: BooleanChunked = lengths.iter().map(|x| x.unwrap_or(0) > 0).collect();
+    let mut elements_df = df.drop("subject").unwrap().filter(&is_non_empty).unwrap();
+    if elements_df.get_column_names().contains(&"verb") {
+        elements_df = elements_df.drop("verb").unwrap();
+    }
+    let mut elements_df = elements_df.explode(["object"]).unwrap();
+    let mut nodes = vec![];
+    let mut rest_subjects = vec![];
+    let mut rest_objects = vec![];
+    let mut last_nodes = vec![];
+    for row in non_empty_rows {
+        let length = lengths[row].unwrap();
+        for position in 0..length {
+            nodes.push(node_name(row, position));
+            if position + 1 < length {
+                rest_subjects.push(node_name(row, position));
+                rest_objects.push(node_name(row, position + 1));
+            } else {
+                last_nodes.push(node_name(row, position));
+            }
+        }
+    }
+    let n_last_nodes = last_nodes.len();
+    elements_df
+        .with_column(Series::new("subject", nodes))
+        .unwrap();
+    if let DataType::List(_) = elements_df.column("object").unwrap().dtype() {
+        add_collection_triples(
+            elements_df,
+            element_rdf_node_type,
+            Some(rdf::FIRST.as_str().to_string()),
+            out,
+        );
+    } else {
+        out.push((
+            elements_df,
+            element_rdf_node_type.clone(),
+            Some(rdf::FIRST.as_str().to_string()),
+        ));
+    }
+    out.push((
+        DataFrame::new(vec![
+            Series::new("subject", rest_subjects),
+            Series::new("object", rest_objects),
+        ])
+        .unwrap(),
+        RDFNodeType::BlankNode,
+        Some(rdf::REST.as_str().to_string()),
+    ));
+    out.push((
+        DataFrame::new(vec![
+            Series::new("subject", last_nodes),
+            Series::new_empty("object", &DataType::Utf8)
+                .extend_constant(AnyValue::Utf8(rdf::NIL.as_str()), n_last_nodes)
+                .unwrap(),
+        ])
+        .unwrap(),
+        RDFNodeType::IRI,
+        Some(rdf::REST.as_str().to_string()),
+    ));
+}
+
+//Rows without a datatype keep the datatype of the column
+fn split_by_datatype(
+    df: DataFrame,
+    datatype_column: &str,
+    default_rdf_node_type: &RDFNodeType,
+) -> Result<Vec<(DataFrame, RDFNodeType)>, MappingError> {
+    let mut out = vec![];
+    for part in df.partition_by([datatype_column], true).unwrap() {
+        let rdf_node_type = match part.column(datatype_column).unwrap().get(0) {
+            Ok(AnyValue::Utf8(dt)) => {
+                let nn = NamedNode::new(dt)
+                    .map_err(|x| MappingError::InvalidDatatypeIRI(dt.to_string(), x))?;
+                if nn.as_ref() == xsd::ANY_URI {
+                    RDFNodeType::IRI
+                } else {
+                    RDFNodeType::Literal(nn)
+                }
+            }
+            _ => default_rdf_node_type.clone(),
+        };
+        let mut part = part.drop(datatype_column).unwrap();
+        if let RDFNodeType::Literal(nn) = &rdf_node_type {
+            if let Some(target_datatype) = xsd_datatype_to_polars_datatype(nn.as_str()) {
+                let object = part.column("object").unwrap();
+                if object.dtype() != &target_datatype {
+                    let cast = coerce_series(object, &target_datatype).map_err(|x| {
+                        MappingError::CouldNotCastToDatatype(
+                            "object".to_string(),
+                            nn.as_str().to_string(),
+                            x,
+                        )
+                    })?;
+                    part.with_column(cast).unwrap();
+                }
+            }
+        }
+        out.push((part, rdf_node_type));
+    }
+    Ok(out)
 }
 
 fn create_dynamic_expression_from_static(
@@ -411,11 +1114,197 @@ fn create_dynamic_expression_from_static(
     let mapped_column = PrimitiveColumn {
         rdf_node_type,
         language_tag,
+        language_tag_from_column: false,
+        datatype_from_column: false,
     };
     expr = expr.alias(column_name);
     Ok((expr, mapped_column))
 }
 
+pub(crate) fn language_tag_column_name(column_name: &str) -> String {
+    format!("{}{}", column_name, LANGUAGE_TAG_COLUMN_SUFFIX)
+}
+
+pub(crate) fn datatype_column_name(column_name: &str) -> String {
+    format!("{}{}", column_name, DATATYPE_COLUMN_SUFFIX)
+}
+
+//Companion columns follow the column they belong to when it is renamed
+fn add_companion_renames(
+    rename_map: &mut HashMap<String, Vec<String>>,
+    primitive_column: &PrimitiveColumn,
+    existing: &str,
+    target: &str,
+    companion_columns: &mut Vec<String>,
+) {
+    let mut companions = vec![];
+    if primitive_column.language_tag_from_column {
+        companions.push((
+            language_tag_column_name(existing),
+            language_tag_column_name(target),
+        ));
+    }
+    if primitive_column.datatype_from_column {
+        companions.push((datatype_column_name(existing), datatype_column_name(target)));
+    }
+    for (existing_companion, target_companion) in companions {
+        add_rename(rename_map, &existing_companion, &target_companion);
+        companion_columns.push(target_companion);
+    }
+}
+
+fn add_rename(rename_map: &mut HashMap<String, Vec<String>>, existing: &str, target: &str) {
+    if let Some(target_names) = rename_map.get_mut(existing) {
+        target_names.push(target.to_string())
+    } else {
+        rename_map.insert(existing.to_string(), vec![target.to_string()]);
+    }
+}
+
+//Lists of terms are built per row, the elements are passed as columns with temporary names
+fn create_list_expression(
+    target_colname: &str,
+    terms: &[StottrTerm],
+    dynamic_columns: &HashMap<String, PrimitiveColumn>,
+    constant_columns: &HashMap<String, StaticColumn>,
+    rename_map: &mut HashMap<String, Vec<String>>,
+) -> Result<(Expr, PrimitiveColumn), MappingError> {
+    let mut element_expressions = vec![];
+    let mut list_column: Option<PrimitiveColumn> = None;
+    for (j, t) in terms.iter().enumerate() {
+        let element_colname = format!("{}_element_{}", target_colname, j);
+        let dynamic_name = match t {
+            StottrTerm::Variable(v) => Some(v.name.clone()),
+            StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::BlankNode(bn))) => {
+                Some(bn.to_string())
+            }
+            _ => None,
+        };
+        let (expr, element_column) = if let Some(c) = dynamic_name
+            .as_ref()
+            .and_then(|name| dynamic_columns.get(name))
+        {
+            add_rename(rename_map, dynamic_name.as_ref().unwrap(), &element_colname);
+            //Language tags and datatypes per row are not supported for list elements
+            let mut element_column = c.clone();
+            add_companion_renames(
+                rename_map,
+                &element_column,
+                dynamic_name.as_ref().unwrap(),
+                &element_colname,
+                &mut vec![],
+            );
+            element_column.language_tag_from_column = false;
+            element_column.datatype_from_column = false;
+            (col(&element_colname), element_column)
+        } else if let StottrTerm::Variable(v) = t {
+            if let Some(sc) = constant_columns.get(&v.name) {
+                create_dynamic_expression_from_static(&element_colname, &sc.constant_term, &None)?
+            } else {
+                return Err(MappingError::UnknownVariableError(v.name.clone()));
+            }
+        } else if let StottrTerm::ConstantTerm(ct @ ConstantTerm::Constant(_)) = t {
+            create_dynamic_expression_from_static(&element_colname, ct, &None)?
+        } else {
+            return Err(MappingError::NestedListTermNotSupported(
+                target_colname.to_string(),
+            ));
+        };
+        if let Some(lc) = &list_column {
+            if lc.rdf_node_type != element_column.rdf_node_type {
+                return Err(MappingError::ListTermHasInconsistentTypes(
+                    target_colname.to_string(),
+                    lc.rdf_node_type.clone(),
+                    element_column.rdf_node_type,
+                ));
+            }
+        } else {
+            list_column = Some(element_column);
+        }
+        element_expressions.push(expr);
+    }
+    if let Some(list_column) = list_column {
+        let expr = concat_list(element_expressions)
+            .unwrap()
+            .alias(target_colname);
+        Ok((expr, list_column))
+    } else {
+        Err(MappingError::EmptyListTerm(target_colname.to_string()))
+    }
+}
+
+//Lists in a row may have different lengths. Each list is exploded separately and the elements are
+//matched on position, keeping the positions of the shortest (zipMin) or longest (zipMax) list.
+//With zipMax, the missing elements of the shorter lists are none.
+fn zip_explode(lf: LazyFrame, to_expand: &Vec<String>, pad: bool) -> LazyFrame {
+    if to_expand.is_empty() {
+        return lf;
+    }
+    let lf = lf.with_row_count(ZIP_ROW_INDEX_COLUMN, None);
+    let mut exploded = vec![];
+    for c in to_expand {
+        let exploded_lf = lf
+            .clone()
+            .select([col(ZIP_ROW_INDEX_COLUMN), col(c)])
+            .with_column(col(c).list().lengths().alias(ZIP_LIST_LENGTH_COLUMN))
+            .explode([col(c)])
+            .with_column(
+                col(ZIP_ROW_INDEX_COLUMN)
+                    .cumcount(false)
+                    .over([col(ZIP_ROW_INDEX_COLUMN)])
+                    .alias(ZIP_POSITION_COLUMN),
+            )
+            //Empty lists are exploded to a single null, these are removed here
+            .filter(col(ZIP_POSITION_COLUMN).lt(col(ZIP_LIST_LENGTH_COLUMN)))
+            .drop_columns([ZIP_LIST_LENGTH_COLUMN]);
+        exploded.push(exploded_lf);
+    }
+    let on = [col(ZIP_ROW_INDEX_COLUMN), col(ZIP_POSITION_COLUMN)];
+    let mut zipped = if pad {
+        let positions: Vec<LazyFrame> = exploded
+            .iter()
+            .map(|x| x.clone().select(on.clone()))
+            .collect();
+        concat(positions, UnionArgs::default())
+            .unwrap()
+            .unique(None, UniqueKeepStrategy::First)
+    } else {
+        exploded.remove(0)
+    };
+    let how = if pad { JoinType::Left } else { JoinType::Inner };
+    for e in exploded {
+        zipped = zipped.join(e, on.clone(), on.clone(), how.clone().into());
+    }
+    let rest = lf.drop_columns(to_expand);
+    zipped
+        .join(
+            rest,
+            [col(ZIP_ROW_INDEX_COLUMN)],
+            [col(ZIP_ROW_INDEX_COLUMN)],
+            JoinType::Inner.into(),
+        )
+        .sort_by_exprs(on, [false, false], false, false)
+        .drop_columns([ZIP_ROW_INDEX_COLUMN, ZIP_POSITION_COLUMN])
+}
+
+fn has_none_for_non_optional(
+    signature: &Signature,
+    static_columns: &HashMap<String, StaticColumn>,
+) -> bool {
+    for p in &signature.parameter_list {
+        if !p.optional {
+            if let Some(StaticColumn {
+                constant_term: ConstantTerm::Constant(ConstantLiteral::None),
+                ..
+            }) = static_columns.get(&p.stottr_variable.name)
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
 fn create_remapped(
     instance: &Instance,
     signature: &Signature,
@@ -429,6 +1318,7 @@ fn create_remapped(
         HashMap<String, PrimitiveColumn>,
         HashMap<String, StaticColumn>,
         Vec<Vec<String>>,
+        Vec<Series>,
     ),
     MappingError,
 > {
@@ -441,7 +1331,8 @@ fn create_remapped(
     let mut expressions = vec![];
     let mut existing = vec![];
     let mut new = vec![];
-    let mut rename_map: HashMap<&String, Vec<&String>> = HashMap::new();
+    let mut rename_map: HashMap<String, Vec<String>> = HashMap::new();
+    let mut companion_columns = vec![];
 
     for (original, target) in instance
         .argument_list
@@ -455,13 +1346,15 @@ fn create_remapped(
         match &original.term {
             StottrTerm::Variable(v) => {
                 if let Some(c) = dynamic_columns.get(&v.name) {
-                    if let Some(target_names) = rename_map.get_mut(&&v.name) {
-                        target_names.push(target_colname)
-                    } else {
-                        rename_map.insert(&v.name, vec![target_colname]);
-                    }
-
-                    existing.push(&v.name);
+                    add_rename(&mut rename_map, &v.name, target_colname);
+                    add_companion_renames(
+                        &mut rename_map,
+                        c,
+                        &v.name,
+                        target_colname,
+                        &mut companion_columns,
+                    );
+                    existing.push(v.name.clone());
                     new.push(target_colname);
                     new_dynamic_columns.insert(target_colname.clone(), c.clone());
                 } else if let Some(c) = constant_columns.get(&v.name) {
@@ -470,6 +1363,17 @@ fn create_remapped(
                     return Err(MappingError::UnknownVariableError(v.name.clone()));
                 }
             }
+            StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::BlankNode(bn)))
+                if dynamic_columns.contains_key(&bn.to_string()) =>
+            {
+                //Blank nodes are minted per row in advance, and are passed on like variables
+                let bn_colname = bn.to_string();
+                let c = dynamic_columns.get(&bn_colname).unwrap();
+                add_rename(&mut rename_map, &bn_colname, target_colname);
+                existing.push(bn_colname);
+                new.push(target_colname);
+                new_dynamic_columns.insert(target_colname.clone(), c.clone());
+            }
             StottrTerm::ConstantTerm(ct) => {
                 if original.list_expand {
                     let (expr, primitive_column) =
@@ -485,26 +1389,43 @@ fn create_remapped(
                     new_constant_columns.insert(target_colname.clone(), static_column);
                 }
             }
-            StottrTerm::List(_) => {
-                todo!()
+            StottrTerm::List(terms) => {
+                let (expr, primitive_column) = create_list_expression(
+                    target_colname,
+                    terms,
+                    dynamic_columns,
+                    constant_columns,
+                    &mut rename_map,
+                )?;
+                expressions.push(expr);
+                new_dynamic_columns.insert(target_colname.clone(), primitive_column);
+                new_dynamic_from_constant.push(target_colname);
             }
         }
     }
 
+    let has_row_index = series_vec.iter().any(|s| s.name() == ROW_INDEX_COLUMN);
     for s in &mut series_vec {
         let sname = s.name().to_string();
-        s.rename(rename_map.get_mut(&sname).unwrap().pop().unwrap());
+        if sname != ROW_INDEX_COLUMN {
+            s.rename(&rename_map.get_mut(&sname).unwrap().pop().unwrap());
+        }
     }
     let mut lf = DataFrame::new(series_vec).unwrap().lazy();
 
     for expr in expressions {
         lf = lf.with_column(expr);
     }
-    let new_column_expressions: Vec<Expr> = new
+    let mut new_column_expressions: Vec<Expr> = new
         .iter()
         .chain(new_dynamic_from_constant.iter())
         .map(|x| col(x))
+        .chain(companion_columns.iter().map(|x| col(x)))
         .collect();
+    //Instances with only constant arguments are expanded once, and do not need the row index
+    if has_row_index && !new_column_expressions.is_empty() {
+        new_column_expressions.push(col(ROW_INDEX_COLUMN));
+    }
     lf = lf.select(new_column_expressions.as_slice());
 
     let mut new_unique_subsets = vec![];
@@ -517,21 +1438,20 @@ fn create_remapped(
                 }
             }
             ListExpanderType::ZipMin => {
-                lf = lf.explode(to_expand_cols.clone());
-                lf = lf.drop_nulls(Some(to_expand_cols));
+                lf = zip_explode(lf, &to_expand, false);
             }
             ListExpanderType::ZipMax => {
-                lf = lf.explode(to_expand_cols);
+                lf = zip_explode(lf, &to_expand, true);
             }
         }
         //Todo: List expanders for constant terms..
     } else {
         for unique_subset in unique_subsets {
-            if unique_subset.iter().all(|x| existing.contains(&x)) {
+            if unique_subset.iter().all(|x| existing.contains(x)) {
                 let mut new_subset = vec![];
                 for x in unique_subset.iter() {
                     new_subset.push(
-                        new.get(existing.iter().position(|e| e == &x).unwrap())
+                        new.get(existing.iter().position(|e| e == x).unwrap())
                             .unwrap()
                             .to_string(),
                     );
@@ -540,15 +1460,63 @@ fn create_remapped(
             }
         }
     }
+    let mut df = lf.collect().unwrap();
+    //Instances with none for a non-optional parameter are ignored
+    let mut non_optional_filter = None;
+    for p in &signature.parameter_list {
+        if !p.optional && new_dynamic_columns.contains_key(&p.stottr_variable.name) {
+            let is_not_null = col(&p.stottr_variable.name).is_not_null();
+            non_optional_filter = Some(if let Some(f) = non_optional_filter {
+                is_not_null.and(f)
+            } else {
+                is_not_null
+            });
+        }
+    }
+    let mut dropped_rows = vec![];
+    if let Some(f) = non_optional_filter {
+        let filtered = df.clone().lazy().filter(f.clone()).collect().unwrap();
+        if filtered.height() < df.height() && df.get_column_names().contains(&ROW_INDEX_COLUMN) {
+            let dropped = df
+                .lazy()
+                .filter(f.not())
+                .select([col(ROW_INDEX_COLUMN)])
+                .collect()
+                .unwrap();
+            dropped_rows.push(dropped.column(ROW_INDEX_COLUMN).unwrap().clone());
+        }
+        df = filtered;
+    }
+    //Lists passed on to non-empty list parameters of nested templates must also be non-empty
+    for p in &signature.parameter_list {
+        if let Some(PType::NEListType(_)) = &p.ptype {
+            let name = &p.stottr_variable.name;
+            if new_dynamic_columns.contains_key(name) {
+                validate_non_empty_list_parameter(&df, name)?;
+            } else if let Some(StaticColumn {
+                constant_term: ConstantTerm::ConstantList(l),
+                ..
+            }) = new_constant_columns.get(name)
+            {
+                if l.is_empty() {
+                    return Err(MappingError::NonEmptyListColumnHasEmptyList(
+                        name.to_string(),
+                        df.head(Some(1)),
+                    ));
+                }
+            }
+        }
+    }
     debug!(
         "Creating remapped took {} seconds",
         now.elapsed().as_secs_f32()
     );
     Ok((
-        lf.collect().unwrap(),
+        df,
         new_dynamic_columns,
         new_constant_columns,
         new_unique_subsets,
+        dropped_rows,
     ))
 }
 
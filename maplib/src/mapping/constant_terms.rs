@@ -5,8 +5,7 @@ use crate::mapping::RDFNodeType;
 use oxrdf::vocab::xsd;
 use oxrdf::NamedNode;
 use polars::prelude::{concat_list, lit, Expr, LiteralValue, SpecialEq};
-use polars_core::datatypes::DataType;
-use polars_core::prelude::{AnyValue, IntoSeries, ListChunked, Series};
+use polars_core::prelude::{IntoSeries, ListChunked, Series};
 use representation::literals::sparql_literal_to_any_value;
 use std::ops::Deref;
 
@@ -23,7 +22,7 @@ pub fn constant_to_expr(
                 None,
             ),
             ConstantLiteral::BlankNode(bn) => (
-                Expr::Literal(LiteralValue::Utf8(bn.as_str().to_string())),
+                Expr::Literal(LiteralValue::Utf8(bn.to_string())),
                 PType::BasicType(
                     NamedNode::new_unchecked(BLANK_NODE_IRI),
                     BLANK_NODE_IRI.to_string(),
@@ -32,15 +31,9 @@ pub fn constant_to_expr(
                 None,
             ),
             ConstantLiteral::Literal(lit) => {
-                let (mut any, dt) = sparql_literal_to_any_value(&lit.value, &lit.data_type_iri);
-                let mut value_series = Series::new_empty("literal", &DataType::Utf8);
-                //Workaround for owned utf 8..
-                if let AnyValue::Utf8Owned(s) = any {
-                    any = AnyValue::Utf8(&s);
-                    value_series = value_series.extend_constant(any, 1).unwrap();
-                } else {
-                    value_series = value_series.extend_constant(any, 1).unwrap();
-                }
+                let (any, dt) = sparql_literal_to_any_value(&lit.value, &lit.data_type_iri)
+                    .map_err(|x| MappingError::InvalidLiteral(x))?;
+                let value_series = Series::from_any_values("literal", &[any], false).unwrap();
                 let language_tag = if let Some(tag) = &lit.language {
                     Some(tag.clone())
                 } else {
@@ -67,9 +60,15 @@ pub fn constant_to_expr(
             let mut expressions = vec![];
             let mut last_ptype = None;
             let mut last_rdf_node_type = None;
+            let inner_ptype_opt = match ptype_opt {
+                Some(PType::ListType(inner_ptype)) | Some(PType::NEListType(inner_ptype)) => {
+                    Some(inner_ptype.as_ref().clone())
+                }
+                _ => None,
+            };
             for ct in inner {
                 let (constant_expr, actual_ptype, rdf_node_type, language_tag) =
-                    constant_to_expr(ct, ptype_opt)?;
+                    constant_to_expr(ct, &inner_ptype_opt)?;
                 if language_tag.is_some() {
                     todo!()
                 }
@@ -118,7 +117,21 @@ pub fn constant_to_expr(
         }
     };
     if let Some(ptype_in) = ptype_opt {
-        if ptype_in != &ptype {
+        let matches = match constant_term {
+            ConstantTerm::Constant(ConstantLiteral::None) => true,
+            ConstantTerm::Constant(ConstantLiteral::BlankNode(_)) => {
+                PType::BasicType(xsd::ANY_URI.into_owned(), "xsd:anyURI".to_string())
+                    .is_subtype_of(ptype_in)
+            }
+            //Constants have the least upper bound of their datatype as their type
+            ConstantTerm::Constant(_) => {
+                PType::LUBType(Box::new(ptype.clone())).is_subtype_of(ptype_in)
+            }
+            ConstantTerm::ConstantList(_) => {
+                matches!(ptype_in, PType::ListType(_) | PType::NEListType(_))
+            }
+        };
+        if !matches {
             return Err(MappingError::ConstantDoesNotMatchDataType(
                 constant_term.clone(),
                 ptype_in.clone(),
@@ -1,7 +1,7 @@
 use super::Mapping;
 use crate::ast::{
     Argument, ConstantLiteral, ConstantTerm, Instance, ListExpanderType, PType, Parameter,
-    Signature, StottrTerm, StottrVariable, Template,
+    Signature, Statement, StottrDocument, StottrTerm, StottrVariable, Template,
 };
 use crate::constants::{DEFAULT_PREDICATE_URI_PREFIX, DEFAULT_TEMPLATE_PREFIX, OTTR_TRIPLE};
 use crate::mapping::errors::MappingError;
@@ -11,10 +11,35 @@ use oxrdf::vocab::xsd;
 use oxrdf::NamedNode;
 use polars::prelude::{col, IntoLazy};
 use polars_core::frame::DataFrame;
-use polars_core::prelude::DataType;
+use polars_core::prelude::{DataType, IntoSeries, Series, Utf8Chunked};
+use representation::RDFNodeType;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
+//Where the fields of a struct are found, as the path of fields from a column to a struct, or to a
+//list of structs and then the path within its elements
+#[derive(Clone)]
+enum StructContext {
+    Row(Vec<String>),
+    List(Vec<String>, Vec<String>),
+}
+
+//Builds a sub-template per struct, the leaf fields of the structs become new columns
+struct StructTemplateBuilder<'a> {
+    template_prefix: &'a str,
+    predicate_prefix: &'a str,
+    minted: HashSet<String>,
+    //The columns of the data frame and the leaf columns so far, new leaf columns may not reuse them
+    columns: HashSet<String>,
+    templates: Vec<Template>,
+    leaf_columns: Vec<(String, StructContext)>,
+    //The columns with the nodes of the structs, and whether they are minted
+    node_columns: Vec<(String, StructContext, bool)>,
+}
+
 impl Mapping {
+    //Struct columns become sub-templates linked to the primary key, the full template set is returned
+    //with the template for the data frame first
     pub fn expand_default(
         &mut self,
         mut df: DataFrame,
@@ -22,77 +47,96 @@ impl Mapping {
         fk_cols: Vec<String>,
         template_prefix: Option<String>,
         predicate_prefix_uri: Option<String>,
-        options: ExpandOptions,
-    ) -> Result<Template, MappingError> {
+        mut options: ExpandOptions,
+    ) -> Result<Vec<Template>, MappingError> {
         let use_template_prefix = template_prefix.unwrap_or(DEFAULT_TEMPLATE_PREFIX.to_string());
         let use_predicate_uri_prefix =
             predicate_prefix_uri.unwrap_or(DEFAULT_PREDICATE_URI_PREFIX.to_string());
-        let mut params = vec![];
         let columns: Vec<String> = df
             .get_column_names()
             .iter()
             .map(|x| x.to_string())
             .collect();
+        let mut builder = StructTemplateBuilder {
+            template_prefix: &use_template_prefix,
+            predicate_prefix: &use_predicate_uri_prefix,
+            minted: options
+                .minting
+                .as_ref()
+                .map(|m| m.keys().cloned().collect())
+                .unwrap_or_default(),
+            columns: columns.iter().cloned().collect(),
+            templates: vec![],
+            leaf_columns: vec![],
+            node_columns: vec![],
+        };
+        let mut params = vec![];
+        let mut patterns = vec![];
+        let mut struct_columns = vec![];
         for c in &columns {
             let dt = df.column(&c).unwrap().dtype().clone();
             let has_null = df.column(c).unwrap().is_null().any();
-            if c == &pk_col {
-                if let DataType::List(..) = dt {
-                    todo!()
-                }
-                if dt != DataType::Utf8 {
-                    warn!(
-                        "Primary key column {} is not Utf8 but instead {}. Will be cast",
-                        &pk_col, dt
-                    );
-                    df = df
-                        .lazy()
-                        .with_column(col(&c).cast(DataType::Utf8))
-                        .collect()
-                        .unwrap();
-                }
-
-                params.push(Parameter {
-                    optional: has_null,
-                    non_blank: false,
-                    ptype: Some(PType::BasicType(
-                        xsd::ANY_URI.into_owned(),
-                        "xsd:anyURI".to_string(),
-                    )),
-                    stottr_variable: StottrVariable {
-                        name: c.to_string(),
-                    },
-                    default_value: None,
-                })
-            } else if fk_cols.contains(&c) {
-                if let DataType::List(..) = dt {
-                    todo!()
+            if c == &pk_col || fk_cols.contains(&c) {
+                let is_list = if let DataType::List(inner) = &dt {
+                    if c == &pk_col || is_struct(inner) {
+                        return Err(MappingError::UnsupportedDefaultColumn(c.clone(), dt));
+                    }
+                    true
+                } else {
+                    false
+                };
+                if let DataType::Struct(..) = dt {
+                    return Err(MappingError::UnsupportedDefaultColumn(c.clone(), dt));
                 }
-
-                if dt != DataType::Utf8 {
+                let cast_to = if is_list {
+                    DataType::List(Box::new(DataType::Utf8))
+                } else {
+                    DataType::Utf8
+                };
+                if dt != cast_to {
+                    let kind = if c == &pk_col { "Primary" } else { "Foreign" };
                     warn!(
-                        "Foreign key column {} is not Utf8 but instead {}. Will be cast",
-                        &c, dt
+                        "{} key column {} is not {} but instead {}. Will be cast",
+                        kind, &c, cast_to, dt
                     );
                     df = df
                         .lazy()
-                        .with_column(col(&c).cast(DataType::Utf8))
+                        .with_column(col(&c).cast(cast_to))
                         .collect()
                         .unwrap();
                 }
-
+                let iri_type =
+                    PType::BasicType(xsd::ANY_URI.into_owned(), "xsd:anyURI".to_string());
                 params.push(Parameter {
                     optional: has_null,
                     non_blank: false,
-                    ptype: Some(PType::BasicType(
-                        xsd::ANY_URI.into_owned(),
-                        "xsd:anyURI".to_string(),
-                    )),
+                    ptype: Some(if is_list {
+                        PType::ListType(Box::new(iri_type))
+                    } else {
+                        iri_type
+                    }),
                     stottr_variable: StottrVariable {
                         name: c.to_string(),
                     },
                     default_value: None,
                 })
+            } else if is_struct(&dt) {
+                let context = if let DataType::List(..) = dt {
+                    StructContext::List(vec![c.clone()], vec![])
+                } else {
+                    StructContext::Row(vec![c.clone()])
+                };
+                let (signature, struct_params) = builder.add_struct(c, c, &dt, context)?;
+                patterns.push(struct_instance(
+                    &signature,
+                    StottrTerm::Variable(StottrVariable {
+                        name: pk_col.clone(),
+                    }),
+                    &struct_params,
+                    matches!(dt, DataType::List(..)),
+                ));
+                params.extend(struct_params);
+                struct_columns.push(c.clone());
             } else {
                 params.push(Parameter {
                     optional: has_null,
@@ -106,57 +150,385 @@ impl Mapping {
             }
         }
 
-        let mut patterns = vec![];
-        for c in columns {
-            if c != pk_col {
-                let list_expander = if let DataType::List(..) = df.column(&c).unwrap().dtype() {
-                    Some(ListExpanderType::Cross)
+        for c in &columns {
+            if c != &pk_col && !struct_columns.contains(c) {
+                patterns.push(triple_instance(
+                    StottrTerm::Variable(StottrVariable {
+                        name: pk_col.clone(),
+                    }),
+                    &use_predicate_uri_prefix,
+                    c,
+                    StottrTerm::Variable(StottrVariable { name: c.clone() }),
+                    matches!(df.column(c).unwrap().dtype(), DataType::List(..)),
+                ));
+            }
+        }
+        if !struct_columns.is_empty() {
+            for (leaf_name, context) in builder.leaf_columns {
+                let mut leaf = leaf_series(&df, &context);
+                leaf.rename(&leaf_name);
+                df.with_column(leaf).unwrap();
+            }
+            let structs: Vec<_> = builder
+                .node_columns
+                .iter()
+                .map(|(_, context, _)| leaf_series(&df, context))
+                .collect();
+            df = df.drop_many(&struct_columns);
+            //The minted nodes are minted here, so that they can be left out for null structs
+            let mut struct_minting = HashMap::new();
+            let mut rdf_node_types = options.rdf_node_types.take().unwrap_or_default();
+            for (name, _, minted) in &builder.node_columns {
+                if *minted {
+                    let minting = options.minting.as_mut().unwrap();
+                    struct_minting.insert(name.clone(), minting.remove(name).unwrap());
+                } else {
+                    rdf_node_types.insert(name.clone(), RDFNodeType::BlankNode);
+                }
+            }
+            options.rdf_node_types = Some(rdf_node_types);
+            df = self.mint_iris(df, &struct_minting)?;
+            for ((name, _, minted), structs) in builder.node_columns.iter().zip(structs) {
+                let minted = if *minted {
+                    Some(df.column(name).unwrap().clone())
                 } else {
                     None
                 };
-
-                patterns.push(Instance {
-                    list_expander: list_expander.clone(),
-                    template_name: NamedNode::new(OTTR_TRIPLE).unwrap(),
-                    prefixed_template_name: "ottr:Triple".to_string(),
-                    argument_list: vec![
-                        Argument {
-                            list_expand: false,
-                            term: StottrTerm::Variable(StottrVariable {
-                                name: pk_col.clone(),
-                            }),
-                        },
-                        Argument {
-                            list_expand: false,
-                            term: StottrTerm::ConstantTerm(ConstantTerm::Constant(
-                                ConstantLiteral::IRI(
-                                    NamedNode::new(format!("{}{}", &use_predicate_uri_prefix, c))
-                                        .unwrap(),
-                                ),
-                            )),
-                        },
-                        Argument {
-                            list_expand: list_expander.is_some(),
-                            term: StottrTerm::Variable(StottrVariable { name: c.clone() }),
-                        },
-                    ],
-                })
+                df.with_column(struct_nodes(name, &structs, minted.as_ref()))
+                    .unwrap();
             }
         }
 
-        let template_uuid = Uuid::new_v4().to_string();
-        let template_name = format!("{}{}", use_template_prefix, &template_uuid);
-        let template = Template {
-            signature: Signature {
-                template_name: NamedNode::new(template_name.clone()).unwrap(),
-                template_prefixed_name: format!("prefix:{}", template_uuid),
-                parameter_list: params,
-                annotation_list: None,
+        let template = default_template(&use_template_prefix, params, patterns);
+        let template_name = template.signature.template_name.as_str().to_string();
+        let mut templates = vec![template];
+        templates.extend(builder.templates);
+        //The templates are added together, as they call each other
+        self.template_dataset
+            .add_documents(vec![StottrDocument {
+                directives: vec![],
+                statements: templates
+                    .iter()
+                    .rev()
+                    .map(|t| Statement::Template(t.clone()))
+                    .collect(),
+                prefix_map: HashMap::new(),
+            }])
+            .map_err(MappingError::TemplateError)?;
+        if let Err(e) = self.expand(template_name.as_str(), df, options) {
+            self.template_dataset.templates.retain(|t| {
+                !templates
+                    .iter()
+                    .any(|x| x.signature.template_name == t.signature.template_name)
+            });
+            return Err(e);
+        }
+        Ok(templates)
+    }
+}
+
+impl StructTemplateBuilder<'_> {
+    //Creates the sub-template of a struct or list of structs, returning its signature and the parameters
+    //apart from the parent that the calling template should pass on
+    fn add_struct(
+        &mut self,
+        name: &str,
+        predicate: &str,
+        dt: &DataType,
+        context: StructContext,
+    ) -> Result<(Signature, Vec<Parameter>), MappingError> {
+        let fields = match dt {
+            DataType::Struct(fields) => fields,
+            DataType::List(inner) => match inner.as_ref() {
+                DataType::Struct(fields) => fields,
+                _ => panic!("Should only be called with structs"),
             },
-            pattern_list: patterns,
+            _ => panic!("Should only be called with structs"),
         };
-        self.template_dataset.templates.push(template.clone());
-        self.expand(template_name.as_str(), df, options)?;
-        Ok(template)
+        let in_list = matches!(context, StructContext::List(..));
+        let parent_name = format!("{}_parent", name);
+        let mut signature_params = vec![Parameter {
+            optional: false,
+            non_blank: false,
+            ptype: None,
+            stottr_variable: StottrVariable {
+                name: parent_name.clone(),
+            },
+            default_value: None,
+        }];
+        //Structs in lists have no row to mint an identifier from, so they always get blank nodes
+        let minted = !in_list && self.minted.contains(name);
+        //The node is null for null structs, so that the sub-template is not instantiated for them
+        let node_parameter = |optional| Parameter {
+            optional,
+            non_blank: false,
+            ptype: if minted {
+                Some(PType::BasicType(
+                    xsd::ANY_URI.into_owned(),
+                    "xsd:anyURI".to_string(),
+                ))
+            } else {
+                None
+            },
+            stottr_variable: StottrVariable {
+                name: name.to_string(),
+            },
+            default_value: None,
+        };
+        signature_params.push(node_parameter(false));
+        let mut params = vec![];
+        self.node_columns
+            .push((name.to_string(), context.clone(), minted));
+        let node = StottrTerm::Variable(StottrVariable {
+            name: name.to_string(),
+        });
+        let mut patterns = vec![triple_instance(
+            StottrTerm::Variable(StottrVariable { name: parent_name }),
+            self.predicate_prefix,
+            predicate,
+            node.clone(),
+            false,
+        )];
+
+        for f in fields {
+            let field_name = f.name.to_string();
+            let leaf_name = format!("{}_{}", name, field_name);
+            let field_context = match &context {
+                StructContext::Row(path) => {
+                    let mut path = path.clone();
+                    path.push(field_name.clone());
+                    StructContext::Row(path)
+                }
+                StructContext::List(path, element_path) => {
+                    let mut element_path = element_path.clone();
+                    element_path.push(field_name.clone());
+                    StructContext::List(path.clone(), element_path)
+                }
+            };
+            if !self.columns.insert(leaf_name.clone()) {
+                return Err(MappingError::DefaultColumnAlreadyPresent(leaf_name));
+            }
+            if is_struct(f.data_type()) {
+                let is_struct_list = matches!(f.data_type(), DataType::List(..));
+                let field_context = if is_struct_list {
+                    if let StructContext::Row(path) = field_context {
+                        StructContext::List(path, vec![])
+                    } else {
+                        //Would require expanding lists within lists
+                        return Err(MappingError::UnsupportedDefaultColumn(
+                            leaf_name,
+                            f.data_type().clone(),
+                        ));
+                    }
+                } else {
+                    field_context
+                };
+                let (signature, struct_params) =
+                    self.add_struct(&leaf_name, &field_name, f.data_type(), field_context)?;
+                patterns.push(struct_instance(
+                    &signature,
+                    node.clone(),
+                    &struct_params,
+                    is_struct_list,
+                ));
+                params.extend(struct_params);
+            } else {
+                self.leaf_columns.push((leaf_name.clone(), field_context));
+                patterns.push(triple_instance(
+                    node.clone(),
+                    self.predicate_prefix,
+                    &field_name,
+                    StottrTerm::Variable(StottrVariable {
+                        name: leaf_name.clone(),
+                    }),
+                    matches!(f.data_type(), DataType::List(..)),
+                ));
+                params.push(Parameter {
+                    optional: true,
+                    non_blank: false,
+                    ptype: None,
+                    stottr_variable: StottrVariable { name: leaf_name },
+                    default_value: None,
+                });
+            }
+        }
+        signature_params.extend(params.iter().cloned());
+        params.insert(0, node_parameter(true));
+        let template = default_template(self.template_prefix, signature_params, patterns);
+        let signature = template.signature.clone();
+        self.templates.push(template);
+        Ok((signature, params))
+    }
+}
+
+fn is_struct(dt: &DataType) -> bool {
+    match dt {
+        DataType::Struct(..) => true,
+        DataType::List(inner) => matches!(inner.as_ref(), DataType::Struct(..)),
+        _ => false,
+    }
+}
+
+fn leaf_series(df: &DataFrame, context: &StructContext) -> Series {
+    let (path, element_path) = match context {
+        StructContext::Row(path) => (path, None),
+        StructContext::List(path, element_path) => (path, Some(element_path)),
+    };
+    let mut series = df.column(&path[0]).unwrap().clone();
+    for f in &path[1..] {
+        series = series.struct_().unwrap().field_by_name(f).unwrap();
+    }
+    if let Some(element_path) = element_path {
+        series = series
+            .list()
+            .unwrap()
+            .apply_amortized(|s| {
+                let mut element = s.as_ref().clone();
+                for f in element_path {
+                    element = element.struct_().unwrap().field_by_name(f).unwrap();
+                }
+                element
+            })
+            .into_series();
+    }
+    series
+}
+
+//The nodes of the structs or lists of structs, null where a struct is null. Structs that are not
+//minted get blank nodes.
+fn struct_nodes(name: &str, structs: &Series, minted: Option<&Series>) -> Series {
+    let blank_node_prefix = format!("_:b{}", Uuid::new_v4().simple());
+    //Structs are null when all their fields are
+    let mut nodes = if let DataType::List(..) = structs.dtype() {
+        if structs.is_empty() {
+            Series::new_empty(name, &DataType::List(Box::new(DataType::Utf8)))
+        } else {
+            let mut i = 0;
+            structs
+                .list()
+                .unwrap()
+                .apply_amortized(|s| {
+                    let nodes: Utf8Chunked = s
+                        .as_ref()
+                        .is_null()
+                        .into_iter()
+                        .map(|is_null| {
+                            i += 1;
+                            if is_null == Some(false) {
+                                Some(format!("{}_{}", blank_node_prefix, i))
+                            } else {
+                                None
+                            }
+                        })
+                        .collect();
+                    nodes.into_series()
+                })
+                .into_series()
+        }
+    } else {
+        let is_null = structs.is_null();
+        let nodes: Utf8Chunked = if let Some(minted) = minted {
+            is_null
+                .into_iter()
+                .zip(minted.utf8().unwrap())
+                .map(|(is_null, iri)| if is_null == Some(false) { iri } else { None })
+                .collect()
+        } else {
+            is_null
+                .into_iter()
+                .enumerate()
+                .map(|(i, is_null)| {
+                    if is_null == Some(false) {
+                        Some(format!("{}_{}", blank_node_prefix, i))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        nodes.into_series()
+    };
+    nodes.rename(name);
+    nodes
+}
+
+fn triple_instance(
+    subject: StottrTerm,
+    predicate_prefix: &str,
+    predicate: &str,
+    object: StottrTerm,
+    cross: bool,
+) -> Instance {
+    Instance {
+        list_expander: if cross {
+            Some(ListExpanderType::Cross)
+        } else {
+            None
+        },
+        template_name: NamedNode::new(OTTR_TRIPLE).unwrap(),
+        prefixed_template_name: "ottr:Triple".to_string(),
+        argument_list: vec![
+            Argument {
+                list_expand: false,
+                term: subject,
+            },
+            Argument {
+                list_expand: false,
+                term: StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::IRI(
+                    NamedNode::new(format!("{}{}", predicate_prefix, predicate)).unwrap(),
+                ))),
+            },
+            Argument {
+                list_expand: cross,
+                term: object,
+            },
+        ],
+    }
+}
+
+//Lists of structs are zipped, so that each element gets its own instance of the sub-template
+fn struct_instance(
+    signature: &Signature,
+    parent: StottrTerm,
+    params: &[Parameter],
+    zip: bool,
+) -> Instance {
+    let mut argument_list = vec![Argument {
+        list_expand: false,
+        term: parent,
+    }];
+    for p in params {
+        argument_list.push(Argument {
+            list_expand: zip,
+            term: StottrTerm::Variable(p.stottr_variable.clone()),
+        });
+    }
+    Instance {
+        list_expander: if zip {
+            Some(ListExpanderType::ZipMin)
+        } else {
+            None
+        },
+        template_name: signature.template_name.clone(),
+        prefixed_template_name: signature.template_prefixed_name.clone(),
+        argument_list,
+    }
+}
+
+fn default_template(
+    template_prefix: &str,
+    parameter_list: Vec<Parameter>,
+    pattern_list: Vec<Instance>,
+) -> Template {
+    let template_uuid = Uuid::new_v4().to_string();
+    Template {
+        signature: Signature {
+            template_name: NamedNode::new(format!("{}{}", template_prefix, &template_uuid))
+                .unwrap(),
+            template_prefixed_name: format!("prefix:{}", template_uuid),
+            parameter_list,
+            annotation_list: None,
+        },
+        pattern_list,
     }
 }
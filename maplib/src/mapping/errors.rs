@@ -1,23 +1,30 @@
 use crate::ast::{ConstantTerm, PType};
-use oxrdf::IriParseError;
+use crate::templates::errors::TemplateError;
+use oxrdf::{IriParseError, NamedNode};
 use polars_core::error::PolarsError;
 use polars_core::frame::DataFrame;
 use polars_core::prelude::{DataType, Series};
+use representation::literals::LiteralParsingError;
+use representation::RDFNodeType;
 use std::fmt::{Display, Formatter};
 use std::io;
 use thiserror::Error;
 use triplestore::errors::TriplestoreError;
+use triplestore::sparql::errors::SparqlError;
 
 #[derive(Error, Debug)]
 pub enum MappingError {
     InvalidTemplateNameError(#[from] IriParseError),
     TemplateNotFound(String),
     NonOptionalColumnHasNull(String, DataFrame),
+    NonEmptyListColumnHasEmptyList(String, DataFrame),
     NonBlankColumnHasBlankNode(String, Series),
     MissingParameterColumn(String),
     ContainsIrrelevantColumns(Vec<String>),
     CouldNotInferStottrDatatypeForColumn(String, DataType),
     ColumnDataTypeMismatch(String, DataType, PType),
+    InvalidLexicalForm(String, NamedNode, Series),
+    CouldNotCoerceColumn(String, DataType, PType, PolarsError),
     InvalidPredicateConstant(ConstantTerm),
     PTypeNotSupported(String, PType),
     UnknownTimeZoneError(String),
@@ -32,6 +39,30 @@ pub enum MappingError {
     WriteNTriplesError(io::Error),
     RemoveParquetFileError(io::Error),
     TriplestoreError(TriplestoreError),
+    MintedColumnAlreadyPresent(String),
+    MintingColumnNotFound(String, String),
+    InvalidMintingStrategy(String, String),
+    MintingError(String, PolarsError),
+    NestedListTermNotSupported(String),
+    ListTermHasInconsistentTypes(String, RDFNodeType, RDFNodeType),
+    EmptyListTerm(String),
+    LanguageTagColumnNotFound(String, String),
+    DatatypeColumnNotFound(String, String),
+    InvalidDatatypeIRI(String, IriParseError),
+    CouldNotCastToDatatype(String, String, PolarsError),
+    UnsupportedInputFileType(String),
+    ReadCsvError(PolarsError),
+    CollectBatchError(PolarsError),
+    InvalidBatchSize(usize),
+    UpsertRequiresUniqueSubsets(String),
+    LoweringNotSupported(String, String),
+    LoweredQueryError(SparqlError),
+    UnsupportedDefaultColumn(String, DataType),
+    DefaultColumnAlreadyPresent(String),
+    InvalidGraphIRI(String, IriParseError),
+    InvalidLiteral(LiteralParsingError),
+    MultipleObjectTypesForPredicate(String, Vec<RDFNodeType>),
+    TemplateError(TemplateError),
 }
 
 impl Display for MappingError {
@@ -47,6 +78,13 @@ impl Display for MappingError {
                     col, nullkey
                 )
             }
+            MappingError::NonEmptyListColumnHasEmptyList(col, empty) => {
+                write!(
+                    f,
+                    "Column {} which is a non-empty list has empty lists for keys: {}",
+                    col, empty
+                )
+            }
             MappingError::NonBlankColumnHasBlankNode(col, blanks) => {
                 write!(f, "Non-blank column {} has blanks {}", col, blanks)
             }
@@ -70,6 +108,20 @@ impl Display for MappingError {
                     col, dt, ptype
                 )
             }
+            MappingError::InvalidLexicalForm(col, datatype, invalid) => {
+                write!(
+                    f,
+                    "Column {} has values that are not lexical forms of {}: {}",
+                    col, datatype, invalid
+                )
+            }
+            MappingError::CouldNotCoerceColumn(col, dt, ptype, e) => {
+                write!(
+                    f,
+                    "Column {} with datatype {} could not be coerced to stottr datatype {}: {}",
+                    col, dt, ptype, e
+                )
+            }
             MappingError::PTypeNotSupported(name, ptype) => {
                 write!(
                     f,
@@ -140,6 +192,118 @@ impl Display for MappingError {
             MappingError::TriplestoreError(e) => {
                 write!(f, "Triplestore error {}", e)
             }
+            MappingError::MintedColumnAlreadyPresent(c) => {
+                write!(f, "Column {} should be minted but is already present", c)
+            }
+            MappingError::MintingColumnNotFound(c, referenced) => {
+                write!(
+                    f,
+                    "Minting column {} refers to column {} which does not exist",
+                    c, referenced
+                )
+            }
+            MappingError::InvalidMintingStrategy(c, reason) => {
+                write!(f, "Invalid minting strategy for column {}: {}", c, reason)
+            }
+            MappingError::MintingError(c, e) => {
+                write!(f, "Minting column {} resulted in an error: {}", c, e)
+            }
+            MappingError::NestedListTermNotSupported(c) => {
+                write!(
+                    f,
+                    "List term for {} contains a nested list, which is not supported",
+                    c
+                )
+            }
+            MappingError::ListTermHasInconsistentTypes(c, first, other) => {
+                write!(
+                    f,
+                    "List term for {} has elements of inconsistent types {:?} and {:?}",
+                    c, first, other
+                )
+            }
+            MappingError::EmptyListTerm(c) => {
+                write!(f, "List term for {} is empty", c)
+            }
+            MappingError::LanguageTagColumnNotFound(parameter, c) => {
+                write!(
+                    f,
+                    "Language tag column {} for parameter {} was not found",
+                    c, parameter
+                )
+            }
+            MappingError::DatatypeColumnNotFound(parameter, c) => {
+                write!(
+                    f,
+                    "Datatype column {} for parameter {} was not found",
+                    c, parameter
+                )
+            }
+            MappingError::InvalidDatatypeIRI(dt, e) => {
+                write!(f, "Datatype {} is not a valid IRI: {}", dt, e)
+            }
+            MappingError::CouldNotCastToDatatype(c, dt, e) => {
+                write!(
+                    f,
+                    "Column {} could not be cast to datatype {}: {}",
+                    c, dt, e
+                )
+            }
+            MappingError::UnsupportedInputFileType(p) => {
+                write!(f, "Input path {} should end with .parquet or .csv", p)
+            }
+            MappingError::ReadCsvError(e) => {
+                write!(f, "Reading csv file resulted in an error: {:?}", e)
+            }
+            MappingError::CollectBatchError(e) => {
+                write!(f, "Reading a batch of input resulted in an error: {:?}", e)
+            }
+            MappingError::InvalidBatchSize(b) => {
+                write!(f, "Batch size {} should be at least 1", b)
+            }
+            MappingError::UpsertRequiresUniqueSubsets(t) => {
+                write!(
+                    f,
+                    "Upserting template {} requires unique subsets identifying the rows",
+                    t
+                )
+            }
+            MappingError::LoweringNotSupported(t, what) => {
+                write!(f, "Template {} can not be lowered, as it uses {}", t, what)
+            }
+            MappingError::LoweredQueryError(e) => {
+                write!(f, "Querying lowered template resulted in an error: {}", e)
+            }
+            MappingError::UnsupportedDefaultColumn(c, dt) => {
+                write!(
+                    f,
+                    "Column {} with datatype {} is not supported by the default mapping",
+                    c, dt
+                )
+            }
+            MappingError::DefaultColumnAlreadyPresent(c) => {
+                write!(
+                    f,
+                    "Column {} should be created for a struct field by the default mapping but is already present",
+                    c
+                )
+            }
+            MappingError::InvalidGraphIRI(g, e) => {
+                write!(f, "Graph {} is not a valid IRI: {}", g, e)
+            }
+            MappingError::InvalidLiteral(e) => {
+                write!(f, "{}", e)
+            }
+            MappingError::MultipleObjectTypesForPredicate(p, types) => {
+                write!(
+                    f,
+                    "Predicate {} would have objects of several types {:?}, which can not be queried",
+                    p, types
+                )
+            }
+            MappingError::TemplateError(e) => {
+                write!(f, "Template error {}", e)
+            }
         }
     }
 }
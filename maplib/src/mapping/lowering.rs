@@ -0,0 +1,326 @@
+use super::Mapping;
+use crate::ast::{ConstantLiteral, ConstantTerm, Instance, StottrLiteral, StottrTerm};
+use crate::constants::OTTR_TRIPLE;
+use crate::mapping::errors::MappingError;
+use crate::mapping::validation_inference::ptype_to_polars_datatype;
+use oxrdf::{Literal, Variable};
+use polars::prelude::DataFrame;
+use representation::RDFNodeType;
+use spargebra::algebra::GraphPattern;
+use spargebra::term::{NamedNodePattern, TermPattern, TriplePattern};
+use spargebra::Query;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const BLANK_NODE_VARIABLE_PREFIX: &str = "blank_node_";
+
+#[derive(Clone)]
+enum LoweredTerm {
+    //A parameter of the lowered template
+    Variable(String),
+    Constant(ConstantLiteral),
+    None,
+}
+
+struct LoweringState {
+    template_name: String,
+    //The parameters of the template, blank node variables are named so that they differ from these
+    parameters: HashSet<String>,
+    optional_variables: HashSet<String>,
+    n_blank_nodes: usize,
+    //Triple patterns keyed on the optional parameters they depend on, these go in OPTIONAL-blocks
+    triples: BTreeMap<BTreeSet<String>, Vec<TriplePattern>>,
+}
+
+impl LoweringState {
+    fn blank_node_variable(&mut self) -> String {
+        loop {
+            self.n_blank_nodes += 1;
+            let variable = format!("{}{}", BLANK_NODE_VARIABLE_PREFIX, self.n_blank_nodes);
+            if !self.parameters.contains(&variable) {
+                return variable;
+            }
+        }
+    }
+}
+
+impl Mapping {
+    //Creates a SPARQL SELECT query matching the triples of the template, with a variable per parameter
+    pub fn lower_template(&self, template: &str) -> Result<Query, MappingError> {
+        let target_template = self.resolve_template(template)?;
+        let template_name = target_template.signature.template_name.as_str().to_string();
+        let mut bindings = HashMap::new();
+        let mut parameters = HashSet::new();
+        let mut optional_variables = HashSet::new();
+        for p in &target_template.signature.parameter_list {
+            let name = p.stottr_variable.name.clone();
+            parameters.insert(name.clone());
+            if p.optional {
+                optional_variables.insert(name.clone());
+            }
+            bindings.insert(name.clone(), LoweredTerm::Variable(name));
+        }
+        let mut state = LoweringState {
+            template_name,
+            parameters,
+            optional_variables,
+            n_blank_nodes: 0,
+            triples: BTreeMap::new(),
+        };
+        self.lower_instances(
+            &target_template.pattern_list,
+            &bindings,
+            &BTreeSet::new(),
+            &mut state,
+        )?;
+        let mut lowered_triples = state.triples;
+        let required = lowered_triples.remove(&BTreeSet::new()).unwrap_or_default();
+        let mut pattern = GraphPattern::Bgp { patterns: required };
+        for (_, patterns) in lowered_triples {
+            pattern = GraphPattern::LeftJoin {
+                left: Box::new(pattern),
+                right: Box::new(GraphPattern::Bgp { patterns }),
+                expression: None,
+            };
+        }
+        //Parameters that are not used in any triple are unbound
+        let variables = target_template
+            .signature
+            .parameter_list
+            .iter()
+            .map(|p| Variable::new_unchecked(p.stottr_variable.name.as_str()))
+            .collect();
+        Ok(Query::Select {
+            dataset: None,
+            pattern: GraphPattern::Project {
+                inner: Box::new(pattern),
+                variables,
+            },
+            base_iri: None,
+        })
+    }
+
+    //Queries the triplestore for the instances of the template, in the shape expected by expand.
+    //Columns of parameters with a type are cast to the type. The RDF node types of the columns are
+    //returned as well, for the parameters without a type, see ExpandOptions::rdf_node_types.
+    pub fn query_template(
+        &mut self,
+        template: &str,
+    ) -> Result<(DataFrame, HashMap<String, RDFNodeType>), MappingError> {
+        let query = self.lower_template(template)?;
+        let (mut df, rdf_node_types) = self
+            .triplestore
+            .query_select(&query.to_string())
+            .map_err(|x| MappingError::LoweredQueryError(x))?;
+        let signature = &self.resolve_template(template)?.signature;
+        for p in &signature.parameter_list {
+            let dtype = if let Some(ptype) = &p.ptype {
+                ptype_to_polars_datatype(ptype).unwrap()
+            } else {
+                continue;
+            };
+            let series = df.column(&p.stottr_variable.name).unwrap();
+            if series.dtype() != &dtype {
+                let series = series.cast(&dtype).map_err(|_| {
+                    MappingError::LoweringNotSupported(
+                        template.to_string(),
+                        format!("parameters of type {}", p.ptype.as_ref().unwrap()),
+                    )
+                })?;
+                df.with_column(series).unwrap();
+            }
+        }
+        Ok((df, rdf_node_types))
+    }
+
+    fn lower_instances(
+        &self,
+        instances: &Vec<Instance>,
+        bindings: &HashMap<String, LoweredTerm>,
+        guard: &BTreeSet<String>,
+        state: &mut LoweringState,
+    ) -> Result<(), MappingError> {
+        //Blank nodes are fresh for each instance of the template, so they become variables
+        let mut blank_node_variables = HashMap::new();
+        'instances: for i in instances {
+            if i.list_expander.is_some() || i.argument_list.iter().any(|a| a.list_expand) {
+                return Err(MappingError::LoweringNotSupported(
+                    state.template_name.clone(),
+                    "list expanders".to_string(),
+                ));
+            }
+            let mut lowered_arguments = vec![];
+            for a in &i.argument_list {
+                let lowered = match &a.term {
+                    StottrTerm::Variable(v) => {
+                        if let Some(b) = bindings.get(&v.name) {
+                            b.clone()
+                        } else {
+                            return Err(MappingError::UnknownVariableError(v.name.clone()));
+                        }
+                    }
+                    StottrTerm::ConstantTerm(ConstantTerm::Constant(
+                        ConstantLiteral::BlankNode(bn),
+                    )) => {
+                        let variable = blank_node_variables
+                            .entry(bn.as_str().to_string())
+                            .or_insert_with(|| state.blank_node_variable());
+                        LoweredTerm::Variable(variable.clone())
+                    }
+                    StottrTerm::ConstantTerm(ConstantTerm::Constant(ConstantLiteral::None)) => {
+                        LoweredTerm::None
+                    }
+                    StottrTerm::ConstantTerm(ConstantTerm::Constant(c)) => {
+                        LoweredTerm::Constant(c.clone())
+                    }
+                    StottrTerm::ConstantTerm(ConstantTerm::ConstantList(_))
+                    | StottrTerm::List(_) => {
+                        return Err(MappingError::LoweringNotSupported(
+                            state.template_name.clone(),
+                            "list arguments".to_string(),
+                        ));
+                    }
+                };
+                lowered_arguments.push(lowered);
+            }
+
+            if i.template_name.as_str() == OTTR_TRIPLE {
+                //Triples with none are not added
+                if lowered_arguments
+                    .iter()
+                    .any(|x| matches!(x, LoweredTerm::None))
+                {
+                    continue;
+                }
+                let mut triple_guard = guard.clone();
+                for l in &lowered_arguments {
+                    if let LoweredTerm::Variable(v) = l {
+                        if state.optional_variables.contains(v) {
+                            triple_guard.insert(v.clone());
+                        }
+                    }
+                }
+                let triple = TriplePattern {
+                    subject: lowered_to_term_pattern(&lowered_arguments[0], &state.template_name)?,
+                    predicate: lowered_to_named_node_pattern(
+                        &lowered_arguments[1],
+                        &state.template_name,
+                    )?,
+                    object: lowered_to_term_pattern(&lowered_arguments[2], &state.template_name)?,
+                };
+                state.triples.entry(triple_guard).or_default().push(triple);
+            } else {
+                let template = self.resolve_template(i.template_name.as_str())?;
+                let mut instance_bindings = HashMap::new();
+                let mut instance_guard = guard.clone();
+                for (p, l) in template
+                    .signature
+                    .parameter_list
+                    .iter()
+                    .zip(lowered_arguments)
+                {
+                    let l = match l {
+                        LoweredTerm::None => {
+                            if let Some(default) = &p.default_value {
+                                match &default.constant_term {
+                                    //Blank node defaults are fresh for each instance, so they
+                                    //can not be told apart from other blank nodes in a query
+                                    ConstantTerm::Constant(ConstantLiteral::BlankNode(_)) => {
+                                        return Err(MappingError::LoweringNotSupported(
+                                            state.template_name.clone(),
+                                            "blank node default values".to_string(),
+                                        ));
+                                    }
+                                    ConstantTerm::Constant(ConstantLiteral::None) => {
+                                        LoweredTerm::None
+                                    }
+                                    ConstantTerm::Constant(c) => LoweredTerm::Constant(c.clone()),
+                                    ConstantTerm::ConstantList(_) => {
+                                        return Err(MappingError::LoweringNotSupported(
+                                            state.template_name.clone(),
+                                            "list arguments".to_string(),
+                                        ));
+                                    }
+                                }
+                            } else if p.optional {
+                                LoweredTerm::None
+                            } else {
+                                //The instance is dropped when a non-optional argument is none
+                                continue 'instances;
+                            }
+                        }
+                        LoweredTerm::Variable(v) => {
+                            //The instance is only there when a non-optional argument is bound
+                            if !p.optional && state.optional_variables.contains(&v) {
+                                instance_guard.insert(v.clone());
+                            }
+                            LoweredTerm::Variable(v)
+                        }
+                        l => l,
+                    };
+                    instance_bindings.insert(p.stottr_variable.name.clone(), l);
+                }
+                self.lower_instances(
+                    &template.pattern_list,
+                    &instance_bindings,
+                    &instance_guard,
+                    state,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn lowered_to_term_pattern(
+    l: &LoweredTerm,
+    template_name: &str,
+) -> Result<TermPattern, MappingError> {
+    match l {
+        LoweredTerm::Variable(v) => Ok(TermPattern::Variable(Variable::new_unchecked(v))),
+        LoweredTerm::Constant(ConstantLiteral::IRI(nn)) => Ok(TermPattern::NamedNode(nn.clone())),
+        LoweredTerm::Constant(ConstantLiteral::Literal(lit)) => {
+            Ok(TermPattern::Literal(stottr_literal_to_literal(lit)))
+        }
+        LoweredTerm::Constant(ConstantLiteral::BlankNode(_)) => {
+            Err(MappingError::LoweringNotSupported(
+                template_name.to_string(),
+                "blank node constants".to_string(),
+            ))
+        }
+        LoweredTerm::Constant(ConstantLiteral::None) | LoweredTerm::None => {
+            Err(MappingError::LoweringNotSupported(
+                template_name.to_string(),
+                "none in triples".to_string(),
+            ))
+        }
+    }
+}
+
+fn lowered_to_named_node_pattern(
+    l: &LoweredTerm,
+    template_name: &str,
+) -> Result<NamedNodePattern, MappingError> {
+    match l {
+        LoweredTerm::Variable(v) => Ok(NamedNodePattern::Variable(Variable::new_unchecked(v))),
+        LoweredTerm::Constant(ConstantLiteral::IRI(nn)) => {
+            Ok(NamedNodePattern::NamedNode(nn.clone()))
+        }
+        LoweredTerm::Constant(c) => Err(MappingError::InvalidPredicateConstant(
+            ConstantTerm::Constant(c.clone()),
+        )),
+        LoweredTerm::None => Err(MappingError::LoweringNotSupported(
+            template_name.to_string(),
+            "none in triples".to_string(),
+        )),
+    }
+}
+
+fn stottr_literal_to_literal(lit: &StottrLiteral) -> Literal {
+    if let Some(language) = &lit.language {
+        Literal::new_language_tagged_literal_unchecked(&lit.value, language)
+    } else if let Some(dt) = &lit.data_type_iri {
+        Literal::new_typed_literal(&lit.value, dt.clone())
+    } else {
+        Literal::new_simple_literal(&lit.value)
+    }
+}
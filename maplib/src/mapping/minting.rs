@@ -0,0 +1,253 @@
+use super::Mapping;
+use crate::mapping::errors::MappingError;
+use oxrdf::NamedNode;
+use polars_core::frame::DataFrame;
+use polars_core::functions::concat_str;
+use polars_core::prelude::{
+    ChunkExpandAtIndex, DataType, IntoSeries, NamedFrom, Series, Utf8Chunked,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+use xxhash_rust::xxh3::xxh3_128;
+
+//Separates values of different columns before hashing, so that ("ab","c") and ("a","bc") differ
+const HASH_VALUE_SEPARATOR: &str = "\u{1f}";
+
+#[derive(Debug, Clone)]
+pub enum MintingStrategy {
+    //A random UUID for each row
+    Uuid,
+    //A stable hash of the values of the given columns
+    Hash(Vec<String>),
+    //A format string where {column} is replaced by the percent-encoded value of that column
+    Format(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct MintingOptions {
+    pub namespace: String,
+    pub strategy: MintingStrategy,
+}
+
+enum FormatPart {
+    Text(String),
+    Column(String),
+}
+
+impl Mapping {
+    //Columns may be minted from other minted columns, so they are minted in dependency order
+    pub fn mint_iris(
+        &self,
+        mut df: DataFrame,
+        minting: &HashMap<String, MintingOptions>,
+    ) -> Result<DataFrame, MappingError> {
+        let mut to_mint = vec![];
+        for (column_name, options) in minting {
+            if df.get_column_names().contains(&column_name.as_str()) {
+                return Err(MappingError::MintedColumnAlreadyPresent(
+                    column_name.clone(),
+                ));
+            }
+            let dependencies: Vec<String> = referenced_columns(column_name, options)?
+                .into_iter()
+                .filter(|c| minting.contains_key(c))
+                .collect();
+            to_mint.push((column_name, options, dependencies));
+        }
+        //Sorted so that the error for a cycle does not depend on the iteration order
+        to_mint.sort_by(|x, y| x.0.cmp(y.0));
+        while !to_mint.is_empty() {
+            let minted = df.get_column_names().len();
+            let mut remaining = vec![];
+            for (column_name, options, dependencies) in to_mint {
+                if dependencies
+                    .iter()
+                    .all(|d| df.get_column_names().contains(&d.as_str()))
+                {
+                    let column = mint_column(&df, column_name, options)?;
+                    df.with_column(column).unwrap();
+                } else {
+                    remaining.push((column_name, options, dependencies));
+                }
+            }
+            if df.get_column_names().len() == minted {
+                let (column_name, _, dependencies) = remaining.first().unwrap();
+                return Err(MappingError::InvalidMintingStrategy(
+                    column_name.to_string(),
+                    format!(
+                        "it refers to the minted columns {} in a cycle",
+                        dependencies.join(", ")
+                    ),
+                ));
+            }
+            to_mint = remaining;
+        }
+        Ok(df)
+    }
+}
+
+//The columns the minted values are made from
+fn referenced_columns(
+    column_name: &str,
+    options: &MintingOptions,
+) -> Result<Vec<String>, MappingError> {
+    Ok(match &options.strategy {
+        MintingStrategy::Uuid => vec![],
+        MintingStrategy::Hash(columns) => columns.clone(),
+        MintingStrategy::Format(format) => parse_format(column_name, format)?
+            .into_iter()
+            .filter_map(|p| match p {
+                FormatPart::Column(c) => Some(c),
+                FormatPart::Text(_) => None,
+            })
+            .collect(),
+    })
+}
+
+fn mint_column(
+    df: &DataFrame,
+    column_name: &str,
+    options: &MintingOptions,
+) -> Result<Series, MappingError> {
+    let namespace = &options.namespace;
+    validate_iri(column_name, namespace)?;
+    let height = if df.width() == 0 { 1 } else { df.height() };
+    let series = match &options.strategy {
+        MintingStrategy::Uuid => {
+            let iris: Vec<String> = (0..height)
+                .map(|_| format!("{}{}", namespace, Uuid::new_v4().simple()))
+                .collect();
+            Series::new(column_name, iris)
+        }
+        MintingStrategy::Hash(columns) => {
+            let mut to_concat = vec![];
+            for c in columns {
+                to_concat.push(get_referenced_column(df, column_name, c)?);
+            }
+            if to_concat.is_empty() {
+                return Err(MappingError::InvalidMintingStrategy(
+                    column_name.to_string(),
+                    "no columns to hash".to_string(),
+                ));
+            }
+            let values = concat_str(&to_concat, HASH_VALUE_SEPARATOR)
+                .map_err(|x| MappingError::MintingError(column_name.to_string(), x))?;
+            let mut hashed: Utf8Chunked = values
+                .into_iter()
+                .map(|x| x.map(|v| format!("{}{:032x}", namespace, stable_hash(v))))
+                .collect();
+            hashed.rename(column_name);
+            hashed.into_series()
+        }
+        MintingStrategy::Format(format) => {
+            let parts = parse_format(column_name, format)?;
+            //The values are percent-encoded, so only the text of the format can make the IRI invalid
+            let mut example = namespace.to_string();
+            for part in &parts {
+                match part {
+                    FormatPart::Text(t) => example.push_str(t),
+                    FormatPart::Column(_) => example.push('x'),
+                }
+            }
+            validate_iri(column_name, &example)?;
+            let mut to_concat = vec![Series::new(column_name, [namespace.as_str()])];
+            for part in parts {
+                match part {
+                    FormatPart::Text(t) => {
+                        to_concat.push(Series::new(column_name, [t]));
+                    }
+                    FormatPart::Column(c) => {
+                        let values = get_referenced_column(df, column_name, &c)?;
+                        to_concat.push(percent_encode_values(column_name, &values)?);
+                    }
+                }
+            }
+            let mut formatted = concat_str(&to_concat, "")
+                .map_err(|x| MappingError::MintingError(column_name.to_string(), x))?;
+            if formatted.len() != height {
+                //Only constant text in the format, so we broadcast it
+                formatted = formatted.new_from_index(0, height);
+            }
+            formatted.rename(column_name);
+            formatted.into_series()
+        }
+    };
+    Ok(series)
+}
+
+fn get_referenced_column(
+    df: &DataFrame,
+    column_name: &str,
+    referenced: &str,
+) -> Result<Series, MappingError> {
+    df.column(referenced).cloned().map_err(|_| {
+        MappingError::MintingColumnNotFound(column_name.to_string(), referenced.to_string())
+    })
+}
+
+fn stable_hash(value: &str) -> u128 {
+    //XXH3 is a fixed algorithm, so the minted IRIs are the same across runs and Rust versions.
+    //128 bits make collisions unlikely also for billions of rows.
+    xxh3_128(value.as_bytes())
+}
+
+fn validate_iri(column_name: &str, iri: &str) -> Result<(), MappingError> {
+    NamedNode::new(iri).map_err(|x| {
+        MappingError::InvalidMintingStrategy(
+            column_name.to_string(),
+            format!("{} is not a valid IRI: {}", iri, x),
+        )
+    })?;
+    Ok(())
+}
+
+fn percent_encode_values(column_name: &str, values: &Series) -> Result<Series, MappingError> {
+    let values = values
+        .cast(&DataType::Utf8)
+        .map_err(|x| MappingError::MintingError(column_name.to_string(), x))?;
+    let mut encoded: Utf8Chunked = values
+        .utf8()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.map(percent_encode))
+        .collect();
+    encoded.rename(values.name());
+    Ok(encoded.into_series())
+}
+
+//All but the unreserved characters of RFC 3986 are encoded
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+fn parse_format(column_name: &str, format: &str) -> Result<Vec<FormatPart>, MappingError> {
+    let mut parts = vec![];
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(FormatPart::Text(rest[..start].to_string()));
+        }
+        let after = &rest[start + 1..];
+        if let Some(end) = after.find('}') {
+            parts.push(FormatPart::Column(after[..end].to_string()));
+            rest = &after[end + 1..];
+        } else {
+            return Err(MappingError::InvalidMintingStrategy(
+                column_name.to_string(),
+                format!("unterminated column reference in format {}", format),
+            ));
+        }
+    }
+    if !rest.is_empty() {
+        parts.push(FormatPart::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
@@ -0,0 +1,207 @@
+use super::{ExpandOptions, Mapping, MappingReport};
+use crate::mapping::errors::MappingError;
+use polars::prelude::{
+    CsvReader, DataFrame, IdxSize, LazyCsvReader, LazyFileListReader, LazyFrame, ParquetReader,
+    ScanArgsParquet, SerReader,
+};
+use polars_core::utils::concat_df;
+use polars_core::error::PolarsError;
+use std::fs::File;
+use uuid::Uuid;
+
+//Rows read at a time when no batch size is given in the options
+const DEFAULT_BATCH_SIZE: usize = 1_000_000;
+
+enum InputFileType {
+    Parquet,
+    Csv,
+}
+
+impl Mapping {
+    //Each batch is collected from a slice of the lazy plan, so that only one batch is in memory
+    //at a time
+    pub fn expand_lazy(
+        &mut self,
+        template: &str,
+        lf: LazyFrame,
+        options: ExpandOptions,
+    ) -> Result<MappingReport, MappingError> {
+        let batch_size = validate_batch_size(options.batch_size)?;
+        let call_uuid = Uuid::new_v4().to_string();
+        let mut report = self.new_streaming_report(&call_uuid);
+        let mut offset = 0;
+        loop {
+            let batch = lf
+                .clone()
+                .slice(offset as i64, batch_size as IdxSize)
+                .collect()
+                .map_err(MappingError::CollectBatchError)?;
+            let height = batch.height();
+            //Always expand the first batch, so that an empty input is still validated
+            if height > 0 || offset == 0 {
+                self.expand_batch(template, batch, &options, &call_uuid, &mut report)?;
+            }
+            if height < batch_size {
+                break;
+            }
+            offset += batch_size;
+        }
+        Ok(report)
+    }
+
+    //Parquet files are read a row group at a time, and csv files a chunk at a time
+    pub fn expand_from_files(
+        &mut self,
+        template: &str,
+        path: &str,
+        options: ExpandOptions,
+    ) -> Result<MappingReport, MappingError> {
+        let file_type = if path.ends_with(".parquet") {
+            InputFileType::Parquet
+        } else if path.ends_with(".csv") {
+            InputFileType::Csv
+        } else {
+            return Err(MappingError::UnsupportedInputFileType(path.to_string()));
+        };
+        let batch_size = validate_batch_size(options.batch_size)?;
+        let mut paths: Vec<_> = glob::glob(path)
+            .map_err(|_| MappingError::PathDoesNotExist(path.to_string()))?
+            .filter_map(|x| x.ok())
+            .collect();
+        if paths.is_empty() {
+            return Err(MappingError::PathDoesNotExist(path.to_string()));
+        }
+        paths.sort();
+
+        let call_uuid = Uuid::new_v4().to_string();
+        let mut report = self.new_streaming_report(&call_uuid);
+        let mut batcher = Batcher::new(batch_size);
+        let mut expanded = false;
+        for p in paths {
+            match file_type {
+                InputFileType::Parquet => {
+                    let file = File::open(&p)
+                        .map_err(|x| MappingError::ReadParquetError(PolarsError::from(x)))?;
+                    let mut reader = ParquetReader::new(file)
+                        .batched(batch_size)
+                        .map_err(MappingError::ReadParquetError)?;
+                    while let Some(dfs) = reader
+                        .next_batches(1)
+                        .map_err(MappingError::ReadParquetError)?
+                    {
+                        for df in dfs {
+                            for batch in batcher.push(df)? {
+                                self.expand_batch(
+                                    template,
+                                    batch,
+                                    &options,
+                                    &call_uuid,
+                                    &mut report,
+                                )?;
+                                expanded = true;
+                            }
+                        }
+                    }
+                }
+                InputFileType::Csv => {
+                    let mut reader = CsvReader::from_path(&p)
+                        .map_err(MappingError::ReadCsvError)?
+                        .with_chunk_size(batch_size);
+                    let mut reader = reader
+                        .batched_borrowed_read()
+                        .map_err(MappingError::ReadCsvError)?;
+                    while let Some(dfs) =
+                        reader.next_batches(1).map_err(MappingError::ReadCsvError)?
+                    {
+                        for df in dfs {
+                            for batch in batcher.push(df)? {
+                                self.expand_batch(
+                                    template,
+                                    batch,
+                                    &options,
+                                    &call_uuid,
+                                    &mut report,
+                                )?;
+                                expanded = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(df) = batcher.finish()? {
+            self.expand_batch(template, df, &options, &call_uuid, &mut report)?;
+        } else if !expanded {
+            //Files without rows are read with their schema, so that an empty input is still
+            //validated
+            let lf = match file_type {
+                InputFileType::Parquet => LazyFrame::scan_parquet(path, ScanArgsParquet::default())
+                    .map_err(MappingError::ReadParquetError)?,
+                InputFileType::Csv => LazyCsvReader::new(path)
+                    .finish()
+                    .map_err(MappingError::ReadCsvError)?,
+            };
+            let df = lf
+                .slice(0, 0)
+                .collect()
+                .map_err(MappingError::CollectBatchError)?;
+            self.expand_batch(template, df, &options, &call_uuid, &mut report)?;
+        }
+        Ok(report)
+    }
+
+    fn new_streaming_report(&self, call_uuid: &str) -> MappingReport {
+        MappingReport {
+            call_uuid: call_uuid.to_string(),
+            used_caching: self.use_caching,
+            ..Default::default()
+        }
+    }
+}
+
+fn validate_batch_size(batch_size: Option<usize>) -> Result<usize, MappingError> {
+    let batch_size = batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    if batch_size == 0 {
+        return Err(MappingError::InvalidBatchSize(batch_size));
+    }
+    Ok(batch_size)
+}
+
+//Collects the row groups and chunks read from files into batches of batch_size rows
+struct Batcher {
+    batch_size: usize,
+    dfs: Vec<DataFrame>,
+    height: usize,
+}
+
+impl Batcher {
+    fn new(batch_size: usize) -> Batcher {
+        Batcher {
+            batch_size,
+            dfs: vec![],
+            height: 0,
+        }
+    }
+
+    fn push(&mut self, df: DataFrame) -> Result<Vec<DataFrame>, MappingError> {
+        self.height += df.height();
+        self.dfs.push(df);
+        let mut batches = vec![];
+        while self.height >= self.batch_size {
+            let df = concat_df(&self.dfs).map_err(MappingError::CollectBatchError)?;
+            let rest = df.slice(self.batch_size as i64, self.height - self.batch_size);
+            batches.push(df.slice(0, self.batch_size));
+            self.height = rest.height();
+            self.dfs = vec![rest];
+        }
+        Ok(batches)
+    }
+
+    fn finish(self) -> Result<Option<DataFrame>, MappingError> {
+        if self.height == 0 {
+            return Ok(None);
+        }
+        let df = concat_df(&self.dfs).map_err(MappingError::CollectBatchError)?;
+        Ok(Some(df))
+    }
+}
@@ -1,16 +1,192 @@
 use super::Mapping;
 use crate::ast::{PType, Parameter, Signature};
+use crate::constants::REJECTION_REASON_COLUMN;
 use crate::mapping::errors::MappingError;
-use crate::mapping::{ExpandOptions, PrimitiveColumn, RDFNodeType};
+use crate::mapping::minting::MintingOptions;
+use crate::mapping::{
+    datatype_column_name, language_tag_column_name, ExpandOptions, PrimitiveColumn, RDFNodeType,
+};
 use oxrdf::vocab::xsd;
-use oxrdf::NamedNode;
+use oxrdf::{NamedNode, NamedNodeRef};
+use polars::prelude::{col, lit, when, IntoLazy, StrptimeOptions, NULL};
 use polars_core::datatypes::BooleanChunked;
 use polars_core::export::rayon::prelude::ParallelIterator;
 use polars_core::frame::DataFrame;
-use polars_core::prelude::DataType;
+use polars_core::prelude::{DataType, PolarsResult, Series, TimeUnit};
 use std::collections::{HashMap, HashSet};
 
 impl Mapping {
+    //Casts columns that do not match the datatype of their parameter, e.g. ISO strings to dates
+    pub fn coerce_dataframe_columns(
+        &self,
+        signature: &Signature,
+        mut df: DataFrame,
+    ) -> Result<DataFrame, MappingError> {
+        for parameter in &signature.parameter_list {
+            let variable_name = &parameter.stottr_variable.name;
+            if let (Some(ptype), Ok(series)) = (&parameter.ptype, df.column(variable_name)) {
+                if validate_datatype(variable_name, series.dtype(), ptype).is_ok() {
+                    continue;
+                }
+                if let Some(target_datatype) = ptype_to_polars_datatype(ptype) {
+                    let coerced = coerce_series(series, &target_datatype).map_err(|x| {
+                        MappingError::CouldNotCoerceColumn(
+                            variable_name.to_string(),
+                            series.dtype().clone(),
+                            ptype.clone(),
+                            x,
+                        )
+                    })?;
+                    df.with_column(coerced).unwrap();
+                }
+            }
+        }
+        Ok(df)
+    }
+
+    //Columns of a subtype of their parameter are cast to the datatype of the parameter, e.g. Int32
+    //to Int64 for xsd:long, so that the triples of a predicate and datatype have the same dtype
+    pub fn cast_subtype_columns(&self, signature: &Signature, mut df: DataFrame) -> DataFrame {
+        for parameter in &signature.parameter_list {
+            let variable_name = &parameter.stottr_variable.name;
+            if let (Some(ptype), Ok(series)) = (&parameter.ptype, df.column(variable_name)) {
+                if !is_native_ptype(ptype) || series.dtype() == &DataType::Null {
+                    continue;
+                }
+                if let Some(target_datatype) = ptype_to_polars_datatype(ptype) {
+                    if series.dtype() != &target_datatype {
+                        let cast = series.cast(&target_datatype).unwrap();
+                        df.with_column(cast).unwrap();
+                    }
+                }
+            }
+        }
+        df
+    }
+
+    //Columns with language tags or datatypes per row are renamed after the parameter they belong to
+    pub fn add_language_tag_and_datatype_columns(
+        &self,
+        signature: &Signature,
+        mut df: DataFrame,
+        options: &ExpandOptions,
+    ) -> Result<DataFrame, MappingError> {
+        let mut sources = HashSet::new();
+        if let Some(language_tag_columns) = &options.language_tag_columns {
+            for (parameter, c) in language_tag_columns {
+                let series = df.column(c).map_err(|_| {
+                    MappingError::LanguageTagColumnNotFound(parameter.clone(), c.clone())
+                })?;
+                let mut series = series.cast(&DataType::Utf8).unwrap();
+                series.rename(&language_tag_column_name(parameter));
+                df.with_column(series).unwrap();
+                sources.insert(c);
+            }
+        }
+        if let Some(datatype_columns) = &options.datatype_columns {
+            for (parameter, c) in datatype_columns {
+                let series = df.column(c).map_err(|_| {
+                    MappingError::DatatypeColumnNotFound(parameter.clone(), c.clone())
+                })?;
+                let mut series = series.cast(&DataType::Utf8).unwrap();
+                series.rename(&datatype_column_name(parameter));
+                df.with_column(series).unwrap();
+                sources.insert(c);
+            }
+        }
+        for c in sources {
+            let is_parameter = signature
+                .parameter_list
+                .iter()
+                .any(|p| &p.stottr_variable.name == c);
+            if !is_parameter {
+                df = df.drop(c).unwrap();
+            }
+        }
+        Ok(df)
+    }
+
+    //Splits off rows that would fail validation. The rows are checked after minting and column
+    //rewriting, but are rejected as they were given in the input, with a column with the reason.
+    pub fn quarantine_invalid_rows(
+        &self,
+        signature: &Signature,
+        input_df: DataFrame,
+        df: DataFrame,
+    ) -> (DataFrame, DataFrame) {
+        let mut violations = vec![];
+        for parameter in &signature.parameter_list {
+            let variable_name = &parameter.stottr_variable.name;
+            if let Ok(series) = df.column(variable_name) {
+                if !parameter.optional {
+                    violations.push((
+                        col(variable_name).is_null(),
+                        format!("Non-optional column {} is null", variable_name),
+                    ));
+                }
+                if parameter.non_blank && series.dtype() == &DataType::Utf8 {
+                    violations.push((
+                        col(variable_name).str().starts_with(lit("_:")),
+                        format!("Non-blank column {} has a blank node", variable_name),
+                    ));
+                }
+                if let (Some(ptype), DataType::Utf8 | DataType::Categorical(_)) =
+                    (&parameter.ptype, series.dtype())
+                {
+                    if let Some((datatype, pattern)) = ptype_lexical_form_pattern(ptype) {
+                        violations.push((
+                            col(variable_name)
+                                .cast(DataType::Utf8)
+                                .str()
+                                .contains(lit(pattern), true)
+                                .not(),
+                            format!(
+                                "Column {} has a value that is not a lexical form of {}",
+                                variable_name, datatype
+                            ),
+                        ));
+                    }
+                }
+                if let (Some(PType::NEListType(_)), DataType::List(_)) =
+                    (&parameter.ptype, series.dtype())
+                {
+                    violations.push((
+                        col(variable_name).list().lengths().eq(lit(0)),
+                        format!("Non-empty list column {} has an empty list", variable_name),
+                    ));
+                }
+            }
+        }
+        //The first violation of a row is used as the reason
+        let mut reason = lit(NULL).cast(DataType::Utf8);
+        for (condition, r) in violations.into_iter().rev() {
+            reason = when(condition).then(lit(r)).otherwise(reason);
+        }
+        let reason = df
+            .clone()
+            .lazy()
+            .with_column(reason.alias(REJECTION_REASON_COLUMN))
+            .select([col(REJECTION_REASON_COLUMN)])
+            .collect()
+            .unwrap()
+            .column(REJECTION_REASON_COLUMN)
+            .unwrap()
+            .clone();
+        let is_valid = reason.is_null();
+        let valid = df.filter(&is_valid).unwrap();
+        let mut rejected = input_df;
+        if is_valid.all() {
+            rejected = rejected.clear();
+            rejected
+                .with_column(Series::new_empty(REJECTION_REASON_COLUMN, &DataType::Utf8))
+                .unwrap();
+        } else {
+            rejected.with_column(reason).unwrap();
+            rejected = rejected.filter(&!is_valid).unwrap();
+        }
+        (valid, rejected)
+    }
+
     pub fn validate_infer_dataframe_columns(
         &self,
         signature: &Signature,
@@ -25,6 +201,8 @@ impl Mapping {
             let variable_name = &parameter.stottr_variable.name;
             if df_columns.contains(variable_name.as_str()) {
                 df_columns.remove(variable_name.as_str());
+                df_columns.remove(&language_tag_column_name(variable_name));
+                df_columns.remove(&datatype_column_name(variable_name));
                 if !parameter.optional {
                     validate_non_optional_parameter(&df, variable_name)?;
                 }
@@ -32,11 +210,16 @@ impl Mapping {
                     //TODO handle blanks;
                     validate_non_blank_parameter(&df, variable_name)?;
                 }
+                if let Some(PType::NEListType(_)) = &parameter.ptype {
+                    validate_non_empty_list_parameter(df, variable_name)?;
+                }
                 let column_data_type = validate_infer_column_data_type(
                     df,
                     &parameter,
                     variable_name,
                     &options.language_tags,
+                    &options.minting,
+                    &options.rdf_node_types,
                 )?;
 
                 map.insert(variable_name.to_string(), column_data_type);
@@ -55,22 +238,58 @@ impl Mapping {
     }
 }
 
+pub(crate) fn coerce_series(series: &Series, target_datatype: &DataType) -> PolarsResult<Series> {
+    match (series.dtype(), target_datatype) {
+        //Temporal values are parsed from ISO strings
+        (DataType::Utf8, DataType::Date)
+        | (DataType::Utf8, DataType::Datetime(..))
+        | (DataType::Utf8, DataType::Time) => {
+            let df = DataFrame::new(vec![series.clone()])?
+                .lazy()
+                .with_column(
+                    col(series.name())
+                        .str()
+                        .strptime(target_datatype.clone(), StrptimeOptions::default()),
+                )
+                .collect()?;
+            Ok(df.column(series.name())?.clone())
+        }
+        _ => series.strict_cast(target_datatype),
+    }
+}
+
 fn validate_infer_column_data_type(
     dataframe: &DataFrame,
     parameter: &Parameter,
     column_name: &str,
     language_tag_map: &Option<HashMap<String, String>>,
+    minting_map: &Option<HashMap<String, MintingOptions>>,
+    rdf_node_type_map: &Option<HashMap<String, RDFNodeType>>,
 ) -> Result<PrimitiveColumn, MappingError> {
     let series = dataframe.column(column_name).unwrap();
     let dtype = series.dtype();
-    let ptype = if let Some(ptype) = &parameter.ptype {
+    let is_minted = if let Some(map) = minting_map {
+        map.contains_key(column_name)
+    } else {
+        false
+    };
+    let given_rdf_node_type = if let Some(map) = rdf_node_type_map {
+        map.get(column_name)
+    } else {
+        None
+    };
+    let rdf_node_type = if let Some(ptype) = &parameter.ptype {
         validate_datatype(series.name(), dtype, ptype)?;
-        ptype.clone()
+        validate_lexical_forms(series, ptype)?;
+        infer_rdf_node_type(ptype)
+    } else if is_minted {
+        RDFNodeType::IRI
+    } else if let Some(rdf_node_type) = given_rdf_node_type {
+        rdf_node_type.clone()
     } else {
-        let target_ptype = polars_datatype_to_xsd_datatype(dtype);
-        target_ptype
+        let target_ptype = polars_datatype_to_xsd_datatype(column_name, dtype)?;
+        infer_rdf_node_type(&target_ptype)
     };
-    let rdf_node_type = infer_rdf_node_type(&ptype);
     let language_tag = if let Some(map) = language_tag_map {
         if let Some(tag) = map.get(column_name) {
             Some(tag.clone())
@@ -80,9 +299,15 @@ fn validate_infer_column_data_type(
     } else {
         None
     };
+    let language_tag_from_column = dataframe
+        .column(&language_tag_column_name(column_name))
+        .is_ok();
+    let datatype_from_column = dataframe.column(&datatype_column_name(column_name)).is_ok();
     Ok(PrimitiveColumn {
         rdf_node_type,
         language_tag,
+        language_tag_from_column,
+        datatype_from_column,
     })
 }
 
@@ -113,6 +338,27 @@ fn validate_non_optional_parameter(df: &DataFrame, column_name: &str) -> Result<
     }
 }
 
+pub(crate) fn validate_non_empty_list_parameter(
+    df: &DataFrame,
+    column_name: &str,
+) -> Result<(), MappingError> {
+    if let DataType::List(_) = df.column(column_name).unwrap().dtype() {
+        let empty = df
+            .clone()
+            .lazy()
+            .filter(col(column_name).list().lengths().eq(lit(0)))
+            .collect()
+            .unwrap();
+        if empty.height() > 0 {
+            return Err(MappingError::NonEmptyListColumnHasEmptyList(
+                column_name.to_string(),
+                empty,
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn validate_non_blank_parameter(df: &DataFrame, column_name: &str) -> Result<(), MappingError> {
     let is_blank_node_mask: BooleanChunked = df
         .column(column_name)
@@ -163,7 +409,7 @@ fn validate_datatype(
                 Ok(validate_basic_datatype(column_name, datatype, bt)?)
             }
         }
-        PType::LUBType(inner) => validate_if_series_list(inner),
+        PType::LUBType(inner) => validate_datatype(column_name, datatype, inner),
         PType::ListType(inner) => validate_if_series_list(inner),
         PType::NEListType(inner) => validate_if_series_list(inner),
     }
@@ -174,20 +420,153 @@ fn validate_basic_datatype(
     datatype: &DataType,
     rdf_datatype: &NamedNode,
 ) -> Result<(), MappingError> {
-    // match rdf_datatype.as_ref() {
-    //     xsd::INT => {
-    //         Ok(());
-    //
-    //     }
-    // }
+    let target = PType::BasicType(rdf_datatype.clone(), rdf_datatype.to_string());
+    let compatible = match datatype {
+        //Strings may hold IRIs or lexical forms of datatypes without a native polars type
+        DataType::Utf8 | DataType::Categorical(_) => !is_native_xsd_datatype(rdf_datatype.as_str()),
+        DataType::Null => true,
+        dt => {
+            if let Some(xsd_nn_ref) = polars_datatype_to_xsd_nn_ref(dt) {
+                PType::BasicType(xsd_nn_ref.into_owned(), "".to_string()).is_subtype_of(&target)
+            } else {
+                false
+            }
+        }
+    };
+    if compatible {
+        Ok(())
+    } else {
+        Err(MappingError::ColumnDataTypeMismatch(
+            column_name.to_string(),
+            datatype.clone(),
+            target,
+        ))
+    }
+}
+
+//Strings given for a datatype with a restricted lexical space must be lexical forms of it
+fn validate_lexical_forms(series: &Series, ptype: &PType) -> Result<(), MappingError> {
+    let (datatype, pattern) = if let Some(p) = ptype_lexical_form_pattern(ptype) {
+        p
+    } else {
+        return Ok(());
+    };
+    let values = if let DataType::List(_) = series.dtype() {
+        series.explode().unwrap()
+    } else {
+        series.clone()
+    };
+    if !matches!(values.dtype(), DataType::Utf8 | DataType::Categorical(_)) {
+        return Ok(());
+    }
+    let values = values.cast(&DataType::Utf8).unwrap();
+    let invalid = DataFrame::new(vec![values])
+        .unwrap()
+        .lazy()
+        .filter(col(series.name()).str().contains(lit(pattern), true).not())
+        .collect()
+        .unwrap();
+    if invalid.height() > 0 {
+        return Err(MappingError::InvalidLexicalForm(
+            series.name().to_string(),
+            datatype.clone(),
+            invalid.column(series.name()).unwrap().clone(),
+        ));
+    }
     Ok(())
 }
 
-pub fn polars_datatype_to_xsd_datatype(datatype: &DataType) -> PType {
+fn ptype_lexical_form_pattern(ptype: &PType) -> Option<(&NamedNode, &'static str)> {
+    match ptype {
+        PType::BasicType(bt, _) => xsd_lexical_form_pattern(bt.as_str()).map(|p| (bt, p)),
+        PType::LUBType(inner) | PType::ListType(inner) | PType::NEListType(inner) => {
+            ptype_lexical_form_pattern(inner)
+        }
+    }
+}
+
+//Patterns of the lexical forms of the numeric datatypes without a native polars datatype
+fn xsd_lexical_form_pattern(iri: &str) -> Option<&'static str> {
+    let pattern = match iri {
+        x if x == xsd::INTEGER.as_str() => r"^[+-]?[0-9]+$",
+        x if x == xsd::NON_NEGATIVE_INTEGER.as_str() => r"^(\+?[0-9]+|-0+)$",
+        x if x == xsd::POSITIVE_INTEGER.as_str() => r"^\+?0*[1-9][0-9]*$",
+        x if x == xsd::NON_POSITIVE_INTEGER.as_str() => r"^(-[0-9]+|\+?0+)$",
+        x if x == xsd::NEGATIVE_INTEGER.as_str() => r"^-0*[1-9][0-9]*$",
+        x if x == xsd::DECIMAL.as_str() => r"^[+-]?([0-9]+(\.[0-9]*)?|\.[0-9]+)$",
+        _ => {
+            return None;
+        }
+    };
+    Some(pattern)
+}
+
+//Datatypes where values must be given as the corresponding polars datatype
+fn is_native_xsd_datatype(iri: &str) -> bool {
+    xsd_datatype_to_polars_datatype(iri).is_some() && iri != xsd::STRING.as_str()
+}
+
+pub(crate) fn xsd_datatype_to_polars_datatype(iri: &str) -> Option<DataType> {
+    let dt = match iri {
+        x if x == xsd::BOOLEAN.as_str() => DataType::Boolean,
+        x if x == xsd::BYTE.as_str() => DataType::Int8,
+        x if x == xsd::SHORT.as_str() => DataType::Int16,
+        x if x == xsd::INT.as_str() => DataType::Int32,
+        x if x == xsd::LONG.as_str() => DataType::Int64,
+        x if x == xsd::UNSIGNED_BYTE.as_str() => DataType::UInt8,
+        x if x == xsd::UNSIGNED_SHORT.as_str() => DataType::UInt16,
+        x if x == xsd::UNSIGNED_INT.as_str() => DataType::UInt32,
+        x if x == xsd::UNSIGNED_LONG.as_str() => DataType::UInt64,
+        x if x == xsd::FLOAT.as_str() => DataType::Float32,
+        x if x == xsd::DOUBLE.as_str() => DataType::Float64,
+        x if x == xsd::DATE.as_str() => DataType::Date,
+        x if x == xsd::DATE_TIME.as_str() => DataType::Datetime(TimeUnit::Nanoseconds, None),
+        x if x == xsd::DATE_TIME_STAMP.as_str() => {
+            DataType::Datetime(TimeUnit::Nanoseconds, Some("UTC".to_string()))
+        }
+        x if x == xsd::DURATION.as_str() => DataType::Duration(TimeUnit::Nanoseconds),
+        x if x == xsd::TIME.as_str() => DataType::Time,
+        x if x == xsd::STRING.as_str() => DataType::Utf8,
+        _ => {
+            return None;
+        }
+    };
+    Some(dt)
+}
+
+fn is_native_ptype(ptype: &PType) -> bool {
+    match ptype {
+        PType::BasicType(bt, _) => is_native_xsd_datatype(bt.as_str()),
+        PType::LUBType(inner) | PType::ListType(inner) | PType::NEListType(inner) => {
+            is_native_ptype(inner)
+        }
+    }
+}
+
+pub(crate) fn ptype_to_polars_datatype(ptype: &PType) -> Option<DataType> {
+    match ptype {
+        PType::BasicType(bt, _) => {
+            if let Some(dt) = xsd_datatype_to_polars_datatype(bt.as_str()) {
+                Some(dt)
+            } else {
+                //IRIs and lexical forms
+                Some(DataType::Utf8)
+            }
+        }
+        PType::LUBType(inner) => ptype_to_polars_datatype(inner),
+        PType::ListType(inner) | PType::NEListType(inner) => {
+            ptype_to_polars_datatype(inner).map(|x| DataType::List(Box::new(x)))
+        }
+    }
+}
+
+fn polars_datatype_to_xsd_nn_ref(datatype: &DataType) -> Option<NamedNodeRef<'static>> {
     let xsd_nn_ref = match datatype {
         DataType::Boolean => xsd::BOOLEAN,
         DataType::Int8 => xsd::BYTE,
+        DataType::Int16 => xsd::SHORT,
         DataType::UInt8 => xsd::UNSIGNED_BYTE,
+        DataType::UInt16 => xsd::UNSIGNED_SHORT,
         DataType::UInt32 => xsd::UNSIGNED_INT,
         DataType::UInt64 => xsd::UNSIGNED_LONG,
         DataType::Int32 => xsd::INT,
@@ -199,13 +578,33 @@ pub fn polars_datatype_to_xsd_datatype(datatype: &DataType) -> PType {
         DataType::Datetime(_, Some(_)) => xsd::DATE_TIME_STAMP,
         DataType::Datetime(_, None) => xsd::DATE_TIME,
         DataType::Duration(_) => xsd::DURATION,
+        DataType::Time => xsd::TIME,
+        DataType::Decimal(_, _) => xsd::DECIMAL,
+        DataType::Binary => xsd::BASE_64_BINARY,
         DataType::Categorical(_) => xsd::STRING,
-        DataType::List(inner) => {
-            return PType::ListType(Box::new(polars_datatype_to_xsd_datatype(inner)))
-        }
         _ => {
-            panic!("Unsupported datatype:{}", datatype)
+            return None;
         }
     };
-    PType::BasicType(xsd_nn_ref.into_owned(), "".to_string())
+    Some(xsd_nn_ref)
+}
+
+pub fn polars_datatype_to_xsd_datatype(
+    column_name: &str,
+    datatype: &DataType,
+) -> Result<PType, MappingError> {
+    if let DataType::List(inner) = datatype {
+        return Ok(PType::ListType(Box::new(polars_datatype_to_xsd_datatype(
+            column_name,
+            inner,
+        )?)));
+    }
+    if let Some(xsd_nn_ref) = polars_datatype_to_xsd_nn_ref(datatype) {
+        Ok(PType::BasicType(xsd_nn_ref.into_owned(), "".to_string()))
+    } else {
+        Err(MappingError::CouldNotInferStottrDatatypeForColumn(
+            column_name.to_string(),
+            datatype.clone(),
+        ))
+    }
 }
@@ -8,6 +8,7 @@ use nom::bytes::complete::{escaped, is_not, tag};
 use nom::character::complete::char as char_func;
 
 use crate::ast::{Directive, ListExpanderType, Prefix, StottrVariable};
+use crate::constants::ANONYMOUS_BLANK_NODE;
 use crate::parsing::parsing_ast::{
     PrefixedName, ResolvesToNamedNode, UnresolvedAnnotation, UnresolvedArgument,
     UnresolvedBaseTemplate, UnresolvedConstantLiteral, UnresolvedConstantTerm,
@@ -219,10 +220,19 @@ fn list_as_term(l: &str) -> IResult<&str, UnresolvedStottrTerm> {
 }
 
 fn list(l: &str) -> IResult<&str, Vec<UnresolvedStottrTerm>> {
-    let (l, (_, li, _)) = tuple((tag("("), separated_list0(tag(","), term), tag(")")))(l)?;
+    let (l, (_, li, _)) = tuple((
+        tag("("),
+        separated_list0(tag(","), list_element),
+        tag(")"),
+    ))(l)?;
     Ok((l, li))
 }
 
+fn list_element(e: &str) -> IResult<&str, UnresolvedStottrTerm> {
+    let (e, (_, t, _)) = tuple((multispace0, term, multispace0))(e)?;
+    Ok((e, t))
+}
+
 fn list_expand(l: &str) -> IResult<&str, &str> {
     let (l, expand) = tag("++")(l)?;
     Ok((l, expand))
@@ -387,7 +397,7 @@ fn blank_node(b: &str) -> IResult<&str, BlankNode> {
 
 fn anon(a: &str) -> IResult<&str, String> {
     let (a, _) = tuple((tag("["), tag("]")))(a)?;
-    Ok((a, "AnonymousBlankNode".to_string()))
+    Ok((a, ANONYMOUS_BLANK_NODE.to_string()))
 }
 
 fn blank_node_label(b: &str) -> IResult<&str, String> {
@@ -3,8 +3,8 @@ use crate::ast::{
     Parameter, Signature, Statement, StottrDocument, StottrLiteral, StottrTerm, Template,
 };
 use crate::constants::{
-    OTTR_PREFIX, OTTR_PREFIX_IRI, RDFS_PREFIX, RDFS_PREFIX_IRI, RDF_PREFIX, RDF_PREFIX_IRI,
-    XSD_PREFIX, XSD_PREFIX_IRI,
+    ANONYMOUS_BLANK_NODE, OTTR_PREFIX, OTTR_PREFIX_IRI, RDFS_PREFIX, RDFS_PREFIX_IRI, RDF_PREFIX,
+    RDF_PREFIX_IRI, XSD_PREFIX, XSD_PREFIX_IRI,
 };
 use crate::parsing::parsing_ast::{
     ResolvesToNamedNode, UnresolvedAnnotation, UnresolvedArgument, UnresolvedBaseTemplate,
@@ -13,7 +13,7 @@ use crate::parsing::parsing_ast::{
     UnresolvedStottrDocument, UnresolvedStottrLiteral, UnresolvedStottrTerm, UnresolvedTemplate,
 };
 use log::warn;
-use oxrdf::{IriParseError, NamedNode};
+use oxrdf::{BlankNode, IriParseError, NamedNode};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
@@ -224,7 +224,14 @@ fn resolve_constant_literal(
 ) -> Result<ConstantLiteral, ResolutionError> {
     Ok(match unresolved_constant_literal {
         UnresolvedConstantLiteral::IRI(iri) => ConstantLiteral::IRI(resolve(iri, prefix_map)?),
-        UnresolvedConstantLiteral::BlankNode(bn) => ConstantLiteral::BlankNode(bn.clone()),
+        UnresolvedConstantLiteral::BlankNode(bn) => {
+            //Each occurrence of [] is a distinct blank node
+            if bn.as_str() == ANONYMOUS_BLANK_NODE {
+                ConstantLiteral::BlankNode(BlankNode::default())
+            } else {
+                ConstantLiteral::BlankNode(bn.clone())
+            }
+        }
         UnresolvedConstantLiteral::Literal(lit) => {
             ConstantLiteral::Literal(resolve_stottr_literal(lit, prefix_map)?)
         }
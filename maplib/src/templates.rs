@@ -1,4 +1,5 @@
 pub mod errors;
+mod validation;
 
 use crate::ast::{
     Instance, PType, Parameter, Signature, Statement, StottrDocument, StottrTerm, StottrVariable,
@@ -22,45 +23,7 @@ pub struct TemplateDataset {
 }
 
 impl TemplateDataset {
-    pub fn new(mut documents: Vec<StottrDocument>) -> Result<TemplateDataset, TemplateError> {
-        let mut templates = vec![];
-        let mut ground_instances = vec![];
-        let mut prefix_map = HashMap::new();
-        let mut defined_prefixes = HashSet::new();
-        for d in &mut documents {
-            for (k, v) in d.prefix_map.drain() {
-                if defined_prefixes.contains(&k) {
-                    let mut remove = false;
-                    if let Some(v_prime) = prefix_map.get(&k) {
-                        if &v != v_prime {
-                            remove = true;
-                        }
-                    }
-                    if remove {
-                        prefix_map.remove(&k);
-                        warn!("Prefix {} has conflicting definitions across documents, consider harmonizing", k);
-                    }
-                } else {
-                    defined_prefixes.insert(k.clone());
-                    prefix_map.insert(k, v);
-                }
-            }
-            for i in d.statements.drain(0..d.statements.len()) {
-                match i {
-                    Statement::Template(t) => {
-                        templates.push(t);
-                    }
-                    Statement::Instance(i) => {
-                        ground_instances.push(i);
-                    }
-                }
-            }
-        }
-        let mut td = TemplateDataset {
-            templates,
-            ground_instances,
-            prefix_map,
-        };
+    pub fn new(documents: Vec<StottrDocument>) -> Result<TemplateDataset, TemplateError> {
         //TODO: Put in function, check not exists and consistent...
         let ottr_triple_subject = Parameter {
             optional: false,
@@ -105,13 +68,66 @@ impl TemplateDataset {
             },
             pattern_list: vec![],
         };
-        td.templates.push(ottr_template);
-        //Todo: variable safe, no cycles, referential integrity, no duplicates, well founded
-        //Check ground instances also!!
-        td.infer_types()?;
+        let mut td = TemplateDataset {
+            templates: vec![ottr_template],
+            ground_instances: vec![],
+            prefix_map: HashMap::new(),
+        };
+        td.add_documents(documents)?;
         Ok(td)
     }
 
+    //Merges the templates, ground instances and prefixes of the documents into the dataset.
+    //The dataset is left unchanged if the result is not well-formed.
+    pub fn add_documents(
+        &mut self,
+        mut documents: Vec<StottrDocument>,
+    ) -> Result<(), TemplateError> {
+        let mut td = self.clone();
+        let mut defined_prefixes = HashSet::new();
+        for d in &mut documents {
+            for (k, v) in d.prefix_map.drain() {
+                if defined_prefixes.contains(&k) || td.prefix_map.contains_key(&k) {
+                    let mut remove = false;
+                    if let Some(v_prime) = td.prefix_map.get(&k) {
+                        if &v != v_prime {
+                            remove = true;
+                        }
+                    }
+                    if remove {
+                        td.prefix_map.remove(&k);
+                        warn!("Prefix {} has conflicting definitions across documents, consider harmonizing", k);
+                    }
+                } else {
+                    td.prefix_map.insert(k.clone(), v);
+                }
+                defined_prefixes.insert(k);
+            }
+            for i in d.statements.drain(0..d.statements.len()) {
+                match i {
+                    Statement::Template(t) => {
+                        td.templates.push(t);
+                    }
+                    Statement::Instance(i) => {
+                        td.ground_instances.push(i);
+                    }
+                }
+            }
+        }
+        td.validate()?;
+        td.infer_types()?;
+        *self = td;
+        Ok(())
+    }
+
+    pub fn add_template(&mut self, template: Template) -> Result<(), TemplateError> {
+        self.add_documents(vec![StottrDocument {
+            directives: vec![],
+            statements: vec![Statement::Template(template)],
+            prefix_map: HashMap::new(),
+        }])
+    }
+
     pub fn from_folder<P: AsRef<Path>>(path: P) -> Result<TemplateDataset, TemplateError> {
         let mut docs = vec![];
         let files_result =
@@ -197,7 +213,7 @@ fn infer_template_types(
                                 if argument.list_expand {
                                     if !other_parameter.optional {
                                         changed = changed
-                                            || lub_update(
+                                            || glb_update(
                                                 &template.signature.template_name,
                                                 v,
                                                 my_parameter,
@@ -205,7 +221,7 @@ fn infer_template_types(
                                             )?;
                                     } else {
                                         changed = changed
-                                            || lub_update(
+                                            || glb_update(
                                                 &template.signature.template_name,
                                                 v,
                                                 my_parameter,
@@ -214,7 +230,7 @@ fn infer_template_types(
                                     }
                                 } else {
                                     changed = changed
-                                        || lub_update(
+                                        || glb_update(
                                             &template.signature.template_name,
                                             v,
                                             my_parameter,
@@ -233,7 +249,7 @@ fn infer_template_types(
     Ok(changed)
 }
 
-fn lub_update(
+fn glb_update(
     template_name: &NamedNode,
     variable: &StottrVariable,
     my_parameter: &mut Parameter,
@@ -243,14 +259,14 @@ fn lub_update(
         my_parameter.ptype = Some(right.clone());
         Ok(true)
     } else {
-        if my_parameter.ptype.as_ref().unwrap() != right {
-            let ptype = lub(
+        if !my_parameter.ptype.as_ref().unwrap().is_same_type(right) {
+            let ptype = glb(
                 template_name,
                 variable,
                 my_parameter.ptype.as_ref().unwrap(),
                 right,
             )?;
-            if my_parameter.ptype.as_ref().unwrap() != &ptype {
+            if !my_parameter.ptype.as_ref().unwrap().is_same_type(&ptype) {
                 my_parameter.ptype = Some(ptype);
                 Ok(true)
             } else {
@@ -262,48 +278,39 @@ fn lub_update(
     }
 }
 
-//TODO: LUB ptype...
-fn lub(
+//The greatest lower bound, i.e. the most specific of two compatible types, as a variable must
+//satisfy both
+fn glb(
     template_name: &NamedNode,
     variable: &StottrVariable,
     left: &PType,
     right: &PType,
 ) -> Result<PType, TemplateError> {
-    if left == right {
+    if left.is_subtype_of(right) {
         return Ok(left.clone());
+    } else if right.is_subtype_of(left) {
+        return Ok(right.clone());
     } else {
-        if let PType::NEListType(left_inner) = left {
-            if let PType::ListType(right_inner) = right {
-                return Ok(PType::NEListType(Box::new(lub(
-                    template_name,
-                    variable,
-                    left_inner,
-                    right_inner,
-                )?)));
-            } else if let PType::NEListType(right_inner) = right {
-                return Ok(PType::NEListType(Box::new(lub(
+        match (left, right) {
+            (PType::NEListType(left_inner), PType::ListType(right_inner))
+            | (PType::ListType(left_inner), PType::NEListType(right_inner))
+            | (PType::NEListType(left_inner), PType::NEListType(right_inner)) => {
+                return Ok(PType::NEListType(Box::new(glb(
                     template_name,
                     variable,
                     left_inner,
                     right_inner,
                 )?)));
             }
-        } else if let PType::ListType(left_inner) = left {
-            if let PType::NEListType(right_inner) = right {
-                return Ok(PType::NEListType(Box::new(lub(
-                    template_name,
-                    variable,
-                    left_inner,
-                    right_inner,
-                )?)));
-            } else if let PType::ListType(right_inner) = right {
-                return Ok(PType::ListType(Box::new(lub(
+            (PType::ListType(left_inner), PType::ListType(right_inner)) => {
+                return Ok(PType::ListType(Box::new(glb(
                     template_name,
                     variable,
                     left_inner,
                     right_inner,
                 )?)));
             }
+            _ => {}
         }
     }
     Err(TemplateError::IncompatibleTypes(
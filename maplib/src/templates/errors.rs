@@ -13,6 +13,12 @@ pub enum TemplateError {
     ReadTemplateDirectoryError(io::Error),
     ParsingError(crate::parsing::errors::ParsingError),
     ResolutionError(ResolutionError),
+    UndefinedTemplate(String, Option<String>),
+    DuplicateTemplate(String),
+    CyclicTemplateDependency(Vec<String>),
+    UndeclaredVariable(String, String),
+    UnusedVariable(String, String),
+    InvalidTemplates(Vec<TemplateError>),
 }
 
 impl Display for TemplateError {
@@ -47,6 +53,49 @@ impl Display for TemplateError {
             TemplateError::ResolutionError(r) => {
                 write!(f, "Template resolution error {}", r)
             }
+            TemplateError::UndefinedTemplate(template, calling) => {
+                if let Some(calling) = calling {
+                    write!(
+                        f,
+                        "Template {} called from template {} is not defined",
+                        template, calling
+                    )
+                } else {
+                    write!(
+                        f,
+                        "Template {} called from ground instance is not defined",
+                        template
+                    )
+                }
+            }
+            TemplateError::DuplicateTemplate(template) => {
+                write!(f, "Template {} is defined more than once", template)
+            }
+            TemplateError::CyclicTemplateDependency(cycle) => {
+                write!(
+                    f,
+                    "Templates have a cyclic dependency: {}",
+                    cycle.join(" -> ")
+                )
+            }
+            TemplateError::UndeclaredVariable(template, variable) => {
+                write!(
+                    f,
+                    "Template {} uses variable {} which is not a parameter",
+                    template, variable
+                )
+            }
+            TemplateError::UnusedVariable(template, variable) => {
+                write!(
+                    f,
+                    "Template {} has parameter {} which is not used in its pattern",
+                    template, variable
+                )
+            }
+            TemplateError::InvalidTemplates(errors) => {
+                let errors: Vec<String> = errors.iter().map(|x| x.to_string()).collect();
+                write!(f, "Templates are not well-formed:\n{}", errors.join("\n"))
+            }
         }
     }
 }
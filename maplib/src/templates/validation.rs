@@ -0,0 +1,150 @@
+use super::TemplateDataset;
+use crate::ast::{Instance, StottrTerm, Template};
+use crate::templates::errors::TemplateError;
+use std::collections::{HashMap, HashSet};
+
+impl TemplateDataset {
+    //All violations are reported, a single violation is returned as it is
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        let mut errors = vec![];
+        let mut templates: HashMap<&str, &Template> = HashMap::new();
+        for t in &self.templates {
+            let name = t.signature.template_name.as_str();
+            if templates.insert(name, t).is_some() {
+                errors.push(TemplateError::DuplicateTemplate(name.to_string()));
+            }
+        }
+
+        for t in &self.templates {
+            let template_name = t.signature.template_name.as_str();
+            for i in &t.pattern_list {
+                validate_instance(&templates, i, Some(template_name), &mut errors);
+            }
+            validate_variables(t, &mut errors);
+        }
+        for i in &self.ground_instances {
+            validate_instance(&templates, i, None, &mut errors);
+        }
+        validate_no_cycles(&templates, &mut errors);
+        if errors.len() > 1 {
+            Err(TemplateError::InvalidTemplates(errors))
+        } else if let Some(e) = errors.pop() {
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn validate_instance(
+    templates: &HashMap<&str, &Template>,
+    instance: &Instance,
+    calling: Option<&str>,
+    errors: &mut Vec<TemplateError>,
+) {
+    let called = instance.template_name.as_str();
+    if let Some(other) = templates.get(called) {
+        if instance.argument_list.len() != other.signature.parameter_list.len() {
+            errors.push(TemplateError::InconsistentNumberOfArguments(
+                calling.unwrap_or("ground instance").to_string(),
+                called.to_string(),
+                instance.argument_list.len(),
+                other.signature.parameter_list.len(),
+            ));
+        }
+    } else {
+        errors.push(TemplateError::UndefinedTemplate(
+            called.to_string(),
+            calling.map(|x| x.to_string()),
+        ));
+    }
+}
+
+fn validate_variables(template: &Template, errors: &mut Vec<TemplateError>) {
+    let template_name = template.signature.template_name.as_str();
+    let declared: HashSet<&String> = template
+        .signature
+        .parameter_list
+        .iter()
+        .map(|p| &p.stottr_variable.name)
+        .collect();
+    let mut used = HashSet::new();
+    for i in &template.pattern_list {
+        for a in &i.argument_list {
+            collect_variables(&a.term, &mut used);
+        }
+    }
+    let mut undeclared: Vec<&&String> = used.iter().filter(|u| !declared.contains(*u)).collect();
+    undeclared.sort();
+    for u in undeclared {
+        errors.push(TemplateError::UndeclaredVariable(
+            template_name.to_string(),
+            u.to_string(),
+        ));
+    }
+    //Templates without patterns are base templates, their parameters are not used in a pattern
+    if !template.pattern_list.is_empty() {
+        for p in &template.signature.parameter_list {
+            if !used.contains(&p.stottr_variable.name) {
+                errors.push(TemplateError::UnusedVariable(
+                    template_name.to_string(),
+                    p.stottr_variable.name.clone(),
+                ));
+            }
+        }
+    }
+}
+
+fn collect_variables<'a>(term: &'a StottrTerm, variables: &mut HashSet<&'a String>) {
+    match term {
+        StottrTerm::Variable(v) => {
+            variables.insert(&v.name);
+        }
+        StottrTerm::ConstantTerm(_) => {}
+        StottrTerm::List(l) => {
+            for t in l {
+                collect_variables(t, variables);
+            }
+        }
+    }
+}
+
+fn validate_no_cycles(templates: &HashMap<&str, &Template>, errors: &mut Vec<TemplateError>) {
+    let mut names: Vec<&&str> = templates.keys().collect();
+    names.sort();
+    let mut finished = HashSet::new();
+    for name in names {
+        let mut path = vec![];
+        find_cycles(templates, name, &mut path, &mut finished, errors);
+    }
+}
+
+fn find_cycles<'a>(
+    templates: &HashMap<&'a str, &'a Template>,
+    name: &'a str,
+    path: &mut Vec<&'a str>,
+    finished: &mut HashSet<&'a str>,
+    errors: &mut Vec<TemplateError>,
+) {
+    if finished.contains(name) {
+        return;
+    }
+    if let Some(pos) = path.iter().position(|x| *x == name) {
+        let mut cycle: Vec<String> = path[pos..].iter().map(|x| x.to_string()).collect();
+        cycle.push(name.to_string());
+        errors.push(TemplateError::CyclicTemplateDependency(cycle));
+        return;
+    }
+    //Undefined templates are reported by validate_instance
+    let template = if let Some(template) = templates.get(name) {
+        template
+    } else {
+        return;
+    };
+    path.push(name);
+    for i in &template.pattern_list {
+        find_cycles(templates, i.template_name.as_str(), path, finished, errors);
+    }
+    path.pop();
+    finished.insert(name);
+}
@@ -4,17 +4,28 @@ extern crate core;
 mod utils;
 
 use crate::utils::triples_from_file;
+use maplib::document::document_from_str;
+use maplib::errors::MaplibError;
+use maplib::mapping::errors::MappingError;
+use maplib::mapping::minting::{MintingOptions, MintingStrategy};
 use maplib::mapping::{ExpandOptions, Mapping};
+use maplib::templates::errors::TemplateError;
+use maplib::templates::TemplateDataset;
 use oxrdf::{Literal, NamedNode, Subject, Term, Triple};
 use polars::frame::DataFrame;
-use polars::prelude::{col, IntoLazy};
+use polars::prelude::{as_struct, col, IntoLazy, NamedFrom, ParquetWriter};
 use polars::series::Series;
-use polars_core::prelude::{AnyValue, TimeUnit};
+use polars_core::prelude::{AnyValue, DataType, TimeUnit};
+use representation::RDFNodeType;
 use rstest::*;
 use serial_test::serial;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::path::PathBuf;
+use triplestore::errors::TriplestoreError;
+use triplestore::native_parquet_write::NativeParquetWriteOptions;
+use triplestore::sparql::errors::SparqlError;
+use triplestore::sparql::QueryResult;
 
 #[fixture]
 fn testdata_path() -> PathBuf {
@@ -987,3 +998,2876 @@ fn test_default_list() {
     ]);
     assert_eq!(expected_triples_set, actual_triples_set);
 }
+
+#[rstest]
+#[serial]
+fn test_ground_instances() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Nested [?subject, ?myVar] :: {
+    ottr:Triple(?subject, ex:hasNumber, ?myVar)
+} .
+ex:Nested(ex:obj1, 1) .
+ottr:Triple(ex:obj2, ex:relatesTo, ex:obj1) .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples.into_iter());
+    let expected_triples_set = HashSet::from([
+        Triple {
+            subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj1")),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#hasNumber"),
+            object: Term::Literal(Literal::new_typed_literal(
+                "1",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#integer"),
+            )),
+        },
+        Triple {
+            subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj2")),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#relatesTo"),
+            object: Term::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj1")),
+        },
+    ]);
+    assert_eq!(expected_triples_set, actual_triples_set);
+}
+
+#[rstest]
+#[serial]
+fn test_blank_nodes_fresh_per_instance() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Measured [?subject, ?value] :: {
+    ottr:Triple(?subject, ex:hasMeasurement, _:m),
+    ottr:Triple(_:m, ex:hasValue, ?value),
+    ottr:Triple(_:m, ex:hasSource, [])
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut subject =
+        Series::from_iter(["http://example.net/ns#obj1", "http://example.net/ns#obj2"]);
+    subject.rename("subject");
+    let mut value = Series::from_iter([1, 2]);
+    value.rename("value");
+    let series = [subject, value];
+    let df = DataFrame::from_iter(series);
+    mapping
+        .expand(
+            "http://example.net/ns#Measured",
+            df,
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert_eq!(triples.len(), 6);
+    let measurements: HashMap<Subject, Term> = triples
+        .iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasMeasurement")
+        .map(|t| (t.subject.clone(), t.object.clone()))
+        .collect();
+    let values: HashMap<Term, Term> = triples
+        .iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasValue")
+        .map(|t| (Term::from(t.subject.clone()), t.object.clone()))
+        .collect();
+    let sources: HashSet<Term> = triples
+        .iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasSource")
+        .map(|t| t.object.clone())
+        .collect();
+    assert_eq!(sources.len(), 2);
+    assert!(sources.iter().all(|s| matches!(s, Term::BlankNode(_))));
+    let m1 = measurements
+        .get(&Subject::NamedNode(NamedNode::new_unchecked(
+            "http://example.net/ns#obj1",
+        )))
+        .unwrap();
+    let m2 = measurements
+        .get(&Subject::NamedNode(NamedNode::new_unchecked(
+            "http://example.net/ns#obj2",
+        )))
+        .unwrap();
+    assert!(matches!(m1, Term::BlankNode(_)));
+    assert_ne!(m1, m2);
+    assert!(!sources.contains(m1) && !sources.contains(m2));
+    assert_eq!(
+        values.get(m1).unwrap(),
+        &Term::Literal(Literal::new_typed_literal(
+            "1",
+            NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#int"),
+        ))
+    );
+    assert_eq!(
+        values.get(m2).unwrap(),
+        &Term::Literal(Literal::new_typed_literal(
+            "2",
+            NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#int"),
+        ))
+    );
+}
+
+#[rstest]
+#[serial]
+fn test_mint_iris() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?card, ?id] :: {
+    ottr:Triple(?person, ex:hasId, ?id),
+    ottr:Triple(?person, ex:hasCard, ?card)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut id = Series::from_iter(["a1", "b2"]);
+    id.rename("id");
+    let df = DataFrame::from_iter([id]);
+    let options = ExpandOptions {
+        minting: Some(HashMap::from([
+            (
+                "person".to_string(),
+                MintingOptions {
+                    namespace: "http://example.net/ns#".to_string(),
+                    strategy: MintingStrategy::Format("person_{id}".to_string()),
+                },
+            ),
+            (
+                "card".to_string(),
+                MintingOptions {
+                    namespace: "http://example.net/card#".to_string(),
+                    strategy: MintingStrategy::Hash(vec!["id".to_string()]),
+                },
+            ),
+        ])),
+        ..Default::default()
+    };
+    mapping
+        .expand("http://example.net/ns#Person", df.clone(), options.clone())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert_eq!(triples.len(), 4);
+    let persons: HashSet<Subject> = triples.iter().map(|t| t.subject.clone()).collect();
+    assert_eq!(
+        persons,
+        HashSet::from([
+            Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#person_a1")),
+            Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#person_b2")),
+        ])
+    );
+    let cards: HashSet<Term> = triples
+        .iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasCard")
+        .map(|t| t.object.clone())
+        .collect();
+    assert_eq!(cards.len(), 2);
+    for c in &cards {
+        if let Term::NamedNode(nn) = c {
+            assert!(nn.as_str().starts_with("http://example.net/card#"));
+        } else {
+            panic!("Expected an IRI, got {}", c);
+        }
+    }
+
+    //Hashing is stable, so expanding again adds no new triples
+    mapping
+        .expand("http://example.net/ns#Person", df, options.clone())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert_eq!(triples.len(), 4);
+    //The hash is fixed, so that the minted IRIs do not change between versions
+    assert!(triples.iter().any(|t| t.to_string()
+        == "<http://example.net/ns#person_a1> <http://example.net/ns#hasCard> <http://example.net/card#a4ccefc60d0b91fbb8fd8403c5ec973c>"));
+
+    //Values are percent-encoded in formatted IRIs
+    let mut id = Series::from_iter(["Main St"]);
+    id.rename("id");
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([id.clone()]),
+            options.clone(),
+        )
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert!(triples
+        .iter()
+        .any(|t| t.subject.to_string() == "<http://example.net/ns#person_Main%20St>"));
+
+    let mut invalid_options = options;
+    invalid_options.minting.as_mut().unwrap().insert(
+        "person".to_string(),
+        MintingOptions {
+            namespace: "not an iri".to_string(),
+            strategy: MintingStrategy::Uuid,
+        },
+    );
+    assert!(matches!(
+        mapping.expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([id]),
+            invalid_options
+        ),
+        Err(MappingError::InvalidMintingStrategy(..))
+    ));
+}
+
+#[rstest]
+#[serial]
+fn test_mint_iris_from_minted_columns() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?card, ?id] :: {
+    ottr:Triple(?person, ex:hasId, ?id),
+    ottr:Triple(?person, ex:hasCard, ?card)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut id = Series::from_iter(["a1", "b2"]);
+    id.rename("id");
+    let df = DataFrame::from_iter([id]);
+    let person = MintingOptions {
+        namespace: "http://example.net/ns#".to_string(),
+        strategy: MintingStrategy::Format("person_{id}".to_string()),
+    };
+    //The card is minted from the minted person
+    let card = MintingOptions {
+        namespace: "http://example.net/card#".to_string(),
+        strategy: MintingStrategy::Hash(vec!["person".to_string()]),
+    };
+    let options = ExpandOptions {
+        minting: Some(HashMap::from([
+            ("person".to_string(), person),
+            ("card".to_string(), card.clone()),
+        ])),
+        ..Default::default()
+    };
+    mapping
+        .expand("http://example.net/ns#Person", df.clone(), options)
+        .unwrap();
+    let cards: HashSet<String> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasCard")
+        .map(|t| t.object.to_string())
+        .collect();
+    assert_eq!(cards.len(), 2);
+
+    let cyclic = MintingOptions {
+        namespace: "http://example.net/ns#".to_string(),
+        strategy: MintingStrategy::Format("person_{card}".to_string()),
+    };
+    let options = ExpandOptions {
+        minting: Some(HashMap::from([
+            ("person".to_string(), cyclic),
+            ("card".to_string(), card),
+        ])),
+        ..Default::default()
+    };
+    assert!(matches!(
+        mapping.expand("http://example.net/ns#Person", df, options),
+        Err(MappingError::InvalidMintingStrategy(..))
+    ));
+}
+
+#[rstest]
+#[serial]
+fn test_none_drops_instance() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ??age] :: {
+    ex:Aged(?person, ?age),
+    ottr:Triple(?person, ex:isA, ex:Person)
+} .
+ex:Aged [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age),
+    ottr:Triple(?person, ex:hasAgeRecord, ex:record)
+} .
+ex:Aged(ex:nobody, none) .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let age = Series::new("age", [Some(30i32), None]);
+    let df = DataFrame::from_iter([person, age]);
+    mapping
+        .expand("http://example.net/ns#Person", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples.into_iter());
+    let ann = Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#ann"));
+    let bob = Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#bob"));
+    let person_type = Term::NamedNode(NamedNode::new_unchecked("http://example.net/ns#Person"));
+    let expected_triples_set = HashSet::from([
+        Triple {
+            subject: ann.clone(),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#isA"),
+            object: person_type.clone(),
+        },
+        Triple {
+            subject: bob,
+            predicate: NamedNode::new_unchecked("http://example.net/ns#isA"),
+            object: person_type,
+        },
+        Triple {
+            subject: ann.clone(),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#hasAge"),
+            object: Term::Literal(Literal::new_typed_literal(
+                "30",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#int"),
+            )),
+        },
+        Triple {
+            subject: ann,
+            predicate: NamedNode::new_unchecked("http://example.net/ns#hasAgeRecord"),
+            object: Term::NamedNode(NamedNode::new_unchecked("http://example.net/ns#record")),
+        },
+    ]);
+    assert_eq!(expected_triples_set, actual_triples_set);
+}
+
+#[rstest]
+#[case::undefined_template("ex:A [?x] :: { ex:Missing(?x) } .", "UndefinedTemplate")]
+#[case::duplicate_template(
+    "ex:A [?x] :: { ottr:Triple(?x, ex:p, ex:o) } . ex:A [?x] :: { ottr:Triple(?x, ex:q, ex:o) } .",
+    "DuplicateTemplate"
+)]
+#[case::cycle(
+    "ex:A [?x] :: { ex:B(?x) } . ex:B [?x] :: { ex:A(?x) } .",
+    "CyclicTemplateDependency"
+)]
+#[case::undeclared_variable("ex:A [?x] :: { ottr:Triple(?x, ex:p, ?y) } .", "UndeclaredVariable")]
+#[case::unused_variable("ex:A [?x, ?y] :: { ottr:Triple(?x, ex:p, ex:o) } .", "UnusedVariable")]
+#[case::argument_count(
+    "ex:A [?x] :: { ottr:Triple(?x, ex:p) } .",
+    "InconsistentNumberOfArguments"
+)]
+#[case::ground_instance_undefined("ex:Missing(ex:a) .", "UndefinedTemplate")]
+#[serial]
+fn test_template_dataset_validation(#[case] templates: &str, #[case] expected: &str) {
+    let stottr = format!("@prefix ex:<http://example.net/ns#>.\n{}", templates);
+    let res = Mapping::from_str(&stottr, None);
+    if let Err(MaplibError::TemplateError(e)) = res {
+        let ok = match e {
+            TemplateError::UndefinedTemplate(..) => expected == "UndefinedTemplate",
+            TemplateError::DuplicateTemplate(..) => expected == "DuplicateTemplate",
+            TemplateError::CyclicTemplateDependency(..) => expected == "CyclicTemplateDependency",
+            TemplateError::UndeclaredVariable(..) => expected == "UndeclaredVariable",
+            TemplateError::UnusedVariable(..) => expected == "UnusedVariable",
+            TemplateError::InconsistentNumberOfArguments(..) => {
+                expected == "InconsistentNumberOfArguments"
+            }
+            _ => false,
+        };
+        assert!(ok, "Expected {} but got {}", expected, e);
+    } else {
+        panic!("Expected a template error");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_template_dataset_validation_reports_all_errors() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:A [?x, ?y] :: { ex:Missing(?x) } .
+ex:B [?x] :: { ottr:Triple(?x, ex:p, ?z) } .
+ex:Missing(ex:a) .
+"#;
+    let res = Mapping::from_str(&stottr, None);
+    if let Err(MaplibError::TemplateError(TemplateError::InvalidTemplates(errors))) = res {
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(errors[0], TemplateError::UndefinedTemplate(..)));
+        assert!(matches!(errors[1], TemplateError::UnusedVariable(..)));
+        assert!(matches!(errors[2], TemplateError::UndeclaredVariable(..)));
+        assert!(matches!(errors[3], TemplateError::UndefinedTemplate(..)));
+    } else {
+        panic!("Expected all template errors");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_list_term_with_variables() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Group [?group, xsd:anyURI ?m1, xsd:anyURI ?m2] :: {
+    cross | ottr:Triple(?group, ex:hasMember, ++(?m1, ?m2, ex:leader)),
+    ex:Listed(?group, (?m1, ?m2))
+} .
+ex:Listed [?group, ?members] :: {
+    cross | ottr:Triple(?group, ex:listed, ++?members)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut group = Series::from_iter(["http://example.net/ns#g1", "http://example.net/ns#g2"]);
+    group.rename("group");
+    let mut m1 = Series::from_iter(["http://example.net/ns#a", "http://example.net/ns#c"]);
+    m1.rename("m1");
+    let mut m2 = Series::from_iter(["http://example.net/ns#b", "http://example.net/ns#d"]);
+    m2.rename("m2");
+    let df = DataFrame::from_iter([group, m1, m2]);
+    mapping
+        .expand("http://example.net/ns#Group", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples.into_iter());
+    let mut expected_triples = vec![];
+    for (g, members) in [("g1", ["a", "b", "leader"]), ("g2", ["c", "d", "leader"])] {
+        let subject = Subject::NamedNode(NamedNode::new_unchecked(format!(
+            "http://example.net/ns#{}",
+            g
+        )));
+        for m in members {
+            let object = Term::NamedNode(NamedNode::new_unchecked(format!(
+                "http://example.net/ns#{}",
+                m
+            )));
+            expected_triples.push(Triple {
+                subject: subject.clone(),
+                predicate: NamedNode::new_unchecked("http://example.net/ns#hasMember"),
+                object: object.clone(),
+            });
+            if m != "leader" {
+                expected_triples.push(Triple {
+                    subject: subject.clone(),
+                    predicate: NamedNode::new_unchecked("http://example.net/ns#listed"),
+                    object,
+                });
+            }
+        }
+    }
+    let expected_triples_set = HashSet::from_iter(expected_triples.into_iter());
+    assert_eq!(expected_triples_set, actual_triples_set);
+}
+
+#[rstest]
+#[case::zip_min("zipMin", vec![("s1", "x1", Some(10))])]
+#[case::zip_max(
+    "zipMax",
+    vec![("s1", "x1", Some(10)), ("s1", "x2", None), ("s1", "x3", None)]
+)]
+#[serial]
+fn test_zip_ragged_lists(#[case] expander: &str, #[case] expected: Vec<(&str, &str, Option<i32>)>) {
+    let stottr = format!(
+        r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Zipped [xsd:anyURI ?s, List<xsd:anyURI> ?l1, List<xsd:int> ?l2] :: {{
+    {} | ex:Pair(?s, ++?l1, ++?l2)
+}} .
+ex:Pair [xsd:anyURI ?s, ??a, ??b] :: {{
+    ottr:Triple(?s, ex:has, ?a),
+    ottr:Triple(?a, ex:value, ?b)
+}} .
+"#,
+        expander
+    );
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let s = Series::new(
+        "s",
+        ["http://example.net/ns#s1", "http://example.net/ns#s2"],
+    );
+    let l1 = Series::new(
+        "l1",
+        [
+            Series::new(
+                "",
+                [
+                    "http://example.net/ns#x1",
+                    "http://example.net/ns#x2",
+                    "http://example.net/ns#x3",
+                ],
+            ),
+            Series::new_empty("", &DataType::Utf8),
+        ],
+    );
+    let l2 = Series::new(
+        "l2",
+        [Series::new("", [10i32]), Series::new("", [20i32, 30])],
+    );
+    let df = DataFrame::new(vec![s, l1, l2]).unwrap();
+    mapping
+        .expand("http://example.net/ns#Zipped", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples.into_iter());
+    let mut expected_triples = vec![];
+    for (s, a, b) in expected {
+        let a_node = NamedNode::new_unchecked(format!("http://example.net/ns#{}", a));
+        expected_triples.push(Triple {
+            subject: Subject::NamedNode(NamedNode::new_unchecked(format!(
+                "http://example.net/ns#{}",
+                s
+            ))),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#has"),
+            object: Term::NamedNode(a_node.clone()),
+        });
+        if let Some(b) = b {
+            expected_triples.push(Triple {
+                subject: Subject::NamedNode(a_node),
+                predicate: NamedNode::new_unchecked("http://example.net/ns#value"),
+                object: Term::Literal(Literal::new_typed_literal(
+                    b.to_string(),
+                    NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#int"),
+                )),
+            });
+        }
+    }
+    let expected_triples_set = HashSet::from_iter(expected_triples.into_iter());
+    assert_eq!(expected_triples_set, actual_triples_set);
+}
+
+#[rstest]
+#[serial]
+fn test_subtype_inference() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Both [?x] :: {
+    ex:Long(?x),
+    ex:Int(?x)
+} .
+ex:Long [xsd:long ?x] :: {
+    ottr:Triple(ex:obj, ex:hasLong, ?x)
+} .
+ex:Int [xsd:int ?x] :: {
+    ottr:Triple(ex:obj, ex:hasInt, ?x)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let df = DataFrame::new(vec![Series::new("x", [1i32])]).unwrap();
+    mapping
+        .expand("http://example.net/ns#Both", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert_eq!(triples.len(), 2);
+    for t in triples {
+        assert_eq!(
+            t.object,
+            Term::Literal(Literal::new_typed_literal(
+                "1",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#int"),
+            ))
+        );
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_subtype_columns_are_cast() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Long [xsd:anyURI ?s, xsd:long ?x] :: {
+    ottr:Triple(?s, ex:hasLong, ?x)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let df = DataFrame::new(vec![
+        Series::new("s", ["http://example.net/ns#s1"]),
+        Series::new("x", [1i32]),
+    ])
+    .unwrap();
+    mapping
+        .expand("http://example.net/ns#Long", df, ExpandOptions::default())
+        .unwrap();
+    let df = DataFrame::new(vec![
+        Series::new("s", ["http://example.net/ns#s2"]),
+        Series::new("x", [2i64]),
+    ])
+    .unwrap();
+    mapping
+        .expand("http://example.net/ns#Long", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    assert_eq!(triples.len(), 2);
+    for t in triples {
+        if let Term::Literal(l) = t.object {
+            assert_eq!(l.datatype().as_str(), "http://www.w3.org/2001/XMLSchema#long");
+        } else {
+            panic!("Expected a literal");
+        }
+    }
+    if let QueryResult::Select(df) = mapping
+        .triplestore
+        .query("PREFIX ex:<http://example.net/ns#> SELECT ?x WHERE { ?s ex:hasLong ?x }")
+        .unwrap()
+    {
+        assert_eq!(df.column("x").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(df.height(), 2);
+    } else {
+        panic!("Expected a select result");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_nelist_rejects_empty_list() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Listed [xsd:anyURI ?s, NEList<xsd:int> ?l] :: {
+    cross | ottr:Triple(?s, ex:hasNumber, ++?l)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let s = Series::new(
+        "s",
+        ["http://example.net/ns#s1", "http://example.net/ns#s2"],
+    );
+    let l = Series::new(
+        "l",
+        [
+            Series::new("", [1i32, 2]),
+            Series::new_empty("", &DataType::Int32),
+        ],
+    );
+    let df = DataFrame::new(vec![s, l]).unwrap();
+    let res = mapping.expand("http://example.net/ns#Listed", df, ExpandOptions::default());
+    if let Err(MappingError::NonEmptyListColumnHasEmptyList(c, empty)) = res {
+        assert_eq!(c, "l");
+        assert_eq!(empty.height(), 1);
+    } else {
+        panic!("Expected an empty list error");
+    }
+
+    //Lists in nested template arguments are checked as well
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Nested [xsd:anyURI ?s, List<List<xsd:int>> ?ls] :: {
+    cross | ex:Listed(?s, ++?ls)
+} .
+ex:Listed [xsd:anyURI ?s, NEList<xsd:int> ?l] :: {
+    cross | ottr:Triple(?s, ex:hasNumber, ++?l)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let s = Series::new("s", ["http://example.net/ns#s1"]);
+    let ls = Series::new(
+        "ls",
+        [Series::new(
+            "",
+            [
+                Series::new("", [1i32, 2]),
+                Series::new_empty("", &DataType::Int32),
+            ],
+        )],
+    );
+    let df = DataFrame::new(vec![s, ls]).unwrap();
+    let res = mapping.expand("http://example.net/ns#Nested", df, ExpandOptions::default());
+    if let Err(MappingError::NonEmptyListColumnHasEmptyList(c, empty)) = res {
+        assert_eq!(c, "l");
+        assert_eq!(empty.height(), 1);
+    } else {
+        panic!("Expected an empty list error");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_column_datatype_mismatch() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Numbered [xsd:int ?x] :: {
+    ottr:Triple(ex:obj, ex:hasNumber, ?x)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let df = DataFrame::new(vec![Series::new("x", ["1"])]).unwrap();
+    let res = mapping.expand(
+        "http://example.net/ns#Numbered",
+        df,
+        ExpandOptions::default(),
+    );
+    assert!(matches!(res, Err(MappingError::ColumnDataTypeMismatch(..))));
+}
+
+#[rstest]
+#[serial]
+fn test_invalid_lexical_forms() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Numbered [xsd:integer ?x, xsd:decimal ?y] :: {
+    ottr:Triple(ex:obj, ex:hasInteger, ?x),
+    ottr:Triple(ex:obj, ex:hasDecimal, ?y)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let df = DataFrame::new(vec![
+        Series::new("x", ["1", "-20"]),
+        Series::new("y", ["1.5", ".5"]),
+    ])
+    .unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Numbered",
+            df,
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 4);
+
+    let df = DataFrame::new(vec![
+        Series::new("x", ["1", "abc"]),
+        Series::new("y", ["1.5", "1.5"]),
+    ])
+    .unwrap();
+    let err = mapping
+        .expand(
+            "http://example.net/ns#Numbered",
+            df.clone(),
+            ExpandOptions::default(),
+        )
+        .unwrap_err();
+    if let MappingError::InvalidLexicalForm(c, _, invalid) = err {
+        assert_eq!(c, "x");
+        assert_eq!(invalid, Series::new("x", ["abc"]));
+    } else {
+        panic!("Expected an invalid lexical form, got {}", err);
+    }
+
+    let report = mapping
+        .expand(
+            "http://example.net/ns#Numbered",
+            df,
+            ExpandOptions {
+                quarantine_invalid_rows: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(report.rejected_rows.unwrap().height(), 1);
+}
+
+#[rstest]
+#[serial]
+fn test_coerce_datatypes() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Measured [xsd:long ?x, xsd:dateTime ?t] :: {
+    ottr:Triple(ex:obj, ex:hasNumber, ?x),
+    ottr:Triple(ex:obj, ex:measuredAt, ?t)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let df = DataFrame::new(vec![
+        Series::new("x", [1i32]),
+        Series::new("t", ["2023-01-02T03:04:05"]),
+    ])
+    .unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Measured",
+            df,
+            ExpandOptions {
+                coerce_datatypes: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples);
+    let expected_triples_set = HashSet::from([
+        Triple {
+            subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj")),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#hasNumber"),
+            object: Term::Literal(Literal::new_typed_literal(
+                "1",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#long"),
+            )),
+        },
+        Triple {
+            subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj")),
+            predicate: NamedNode::new_unchecked("http://example.net/ns#measuredAt"),
+            object: Term::Literal(Literal::new_typed_literal(
+                "2023-01-02T03:04:05",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#dateTime"),
+            )),
+        },
+    ]);
+    assert_eq!(expected_triples_set, actual_triples_set);
+}
+
+#[rstest]
+#[serial]
+fn test_mapping_report() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ??age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age),
+    ottr:Triple(?person, ex:isA, ex:Person)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter([
+        "http://example.net/ns#ann",
+        "http://example.net/ns#ann",
+        "http://example.net/ns#bob",
+    ]);
+    person.rename("person");
+    let age = Series::new("age", [Some(30i32), Some(30i32), None]);
+    let df = DataFrame::from_iter([person, age]);
+    let report = mapping
+        .expand("http://example.net/ns#Person", df, ExpandOptions::default())
+        .unwrap();
+    let expected_triples_added = HashMap::from([
+        (
+            "http://example.net/ns#hasAge".to_string(),
+            HashMap::from([(
+                RDFNodeType::Literal(NamedNode::new_unchecked(
+                    "http://www.w3.org/2001/XMLSchema#int",
+                )),
+                1,
+            )]),
+        ),
+        (
+            "http://example.net/ns#isA".to_string(),
+            HashMap::from([(RDFNodeType::IRI, 2)]),
+        ),
+    ]);
+    assert_eq!(expected_triples_added, report.triples_added);
+    assert_eq!(1, report.rows_dropped_null);
+    assert_eq!(2, report.duplicates_removed);
+    assert!(!report.used_caching);
+}
+
+#[rstest]
+#[serial]
+fn test_mapping_report_counts_dropped_rows_once() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Age [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+ex:Person [?person, ??age, ??name] :: {
+    ottr:Triple(?person, ex:hasName, ?name),
+    ottr:Triple(?person, ex:hasNameAgain, ?name),
+    ex:Age(?person, ?age)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter([
+        "http://example.net/ns#ann",
+        "http://example.net/ns#bob",
+        "http://example.net/ns#cat",
+    ]);
+    person.rename("person");
+    let age = Series::new("age", [Some(30i32), None, None]);
+    let name = Series::new("name", [Some("Ann"), None, Some("Cat")]);
+    let df = DataFrame::from_iter([person, age, name]);
+    let report = mapping
+        .expand("http://example.net/ns#Person", df, ExpandOptions::default())
+        .unwrap();
+    //Bob is dropped from both name triples and the age instance, but is one input row
+    assert_eq!(2, report.rows_dropped_null);
+    assert_eq!(0, report.duplicates_removed);
+}
+
+#[rstest]
+#[serial]
+fn test_quarantine_invalid_rows() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Knows [xsd:anyURI ?person, ! xsd:anyURI ?friend] :: {
+    ottr:Triple(?person, ex:knows, ?friend)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let person = Series::new(
+        "person",
+        [
+            Some("http://example.net/ns#ann"),
+            None,
+            Some("http://example.net/ns#ann"),
+        ],
+    );
+    let friend = Series::new(
+        "friend",
+        [
+            "http://example.net/ns#bob",
+            "http://example.net/ns#bob",
+            "_:b",
+        ],
+    );
+    let df = DataFrame::from_iter([person, friend]);
+    let report = mapping
+        .expand(
+            "http://example.net/ns#Knows",
+            df,
+            ExpandOptions {
+                quarantine_invalid_rows: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let expected_triples = vec![Triple {
+        subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#ann")),
+        predicate: NamedNode::new_unchecked("http://example.net/ns#knows"),
+        object: Term::NamedNode(NamedNode::new_unchecked("http://example.net/ns#bob")),
+    }];
+    assert_eq!(expected_triples, triples);
+    let rejected_rows = report.rejected_rows.unwrap();
+    let reasons: Vec<Option<&str>> = rejected_rows
+        .column("rejection_reason")
+        .unwrap()
+        .utf8()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(
+        vec![
+            Some("Non-optional column person is null"),
+            Some("Non-blank column friend has a blank node"),
+        ],
+        reasons
+    );
+}
+
+#[rstest]
+#[serial]
+fn test_quarantine_returns_input_rows() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Named [?person, ?name] :: {
+    ottr:Triple(?person, ex:hasName, ?name)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let name = Series::new("name", [Some("Ann"), None]);
+    let lang = Series::new("lang", ["en", "nb"]);
+    let df = DataFrame::from_iter([name, lang]);
+    let report = mapping
+        .expand(
+            "http://example.net/ns#Named",
+            df,
+            ExpandOptions {
+                quarantine_invalid_rows: true,
+                language_tag_columns: Some(HashMap::from([(
+                    "name".to_string(),
+                    "lang".to_string(),
+                )])),
+                minting: Some(HashMap::from([(
+                    "person".to_string(),
+                    MintingOptions {
+                        namespace: "http://example.net/person#".to_string(),
+                        strategy: MintingStrategy::Uuid,
+                    },
+                )])),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 1);
+    //The minted column and the renamed language tag column are not part of the rejected rows
+    let rejected_rows = report.rejected_rows.unwrap();
+    assert_eq!(
+        rejected_rows.get_column_names(),
+        vec!["name", "lang", "rejection_reason"]
+    );
+    assert_eq!(
+        rejected_rows.column("lang").unwrap(),
+        &Series::new("lang", ["nb"])
+    );
+}
+
+#[rstest]
+#[serial]
+fn test_language_tags_and_datatypes_from_columns() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Product [xsd:anyURI ?product, ?name, ?weight] :: {
+    ex:Named(?product, ?name),
+    ottr:Triple(?product, ex:weight, ?weight)
+} .
+ex:Named [?x, ?n] :: {
+    ottr:Triple(?x, ex:name, ?n)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let product = Series::new(
+        "product",
+        ["http://example.net/ns#chair", "http://example.net/ns#table"],
+    );
+    let name = Series::new("name", ["Stol", "Table"]);
+    let lang = Series::new("lang", [Some("no"), None]);
+    let weight = Series::new("weight", ["12.5", "30.5"]);
+    let weight_datatype = Series::new(
+        "weight_datatype",
+        [
+            "http://www.w3.org/2001/XMLSchema#double",
+            "http://www.w3.org/2001/XMLSchema#double",
+        ],
+    );
+    let df = DataFrame::from_iter([product, name, lang, weight, weight_datatype]);
+    let options = ExpandOptions {
+        language_tag_columns: Some(HashMap::from([("name".to_string(), "lang".to_string())])),
+        datatype_columns: Some(HashMap::from([(
+            "weight".to_string(),
+            "weight_datatype".to_string(),
+        )])),
+        ..Default::default()
+    };
+    mapping
+        .expand("http://example.net/ns#Product", df.clone(), options.clone())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples);
+    let chair = Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#chair"));
+    let table = Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#table"));
+    let name = NamedNode::new_unchecked("http://example.net/ns#name");
+    let weight = NamedNode::new_unchecked("http://example.net/ns#weight");
+    let expected_triples_set = HashSet::from([
+        Triple {
+            subject: chair.clone(),
+            predicate: name.clone(),
+            object: Term::Literal(Literal::new_language_tagged_literal_unchecked("Stol", "no")),
+        },
+        Triple {
+            subject: table.clone(),
+            predicate: name,
+            object: Term::Literal(Literal::new_simple_literal("Table")),
+        },
+        Triple {
+            subject: chair,
+            predicate: weight.clone(),
+            object: Term::Literal(Literal::new_typed_literal(
+                "12.5",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#double"),
+            )),
+        },
+        Triple {
+            subject: table,
+            predicate: weight,
+            object: Term::Literal(Literal::new_typed_literal(
+                "30.5",
+                NamedNode::new_unchecked("http://www.w3.org/2001/XMLSchema#double"),
+            )),
+        },
+    ]);
+    assert_eq!(expected_triples_set, actual_triples_set);
+    let query = "PREFIX ex:<http://example.net/ns#> SELECT ?p ?w WHERE { ?p ex:weight ?w }";
+    if let QueryResult::Select(df) = mapping.triplestore.query(query).unwrap() {
+        assert_eq!(df.height(), 2);
+    } else {
+        panic!("Expected select result");
+    }
+
+    //Objects of a predicate with different datatypes could not be queried
+    let mixed_datatype = Series::new(
+        "weight_datatype",
+        [
+            "http://www.w3.org/2001/XMLSchema#double",
+            "http://www.w3.org/2001/XMLSchema#int",
+        ],
+    );
+    let mut mixed = df.clone();
+    mixed.with_column(mixed_datatype).unwrap();
+    mixed
+        .with_column(Series::new("weight", ["12.5", "30"]))
+        .unwrap();
+    let mut new_mapping = Mapping::from_str(&stottr, None).unwrap();
+    let err = new_mapping
+        .expand("http://example.net/ns#Product", mixed, options.clone())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        MappingError::MultipleObjectTypesForPredicate(..)
+    ));
+
+    let int_datatype = Series::new(
+        "weight_datatype",
+        [
+            "http://www.w3.org/2001/XMLSchema#int",
+            "http://www.w3.org/2001/XMLSchema#int",
+        ],
+    );
+    let mut ints = df;
+    ints.with_column(int_datatype).unwrap();
+    ints.with_column(Series::new("weight", ["12", "30"]))
+        .unwrap();
+    let err = mapping
+        .expand("http://example.net/ns#Product", ints, options)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        MappingError::MultipleObjectTypesForPredicate(..)
+    ));
+}
+
+#[rstest]
+#[serial]
+fn test_remaining_polars_datatypes() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Values [xsd:anyURI ?obj, ?s, ?us, ?t, ?dec, ?bin, ?dur] :: {
+    ottr:Triple(?obj, ex:short, ?s),
+    ottr:Triple(?obj, ex:unsignedShort, ?us),
+    ottr:Triple(?obj, ex:time, ?t),
+    ottr:Triple(?obj, ex:decimal, ?dec),
+    ottr:Triple(?obj, ex:binary, ?bin),
+    ottr:Triple(?obj, ex:duration, ?dur)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let obj = Series::new("obj", ["http://example.net/ns#obj"]);
+    let s = Series::new("s", [-3i16]);
+    let us = Series::new("us", [7u16]);
+    let t = Series::new("t", [45_000_000_000_000i64])
+        .cast(&DataType::Time)
+        .unwrap();
+    let dec = Series::from_any_values("dec", &[AnyValue::Decimal(150, 2)], true).unwrap();
+    let bin = Series::new("bin", [b"hi".as_slice()]);
+    let dur = Series::new("dur", [90_500_000_000i64])
+        .cast(&DataType::Duration(TimeUnit::Nanoseconds))
+        .unwrap();
+    let df = DataFrame::from_iter([obj, s, us, t, dec, bin, dur]);
+    mapping
+        .expand("http://example.net/ns#Values", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let actual_triples_set: HashSet<Triple> = HashSet::from_iter(triples);
+    let typed_triple = |p: &str, lex: &str, dt: &str| Triple {
+        subject: Subject::NamedNode(NamedNode::new_unchecked("http://example.net/ns#obj")),
+        predicate: NamedNode::new_unchecked(format!("http://example.net/ns#{}", p)),
+        object: Term::Literal(Literal::new_typed_literal(
+            lex,
+            NamedNode::new_unchecked(format!("http://www.w3.org/2001/XMLSchema#{}", dt)),
+        )),
+    };
+    let expected_triples_set = HashSet::from([
+        typed_triple("short", "-3", "short"),
+        typed_triple("unsignedShort", "7", "unsignedShort"),
+        typed_triple("time", "12:30:00", "time"),
+        typed_triple("decimal", "1.50", "decimal"),
+        typed_triple("binary", "aGk=", "base64Binary"),
+        typed_triple("duration", "PT1M30.5S", "duration"),
+    ]);
+    assert_eq!(expected_triples_set, actual_triples_set);
+
+    let mut ntriples = vec![];
+    mapping.write_n_triples(&mut ntriples).unwrap();
+    let ntriples = String::from_utf8(ntriples).unwrap();
+    assert!(ntriples.contains("\"12:30:00\"^^<http://www.w3.org/2001/XMLSchema#time>"));
+    assert!(ntriples.contains("\"PT1M30.5S\"^^<http://www.w3.org/2001/XMLSchema#duration>"));
+
+    let query = r#"
+PREFIX ex:<http://example.net/ns#>
+PREFIX xsd:<http://www.w3.org/2001/XMLSchema#>
+SELECT ?obj WHERE {
+    ?obj ex:short "-3"^^xsd:short .
+    ?obj ex:time "12:30:00"^^xsd:time .
+    ?obj ex:duration "PT1M30.5S"^^xsd:duration .
+}
+"#;
+    if let QueryResult::Select(df) = mapping.triplestore.query(query).unwrap() {
+        assert_eq!(df.height(), 1);
+    } else {
+        panic!("Expected select result");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_expand_lazy_and_from_files() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter([
+        "http://example.net/ns#ann",
+        "http://example.net/ns#bob",
+        "http://example.net/ns#cid",
+        "http://example.net/ns#dan",
+        "http://example.net/ns#eve",
+    ]);
+    person.rename("person");
+    let age = Series::new("age", [30i64, 31, 32, 33, 34]);
+    let df = DataFrame::from_iter([person, age]);
+    let options = ExpandOptions {
+        batch_size: Some(2),
+        ..Default::default()
+    };
+    let report = mapping
+        .expand_lazy("http://example.net/ns#Person", df.lazy(), options.clone())
+        .unwrap();
+    assert_eq!(
+        Some(&5),
+        report
+            .triples_added
+            .get("http://example.net/ns#hasAge")
+            .and_then(|m| m.values().next())
+    );
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+
+    let mut csv_path = std::env::temp_dir();
+    csv_path.push("maplib_test_expand_from_files.csv");
+    std::fs::write(
+        &csv_path,
+        "person,age\nhttp://example.net/ns#fay,35\nhttp://example.net/ns#gus,36\nhttp://example.net/ns#hal,37\n",
+    )
+    .unwrap();
+    mapping
+        .expand_from_files(
+            "http://example.net/ns#Person",
+            csv_path.to_str().unwrap(),
+            options.clone(),
+        )
+        .unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 8);
+
+    //Row groups of two rows are collected into batches of four rows
+    let mut parquet_folder = std::env::temp_dir();
+    parquet_folder.push("maplib_test_expand_from_parquet_files");
+    std::fs::create_dir_all(&parquet_folder).unwrap();
+    for (file_name, names) in [
+        ("a.parquet", ["ida", "jon", "kim"]),
+        ("b.parquet", ["leo", "max", "ned"]),
+    ] {
+        let mut person =
+            Series::from_iter(names.iter().map(|x| format!("http://example.net/ns#{}", x)));
+        person.rename("person");
+        let age = Series::new("age", [40i64, 41, 42]);
+        let mut df = DataFrame::from_iter([person, age]);
+        let file = File::create(parquet_folder.join(file_name)).unwrap();
+        ParquetWriter::new(file)
+            .with_row_group_size(Some(2))
+            .finish(&mut df)
+            .unwrap();
+    }
+    let parquet_glob = parquet_folder.join("*.parquet");
+    mapping
+        .expand_from_files(
+            "http://example.net/ns#Person",
+            parquet_glob.to_str().unwrap(),
+            ExpandOptions {
+                batch_size: Some(4),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 14);
+    let err = mapping
+        .expand_from_files(
+            "http://example.net/ns#Person",
+            parquet_glob.to_str().unwrap(),
+            ExpandOptions {
+                batch_size: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, MappingError::InvalidBatchSize(0)));
+    std::fs::remove_dir_all(&parquet_folder).unwrap();
+
+    let err = mapping
+        .expand_from_files("http://example.net/ns#Person", "people.json", options)
+        .unwrap_err();
+    assert!(matches!(err, MappingError::UnsupportedInputFileType(_)));
+}
+
+#[rstest]
+#[serial]
+fn test_upsert() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ??age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age),
+    ottr:Triple(?person, ex:isA, ex:Person)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter([
+        "http://example.net/ns#ann",
+        "http://example.net/ns#bob",
+        "http://example.net/ns#cid",
+    ]);
+    person.rename("person");
+    let age = Series::new("age", [Some(30i32), Some(40), Some(50)]);
+    let df = DataFrame::from_iter([person, age]);
+    let options = ExpandOptions {
+        unique_subsets: Some(vec![vec!["person".to_string()]]),
+        upsert: true,
+        ..Default::default()
+    };
+    mapping
+        .expand("http://example.net/ns#Person", df, options.clone())
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 6);
+
+    //Ann's age changes and Cid's age is removed, Bob is not part of the new input
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#cid"]);
+    person.rename("person");
+    let age = Series::new("age", [Some(31i32), None]);
+    let df = DataFrame::from_iter([person, age]);
+    let report = mapping
+        .expand("http://example.net/ns#Person", df.clone(), options)
+        .unwrap();
+    assert_eq!(report.triples_retracted, 4);
+    let ages: HashSet<(String, String)> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.predicate.as_str() == "http://example.net/ns#hasAge")
+        .map(|t| (t.subject.to_string(), t.object.to_string()))
+        .collect();
+    assert_eq!(
+        ages,
+        HashSet::from([
+            (
+                "<http://example.net/ns#ann>".to_string(),
+                "\"31\"^^<http://www.w3.org/2001/XMLSchema#int>".to_string()
+            ),
+            (
+                "<http://example.net/ns#bob>".to_string(),
+                "\"40\"^^<http://www.w3.org/2001/XMLSchema#int>".to_string()
+            ),
+        ])
+    );
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+
+    let err = mapping
+        .expand(
+            "http://example.net/ns#Person",
+            df,
+            ExpandOptions {
+                upsert: true,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, MappingError::UpsertRequiresUniqueSubsets(_)));
+}
+
+#[rstest]
+#[serial]
+fn test_upsert_retracts_only_the_template() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Addressed [?person, ?street] :: {
+    ottr:Triple(?person, ex:hasAddress, _:address),
+    ottr:Triple(_:address, ex:street, ?street)
+} .
+ex:Employee [?person] :: {
+    ottr:Triple(?person, ex:isA, ex:Employee)
+} .
+"#;
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_upsert_retracts_only_the_template");
+    let caching_folder = folder.to_str().unwrap().to_string();
+    let addressed_df = |streets: &[&str]| {
+        let person = Series::new(
+            "person",
+            ["http://example.net/ns#ann", "http://example.net/ns#bob"],
+        );
+        let street = Series::new("street", streets);
+        DataFrame::from_iter([person, street])
+    };
+    let options = ExpandOptions {
+        unique_subsets: Some(vec![vec!["person".to_string()]]),
+        upsert: true,
+        ..Default::default()
+    };
+    let mut mapping = Mapping::from_str(&stottr, Some(caching_folder.clone())).unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Addressed",
+            addressed_df(&["Main St", "High St"]),
+            options.clone(),
+        )
+        .unwrap();
+    let person = Series::new("person", ["http://example.net/ns#ann"]);
+    mapping
+        .expand(
+            "http://example.net/ns#Employee",
+            DataFrame::from_iter([person]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+
+    //The template is known to have produced the triples after the folder is opened
+    drop(mapping);
+    let doc = document_from_str(stottr).unwrap();
+    let dataset = TemplateDataset::new(vec![doc]).unwrap();
+    let mut mapping = Mapping::open(&dataset, caching_folder.clone()).unwrap();
+    let report = mapping
+        .expand(
+            "http://example.net/ns#Addressed",
+            addressed_df(&["Side St", "High St"]),
+            options,
+        )
+        .unwrap();
+    //The blank nodes of the addresses are retracted with the people, the employee is kept
+    assert_eq!(report.triples_retracted, 4);
+    let mut triples: Vec<(String, String)> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.predicate.to_string(), t.object.to_string()))
+        .filter(|(p, _)| p != "<http://example.net/ns#hasAddress>")
+        .collect();
+    triples.sort();
+    assert_eq!(
+        triples,
+        vec![
+            (
+                "<http://example.net/ns#isA>".to_string(),
+                "<http://example.net/ns#Employee>".to_string()
+            ),
+            (
+                "<http://example.net/ns#street>".to_string(),
+                "\"High St\"".to_string()
+            ),
+            (
+                "<http://example.net/ns#street>".to_string(),
+                "\"Side St\"".to_string()
+            ),
+        ]
+    );
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+    drop(mapping);
+    std::fs::remove_dir_all(caching_folder).unwrap();
+}
+
+#[rstest]
+#[serial]
+fn test_caching_expands_each_chunk_once() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Note [?subject, ?text] :: {
+    ottr:Triple(?subject, ex:hasNote, _:note),
+    ottr:Triple(_:note, ex:text, ?text)
+} .
+"#;
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_caching_expands_each_chunk_once");
+    let caching_folder = folder.to_str().unwrap().to_string();
+    let mut mapping = Mapping::from_str(&stottr, Some(caching_folder.clone())).unwrap();
+    //More than 100 MB, so that the rows are expanded in at least three chunks
+    let n = 250_000;
+    let mut subject = Series::from_iter((0..n).map(|i| format!("http://example.net/ns#s{}", i)));
+    subject.rename("subject");
+    let mut text = Series::from_iter((0..n).map(|i| format!("{:0>400}", i)));
+    text.rename("text");
+    let report = mapping
+        .expand(
+            "http://example.net/ns#Note",
+            DataFrame::from_iter([subject, text]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(
+        Some(&n),
+        report
+            .triples_added
+            .get("http://example.net/ns#hasNote")
+            .and_then(|m| m.values().next())
+    );
+    std::fs::remove_dir_all(caching_folder).unwrap();
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+#[serial]
+fn test_retract_call(#[case] use_caching: bool) {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let caching_folder = if use_caching {
+        let mut folder = std::env::temp_dir();
+        folder.push("maplib_test_retract_call");
+        Some(folder.to_str().unwrap().to_string())
+    } else {
+        None
+    };
+    let mut mapping = Mapping::from_str(&stottr, caching_folder.clone()).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let age = Series::new("age", [30i32, 40]);
+    let first = mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#bob", "http://example.net/ns#cid"]);
+    person.rename("person");
+    let age = Series::new("age", [40i32, 50]);
+    let second = mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    assert_ne!(first.call_uuid, second.call_uuid);
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 3);
+
+    //Bob's triple was added by the first call, and is kept
+    let n_retracted = mapping.retract(&second.call_uuid).unwrap();
+    assert_eq!(n_retracted, 1);
+    let subjects: HashSet<String> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.subject.to_string())
+        .collect();
+    assert_eq!(
+        subjects,
+        HashSet::from([
+            "<http://example.net/ns#ann>".to_string(),
+            "<http://example.net/ns#bob>".to_string()
+        ])
+    );
+
+    assert_eq!(mapping.retract(&first.call_uuid).unwrap(), 2);
+    assert!(mapping.export_oxrdf_triples().unwrap().is_empty());
+    if let Some(caching_folder) = caching_folder {
+        std::fs::remove_dir_all(caching_folder).unwrap();
+    }
+}
+
+#[rstest]
+#[case(false, false)]
+#[case(false, true)]
+#[case(true, false)]
+#[case(true, true)]
+#[serial]
+fn test_retract_call_keeps_shared_triples(#[case] use_caching: bool, #[case] deduplicate: bool) {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let caching_folder = if use_caching {
+        let mut folder = std::env::temp_dir();
+        folder.push("maplib_test_retract_call_keeps_shared_triples");
+        Some(folder.to_str().unwrap().to_string())
+    } else {
+        None
+    };
+    let mut mapping = Mapping::from_str(&stottr, caching_folder.clone()).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let age = Series::new("age", [30i32, 40]);
+    let first = mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#bob", "http://example.net/ns#cid"]);
+    person.rename("person");
+    let age = Series::new("age", [40i32, 50]);
+    let second = mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    if deduplicate {
+        assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 3);
+    }
+
+    //Bob's triple was also added by the second call, and is kept
+    assert_eq!(mapping.retract(&first.call_uuid).unwrap(), 1);
+    let subjects: HashSet<String> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.subject.to_string())
+        .collect();
+    assert_eq!(
+        subjects,
+        HashSet::from([
+            "<http://example.net/ns#bob>".to_string(),
+            "<http://example.net/ns#cid>".to_string()
+        ])
+    );
+
+    assert_eq!(mapping.retract(&second.call_uuid).unwrap(), 2);
+    assert!(mapping.export_oxrdf_triples().unwrap().is_empty());
+    if let Some(caching_folder) = caching_folder {
+        std::fs::remove_dir_all(caching_folder).unwrap();
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_query_template_round_trip() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Person [xsd:anyURI ?person, xsd:string ?name, ? xsd:int ?age] :: {
+    ottr:Triple(?person, ex:hasName, ?name),
+    ex:Aged(?person, ?age)
+} .
+ex:Aged [xsd:anyURI ?x, xsd:int ?a] :: {
+    ottr:Triple(?x, ex:hasAge, ?a),
+    ottr:Triple(?x, ex:isA, ex:Aged)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let name = Series::new("name", ["Ann", "Bob"]);
+    let age = Series::new("age", [Some(30i32), None]);
+    let df = DataFrame::from_iter([person, name, age]);
+    mapping
+        .expand("http://example.net/ns#Person", df, ExpandOptions::default())
+        .unwrap();
+
+    let query = mapping
+        .lower_template("http://example.net/ns#Person")
+        .unwrap()
+        .to_string();
+    assert!(query.contains("OPTIONAL"));
+    let lowered = mapping
+        .query_template("http://example.net/ns#Person")
+        .unwrap()
+        .0
+        .sort(["person"], false, false)
+        .unwrap();
+    assert_eq!(lowered.get_column_names(), vec!["person", "name", "age"]);
+    assert_eq!(
+        lowered.column("age").unwrap(),
+        &Series::new("age", [Some(30i32), None])
+    );
+
+    let mut round_trip = Mapping::from_str(&stottr, None).unwrap();
+    round_trip
+        .expand(
+            "http://example.net/ns#Person",
+            lowered,
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let expected: HashSet<Triple> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let actual: HashSet<Triple> = round_trip
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(expected, actual);
+}
+
+#[rstest]
+#[serial]
+fn test_lower_template_with_blank_node_default() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Linked [?x, ?y = _:b1] :: {
+    ottr:Triple(?x, ex:linkedTo, ?y)
+} .
+ex:Outer [?x] :: {
+    ex:Linked(?x, none)
+} .
+"#;
+    let mapping = Mapping::from_str(&stottr, None).unwrap();
+    let err = mapping
+        .lower_template("http://example.net/ns#Outer")
+        .unwrap_err();
+    assert!(matches!(err, MappingError::LoweringNotSupported(_, _)));
+}
+
+#[rstest]
+#[serial]
+fn test_query_template_round_trip_untyped_iris() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Knows [?person, ?friend, ? ?unused] :: {
+    ottr:Triple(?person, ex:knows, ?friend),
+    ex:Note(?person, ?unused, none)
+} .
+ex:Note [?x, ?note, ?by] :: {
+    ottr:Triple(?x, ex:note, ?note),
+    ottr:Triple(?x, ex:notedBy, ?by)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let unused = Series::new("unused", [None::<&str>, None]);
+    mapping
+        .expand(
+            "http://example.net/ns#Knows",
+            DataFrame::from_iter([person, unused]),
+            ExpandOptions {
+                minting: Some(HashMap::from([(
+                    "friend".to_string(),
+                    MintingOptions {
+                        namespace: "http://example.net/friend#".to_string(),
+                        strategy: MintingStrategy::Uuid,
+                    },
+                )])),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let (lowered, rdf_node_types) = mapping
+        .query_template("http://example.net/ns#Knows")
+        .unwrap();
+    assert_eq!(
+        lowered.get_column_names(),
+        vec!["person", "friend", "unused"]
+    );
+    assert_eq!(lowered.column("unused").unwrap().null_count(), 2);
+    assert_eq!(rdf_node_types.get("friend"), Some(&RDFNodeType::IRI));
+
+    let mut round_trip = Mapping::from_str(&stottr, None).unwrap();
+    round_trip
+        .expand(
+            "http://example.net/ns#Knows",
+            lowered,
+            ExpandOptions {
+                rdf_node_types: Some(rdf_node_types),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let expected: HashSet<Triple> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let actual: HashSet<Triple> = round_trip
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(expected, actual);
+}
+
+#[rstest]
+#[serial]
+fn test_lower_template_blank_node_variables_differ_from_parameters() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Labelled [?x, ?blank_node_1] :: {
+    ottr:Triple(?x, ex:hasLabel, _:l),
+    ottr:Triple(_:l, ex:value, ?blank_node_1)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut x = Series::from_iter(["http://example.net/ns#ann"]);
+    x.rename("x");
+    let label = Series::new("blank_node_1", ["Ann"]);
+    mapping
+        .expand(
+            "http://example.net/ns#Labelled",
+            DataFrame::from_iter([x, label]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let (lowered, _) = mapping
+        .query_template("http://example.net/ns#Labelled")
+        .unwrap();
+    assert_eq!(
+        lowered.column("blank_node_1").unwrap(),
+        &Series::new("blank_node_1", ["Ann"])
+    );
+}
+
+#[rstest]
+#[serial]
+fn test_add_documents() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann"]);
+    person.rename("person");
+    let age = Series::new("age", [30i32]);
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+
+    let pets = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix pet:<http://example.net/pets#>.
+pet:Pet [?owner, ?pet] :: {
+    ottr:Triple(?owner, ex:hasPet, ?pet),
+    ottr:Triple(?pet, ex:isA, ex:Pet)
+} .
+pet:Pet(ex:ann, ex:rex) .
+"#;
+    mapping
+        .add_documents(vec![document_from_str(pets).unwrap()])
+        .unwrap();
+    //The triple from the first expansion is kept and the ground instance is expanded
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 3);
+
+    let mut owner = Series::from_iter(["http://example.net/ns#bob"]);
+    owner.rename("owner");
+    let mut pet = Series::from_iter(["http://example.net/ns#fido"]);
+    pet.rename("pet");
+    mapping
+        .expand(
+            "pet:Pet",
+            DataFrame::from_iter([owner, pet]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+
+    let err = mapping
+        .add_documents(vec![document_from_str(pets).unwrap()])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        MaplibError::TemplateError(TemplateError::DuplicateTemplate(_))
+    ));
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+
+    //A ground instance that can not be expanded leaves the templates as they were
+    let invalid = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Aged [?x, ?age] :: {
+    ottr:Triple(?x, ex:hasAge, ?age)
+} .
+ex:Aged(ex:ann, "thirty"^^xsd:int) .
+"#;
+    let err = mapping
+        .add_documents(vec![document_from_str(invalid).unwrap()])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        MaplibError::MappingError(MappingError::InvalidLiteral(_))
+    ));
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 5);
+    //The template was not kept, so it can be added again
+    mapping
+        .add_documents(vec![document_from_str(
+            &invalid.replace("\"thirty\"", "\"31\""),
+        )
+        .unwrap()])
+        .unwrap();
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 6);
+}
+
+#[rstest]
+#[serial]
+fn test_lists_become_rdf_collections() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Route [?route, ?waypoints] :: {
+    ottr:Triple(?route, ex:hasWaypoints, ?waypoints)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut route = Series::from_iter([
+        "http://example.net/ns#r1",
+        "http://example.net/ns#r2",
+        "http://example.net/ns#r3",
+    ]);
+    route.rename("route");
+    let waypoints = Series::new(
+        "waypoints",
+        [
+            Series::new("", [Series::new("", [1i32, 2]), Series::new("", [3i32])]),
+            Series::new("", [Series::new_empty("", &DataType::Int32)]),
+            Series::new_empty("", &DataType::List(Box::new(DataType::Int32))),
+        ],
+    );
+    let df = DataFrame::from_iter([route, waypoints]);
+    mapping
+        .expand("http://example.net/ns#Route", df, ExpandOptions::default())
+        .unwrap();
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    let objects: HashMap<(String, String), Term> = triples
+        .iter()
+        .map(|t| {
+            (
+                (t.subject.to_string(), t.predicate.as_str().to_string()),
+                t.object.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(objects.len(), triples.len());
+
+    fn read_collection(objects: &HashMap<(String, String), Term>, head: &Term) -> Vec<String> {
+        let mut elements = vec![];
+        let mut current = head.clone();
+        while current.to_string() != "<http://www.w3.org/1999/02/22-rdf-syntax-ns#nil>" {
+            assert!(matches!(current, Term::BlankNode(_)));
+            let first = objects
+                .get(&(
+                    current.to_string(),
+                    "http://www.w3.org/1999/02/22-rdf-syntax-ns#first".to_string(),
+                ))
+                .unwrap();
+            if let Term::Literal(l) = first {
+                elements.push(l.value().to_string());
+            } else {
+                elements.push(format!("({})", read_collection(objects, first).join(" ")));
+            }
+            current = objects
+                .get(&(
+                    current.to_string(),
+                    "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest".to_string(),
+                ))
+                .unwrap()
+                .clone();
+        }
+        elements
+    }
+
+    let head = |route: &str| {
+        objects
+            .get(&(
+                format!("<http://example.net/ns#{}>", route),
+                "http://example.net/ns#hasWaypoints".to_string(),
+            ))
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(read_collection(&objects, &head("r1")), vec!["(1 2)", "(3)"]);
+    assert_eq!(read_collection(&objects, &head("r2")), vec!["()"]);
+    assert!(read_collection(&objects, &head("r3")).is_empty());
+    //r1: 1 head, 2 outer cells and 3 inner cells with rdf:first and rdf:rest each
+    //r2: 1 head, 1 outer cell, r3: 1 head
+    assert_eq!(triples.len(), 1 + 2 * 2 + 3 * 2 + 1 + 2 + 1);
+}
+
+#[rstest]
+#[serial]
+fn test_query_rdf_collections() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Route [?route, ?waypoints] :: {
+    ottr:Triple(?route, ex:hasWaypoints, ?waypoints)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut route = Series::from_iter(["http://example.net/ns#r1", "http://example.net/ns#r2"]);
+    route.rename("route");
+    let waypoints = Series::new(
+        "waypoints",
+        [
+            Series::new("", [1i32, 2, 3]),
+            Series::new_empty("", &DataType::Int32),
+        ],
+    );
+    let df = DataFrame::from_iter([route, waypoints]);
+    mapping
+        .expand("http://example.net/ns#Route", df, ExpandOptions::default())
+        .unwrap();
+
+    //The heads and rdf:rest have both blank node and rdf:nil objects
+    let mut query_rows = |query: &str| -> Vec<String> {
+        if let QueryResult::Select(df) = mapping.triplestore.query(query).unwrap() {
+            let mut rows: Vec<String> = (0..df.height())
+                .map(|i| {
+                    df.get_row(i)
+                        .unwrap()
+                        .0
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            rows.sort();
+            rows
+        } else {
+            panic!("Expected a select result");
+        }
+    };
+    assert_eq!(
+        query_rows(
+            r#"
+    PREFIX ex:<http://example.net/ns#>
+    PREFIX rdf:<http://www.w3.org/1999/02/22-rdf-syntax-ns#>
+    SELECT ?route ?waypoint WHERE {
+        ?route ex:hasWaypoints ?head .
+        ?head rdf:rest* ?cell .
+        ?cell rdf:first ?waypoint .
+    }
+    "#
+        ),
+        vec![
+            r#""http://example.net/ns#r1" 1"#,
+            r#""http://example.net/ns#r1" 2"#,
+            r#""http://example.net/ns#r1" 3"#,
+        ]
+    );
+    assert_eq!(
+        query_rows(
+            r#"
+    PREFIX ex:<http://example.net/ns#>
+    PREFIX rdf:<http://www.w3.org/1999/02/22-rdf-syntax-ns#>
+    SELECT ?route WHERE {
+        ?route ex:hasWaypoints rdf:nil .
+    }
+    "#
+        ),
+        vec![r#""http://example.net/ns#r2""#]
+    );
+    assert_eq!(
+        query_rows(
+            r#"
+    PREFIX rdf:<http://www.w3.org/1999/02/22-rdf-syntax-ns#>
+    SELECT ?cell WHERE {
+        ?cell rdf:rest rdf:nil .
+        ?cell rdf:first 3 .
+    }
+    "#
+        )
+        .len(),
+        1
+    );
+}
+
+#[rstest]
+#[serial]
+fn test_default_struct_columns() {
+    let mut mapping = Mapping::from_str("", None).unwrap();
+    let mut subject = Series::from_iter([
+        "http://example.net/ns#obj1",
+        "http://example.net/ns#obj1",
+        "http://example.net/ns#obj2",
+    ]);
+    subject.rename("subject");
+    let mut street = Series::from_iter(["Main St", "Main St", "High St"]);
+    street.rename("street");
+    let mut lat = Series::from_iter([59.9f64, 59.9, 60.4]);
+    lat.rename("lat");
+    let mut part_name = Series::from_iter(["wheel", "axle", "bolt"]);
+    part_name.rename("part_name");
+    let mut part_weight = Series::from_iter([2i32, 5, 1]);
+    part_weight.rename("part_weight");
+    let df = DataFrame::from_iter([subject, street, lat, part_name, part_weight])
+        .lazy()
+        .groupby_stable([col("subject")])
+        .agg([
+            as_struct(&[col("street"), as_struct(&[col("lat")]).alias("geo")])
+                .first()
+                .alias("address"),
+            as_struct(&[
+                col("part_name").alias("name"),
+                col("part_weight").alias("weight"),
+            ])
+            .alias("parts"),
+        ])
+        .collect()
+        .unwrap();
+    let options = ExpandOptions {
+        minting: Some(HashMap::from([(
+            "address".to_string(),
+            MintingOptions {
+                namespace: "http://example.net/address#".to_string(),
+                strategy: MintingStrategy::Uuid,
+            },
+        )])),
+        ..Default::default()
+    };
+    let templates = mapping
+        .expand_default(
+            df,
+            "subject".to_string(),
+            vec![],
+            None,
+            Some("http://example.net/ns#".to_string()),
+            options,
+        )
+        .unwrap();
+    //The data frame template, and sub-templates for address, address.geo and parts
+    assert_eq!(templates.len(), 4);
+
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    //Per subject: address with street, geo and lat, and per part a link, name and weight
+    assert_eq!(triples.len(), 4 + 2 * 3 + 4 + 3);
+    for t in &triples {
+        match t.predicate.as_str() {
+            "http://example.net/ns#address" => {
+                assert!(t.object.to_string().starts_with("<http://example.net/address#"))
+            }
+            "http://example.net/ns#parts" | "http://example.net/ns#geo" => {
+                assert!(matches!(t.object, Term::BlankNode(_)))
+            }
+            _ => {}
+        }
+    }
+
+    let query = r#"
+    PREFIX ex:<http://example.net/ns#>
+    SELECT ?subject ?street ?lat ?name ?weight WHERE {
+        ?subject ex:address ?address .
+        ?address ex:street ?street .
+        ?address ex:geo ?geo .
+        ?geo ex:lat ?lat .
+        ?subject ex:parts ?part .
+        ?part ex:name ?name .
+        ?part ex:weight ?weight .
+    } ORDER BY ?name
+    "#;
+    if let QueryResult::Select(df) = mapping.triplestore.query(query).unwrap() {
+        let rows: Vec<String> = (0..df.height())
+            .map(|i| {
+                df.get_row(i)
+                    .unwrap()
+                    .0
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                r#""http://example.net/ns#obj1" "Main St" 59.9 "axle" 5"#,
+                r#""http://example.net/ns#obj2" "High St" 60.4 "bolt" 1"#,
+                r#""http://example.net/ns#obj1" "Main St" 59.9 "wheel" 2"#,
+            ]
+        );
+    } else {
+        panic!("Expected a select result");
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_default_null_struct_columns() {
+    let mut mapping = Mapping::from_str("", None).unwrap();
+    let mut subject = Series::from_iter([
+        "http://example.net/ns#obj1",
+        "http://example.net/ns#obj1",
+        "http://example.net/ns#obj2",
+        "http://example.net/ns#obj3",
+    ]);
+    subject.rename("subject");
+    let street = Series::new(
+        "street",
+        [Some("Main St"), Some("Main St"), None, Some("High St")],
+    );
+    let lat = Series::new("lat", [Some(59.9f64), Some(59.9), None, None]);
+    let part_name = Series::new("part_name", [Some("wheel"), None, None, Some("bolt")]);
+    let part_weight = Series::new("part_weight", [Some(2i32), None, None, Some(1)]);
+    let df = DataFrame::from_iter([subject, street, lat, part_name, part_weight])
+        .lazy()
+        .groupby_stable([col("subject")])
+        .agg([
+            as_struct(&[col("street"), as_struct(&[col("lat")]).alias("geo")])
+                .first()
+                .alias("address"),
+            as_struct(&[
+                col("part_name").alias("name"),
+                col("part_weight").alias("weight"),
+            ])
+            .alias("parts"),
+        ])
+        .collect()
+        .unwrap();
+    mapping
+        .expand_default(
+            df,
+            "subject".to_string(),
+            vec![],
+            None,
+            Some("http://example.net/ns#".to_string()),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+
+    let triples = mapping.export_oxrdf_triples().unwrap();
+    //obj1 has an address with a geo and one part, obj2 only null structs, and obj3 an address
+    //without a geo and one part
+    assert_eq!(triples.len(), 4 + 3 + 2 + 3);
+    let subjects: HashSet<String> = triples.iter().map(|t| t.subject.to_string()).collect();
+    for t in &triples {
+        assert_ne!(t.subject.to_string(), "<http://example.net/ns#obj2>");
+        if let Term::BlankNode(_) = &t.object {
+            assert!(subjects.contains(&t.object.to_string()));
+        }
+    }
+}
+
+#[rstest]
+#[serial]
+fn test_default_struct_field_column_already_present() {
+    let mut mapping = Mapping::from_str("", None).unwrap();
+    let mut subject = Series::from_iter(["http://example.net/ns#obj1"]);
+    subject.rename("subject");
+    let mut street = Series::from_iter(["Main St"]);
+    street.rename("street");
+    let mut address_street = Series::from_iter(["High St"]);
+    address_street.rename("address_street");
+    let df = DataFrame::from_iter([subject, street, address_street])
+        .lazy()
+        .select([
+            col("subject"),
+            col("address_street"),
+            as_struct(&[col("street")]).alias("address"),
+        ])
+        .collect()
+        .unwrap();
+    let err = mapping
+        .expand_default(
+            df,
+            "subject".to_string(),
+            vec![],
+            None,
+            None,
+            ExpandOptions::default(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        MappingError::DefaultColumnAlreadyPresent(c) if c == "address_street"
+    ));
+}
+
+#[rstest]
+#[serial]
+fn test_named_graphs() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Reading [?sensor, ?value] :: {
+    ottr:Triple(?sensor, ex:hasValue, ?value)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let reading_df = |sensors: &[&str], values: &[i32]| {
+        let mut sensor = Series::from_iter(
+            sensors
+                .iter()
+                .map(|x| format!("http://example.net/ns#{}", x)),
+        );
+        sensor.rename("sensor");
+        let mut value = Series::from_iter(values.iter().cloned());
+        value.rename("value");
+        DataFrame::from_iter([sensor, value])
+    };
+    for (graph, sensors, values) in [
+        (None, ["s0"], [0]),
+        (Some("http://example.net/graph#a"), ["s1"], [1]),
+        (Some("http://example.net/graph#b"), ["s1"], [1]),
+        (Some("http://example.net/graph#b"), ["s2"], [2]),
+    ] {
+        mapping
+            .expand(
+                "http://example.net/ns#Reading",
+                reading_df(&sensors, &values),
+                ExpandOptions {
+                    graph: graph.map(|x| x.to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+
+    let mut query_rows = |query: &str| {
+        if let QueryResult::Select(df) = mapping.triplestore.query(query).unwrap() {
+            let mut rows: Vec<String> = (0..df.height())
+                .map(|i| {
+                    df.get_row(i)
+                        .unwrap()
+                        .0
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            rows.sort();
+            rows
+        } else {
+            panic!("Expected a select result")
+        }
+    };
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s ?v WHERE { ?s ex:hasValue ?v }"
+        ),
+        vec![r#""http://example.net/ns#s0" 0"#]
+    );
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?g ?s ?v WHERE { GRAPH ?g { ?s ex:hasValue ?v } }"
+        ),
+        vec![
+            r#""http://example.net/graph#a" "http://example.net/ns#s1" 1"#,
+            r#""http://example.net/graph#b" "http://example.net/ns#s1" 1"#,
+            r#""http://example.net/graph#b" "http://example.net/ns#s2" 2"#,
+        ]
+    );
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s ?v WHERE { GRAPH <http://example.net/graph#a> { ?s ex:hasValue ?v } }"
+        ),
+        vec![r#""http://example.net/ns#s1" 1"#]
+    );
+    //The default graph is the merge of the FROM graphs
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s ?v FROM <http://example.net/graph#a> FROM <http://example.net/graph#b> WHERE { ?s ex:hasValue ?v }"
+        ),
+        vec![
+            r#""http://example.net/ns#s1" 1"#,
+            r#""http://example.net/ns#s2" 2"#,
+        ]
+    );
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?g ?s WHERE { ?s ex:hasValue ?v . GRAPH ?g { ?s ex:hasValue ?w } }"
+        ),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        query_rows(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?g ?s FROM <http://example.net/graph#a> FROM NAMED <http://example.net/graph#b> WHERE { ?s ex:hasValue ?v . GRAPH ?g { ?s ex:hasValue ?w } }"
+        ),
+        vec![r#""http://example.net/graph#b" "http://example.net/ns#s1""#]
+    );
+
+    //Graphs with objects of different types can not be merged
+    let mut sensor = Series::from_iter(["http://example.net/ns#s3"]);
+    sensor.rename("sensor");
+    let value = Series::new("value", ["three"]);
+    mapping
+        .expand(
+            "http://example.net/ns#Reading",
+            DataFrame::from_iter([sensor, value]),
+            ExpandOptions {
+                graph: Some("http://example.net/graph#c".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(matches!(
+        mapping.triplestore.query(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s ?v FROM <http://example.net/graph#a> FROM <http://example.net/graph#c> WHERE { ?s ex:hasValue ?v }"
+        ),
+        Err(SparqlError::MultipleObjectTypes(_, _))
+    ));
+
+    //Only the default graph is exported as triples
+    assert_eq!(mapping.export_oxrdf_triples().unwrap().len(), 1);
+}
+
+#[rstest]
+#[serial]
+fn test_open_caching_folder() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?name, ?age] :: {
+    ottr:Triple(?person, ex:hasName, ?name) ,
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_open_caching_folder");
+    let caching_folder = folder.to_str().unwrap().to_string();
+    let person_df = |persons: &[&str], names: &[&str], ages: &[i32]| {
+        let mut person = Series::from_iter(
+            persons
+                .iter()
+                .map(|x| format!("http://example.net/ns#{}", x)),
+        );
+        person.rename("person");
+        let mut name = Series::from_iter(names.iter().cloned());
+        name.rename("name");
+        let mut age = Series::from_iter(ages.iter().cloned());
+        age.rename("age");
+        DataFrame::from_iter([person, name, age])
+    };
+    let mut mapping = Mapping::from_str(&stottr, Some(caching_folder.clone())).unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            person_df(&["ann", "bob"], &["Ann", "Bob"], &[30, 40]),
+            ExpandOptions {
+                language_tags: Some(HashMap::from([("name".to_string(), "en".to_string())])),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    //Bob is added twice, and is deduplicated after opening
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            person_df(&["bob", "cid"], &["Bob", "Cid"], &[40, 50]),
+            ExpandOptions {
+                language_tags: Some(HashMap::from([("name".to_string(), "en".to_string())])),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            person_df(&["dan"], &["Dan"], &[60]),
+            ExpandOptions {
+                graph: Some("http://example.net/graph#a".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let mut expected_triples: Vec<String> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.to_string())
+        .collect();
+    expected_triples.sort();
+    assert_eq!(expected_triples.len(), 6);
+    assert!(expected_triples.contains(
+        &r#"<http://example.net/ns#ann> <http://example.net/ns#hasName> "Ann"@en"#.to_string()
+    ));
+    drop(mapping);
+
+    let doc = document_from_str(stottr).unwrap();
+    let dataset = TemplateDataset::new(vec![doc]).unwrap();
+    let mut mapping = Mapping::open(&dataset, caching_folder.clone()).unwrap();
+    let mut actual_triples: Vec<String> = mapping
+        .export_oxrdf_triples()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.to_string())
+        .collect();
+    actual_triples.sort();
+    assert_eq!(actual_triples, expected_triples);
+    if let QueryResult::Select(df) = mapping
+        .triplestore
+        .query(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s WHERE { GRAPH <http://example.net/graph#a> { ?s ex:hasAge 60 } }",
+        )
+        .unwrap()
+    {
+        assert_eq!(df.height(), 1);
+    } else {
+        panic!("Expected a select result")
+    }
+    drop(mapping);
+
+    //A new mapping starts from an empty caching folder
+    let mapping = Mapping::new(&dataset, Some(caching_folder.clone())).unwrap();
+    drop(mapping);
+    assert!(matches!(
+        Mapping::open(&dataset, caching_folder.clone()),
+        Err(MaplibError::MappingError(MappingError::TriplestoreError(_)))
+    ));
+    assert_eq!(std::fs::read_dir(&caching_folder).unwrap().count(), 0);
+    std::fs::remove_dir_all(caching_folder).unwrap();
+}
+
+#[rstest]
+#[serial]
+fn test_native_parquet_round_trip() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?name, ?age] :: {
+    ottr:Triple(?person, ex:hasName, ?name) ,
+    ottr:Triple(?person, ex:has-name, ?name) ,
+    ottr:Triple(?person, ex:hasAge, ?age)
+} .
+"#;
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_native_parquet_round_trip");
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+    std::fs::create_dir(&folder).unwrap();
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#ann", "http://example.net/ns#bob"]);
+    person.rename("person");
+    let name = Series::new("name", ["Ann", "Bob"]);
+    let age = Series::new("age", [30i32, 40]);
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, name, age]),
+            ExpandOptions {
+                language_tags: Some(HashMap::from([("name".to_string(), "en".to_string())])),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#cid"]);
+    person.rename("person");
+    let name = Series::new("name", ["Cid"]);
+    let age = Series::new("age", [50i32]);
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, name, age]),
+            ExpandOptions {
+                graph: Some("http://example.net/graph#a".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    mapping
+        .write_native_parquet(
+            folder.to_str().unwrap(),
+            &NativeParquetWriteOptions::default(),
+        )
+        .unwrap();
+
+    //Predicates with the same file name are read back as different predicates
+    let mut read_mapping = Mapping::from_str(&stottr, None).unwrap();
+    read_mapping
+        .read_native_parquet(folder.to_str().unwrap())
+        .unwrap();
+    let sorted_triples = |mapping: &mut Mapping| {
+        let mut triples: Vec<String> = mapping
+            .export_oxrdf_triples()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.to_string())
+            .collect();
+        triples.sort();
+        triples
+    };
+    let expected_triples = sorted_triples(&mut mapping);
+    assert_eq!(expected_triples.len(), 6);
+    assert_eq!(sorted_triples(&mut read_mapping), expected_triples);
+    if let QueryResult::Select(df) = read_mapping
+        .triplestore
+        .query(
+            "PREFIX ex:<http://example.net/ns#> SELECT ?s WHERE { GRAPH <http://example.net/graph#a> { ?s ex:hasAge 50 } }",
+        )
+        .unwrap()
+    {
+        assert_eq!(df.height(), 1);
+    } else {
+        panic!("Expected a select result")
+    }
+
+    //Reading the same export again does not duplicate the triples
+    read_mapping
+        .read_native_parquet(folder.to_str().unwrap())
+        .unwrap();
+    assert_eq!(sorted_triples(&mut read_mapping), expected_triples);
+    std::fs::remove_dir_all(folder).unwrap();
+}
+
+#[rstest]
+#[serial]
+fn test_native_parquet_from_caching_folder() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?age] :: {
+    ottr:Triple(?person, ex:hasAge, ?age) ,
+    ottr:Triple(?person, ex:knows, ex:ann)
+} .
+"#;
+    let mut caching_folder = std::env::temp_dir();
+    caching_folder.push("maplib_test_native_parquet_caching_folder");
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_native_parquet_export");
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+    std::fs::create_dir(&folder).unwrap();
+    let mut mapping = Mapping::from_str(
+        &stottr,
+        Some(caching_folder.to_str().unwrap().to_string()),
+    )
+    .unwrap();
+    let mut person = Series::from_iter(["http://example.net/ns#bob", "http://example.net/ns#cid"]);
+    person.rename("person");
+    let age = Series::new("age", [40i32, 50]);
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            DataFrame::from_iter([person, age]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    mapping
+        .write_native_parquet(
+            folder.to_str().unwrap(),
+            &NativeParquetWriteOptions {
+                compression: NativeParquetWriteOptions::parse_compression("snappy").unwrap(),
+                row_group_size: Some(1),
+                partition_by_predicate: true,
+            },
+        )
+        .unwrap();
+    let mut partitions: Vec<String> = std::fs::read_dir(&folder)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().to_str().unwrap().to_string())
+        .filter(|x| x.starts_with("predicate="))
+        .collect();
+    partitions.sort();
+    assert_eq!(
+        partitions,
+        vec![
+            "predicate=http%3A%2F%2Fexample.net%2Fns%23hasAge",
+            "predicate=http%3A%2F%2Fexample.net%2Fns%23knows"
+        ]
+    );
+
+    let mut read_mapping = Mapping::from_str(&stottr, None).unwrap();
+    read_mapping
+        .read_native_parquet(folder.to_str().unwrap())
+        .unwrap();
+    let sorted_triples = |mapping: &mut Mapping| {
+        let mut triples: Vec<String> = mapping
+            .export_oxrdf_triples()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.to_string())
+            .collect();
+        triples.sort();
+        triples
+    };
+    let expected_triples = sorted_triples(&mut mapping);
+    assert_eq!(expected_triples.len(), 4);
+    assert_eq!(sorted_triples(&mut read_mapping), expected_triples);
+    assert!(matches!(
+        NativeParquetWriteOptions::parse_compression("zip"),
+        Err(TriplestoreError::InvalidParquetCompression(_))
+    ));
+    std::fs::remove_dir_all(folder).unwrap();
+    std::fs::remove_dir_all(caching_folder).unwrap();
+}
+#[rstest]
+#[serial]
+fn test_term_dictionary_queries() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+ex:Person [?person, ?name] :: {
+    ottr:Triple(?person, ex:hasName, ?name)
+} .
+ex:Knows [?a, ?b] :: {
+    ottr:Triple(?a, ex:knows, ?b)
+} .
+"#;
+    let mut folder = std::env::temp_dir();
+    folder.push("maplib_test_term_dictionary_queries");
+    let caching_folder = folder.to_str().unwrap().to_string();
+    let iris = |name: &str, xs: &[&str]| {
+        let mut s = Series::from_iter(xs.iter().map(|x| format!("http://example.net/ns#{}", x)));
+        s.rename(name);
+        s
+    };
+    let person_df = |persons: &[&str], names: &[&str]| {
+        let mut name = Series::from_iter(names.iter().cloned());
+        name.rename("name");
+        DataFrame::from_iter([iris("person", persons), name])
+    };
+    let knows_df = |a: &[&str], b: &[&str]| DataFrame::from_iter([iris("a", a), iris("b", b)]);
+    let query_rows = |mapping: &mut Mapping, query: &str| {
+        let query = format!("PREFIX ex:<http://example.net/ns#> {}", query);
+        if let QueryResult::Select(df) = mapping.triplestore.query(&query).unwrap() {
+            //IRIs are decoded to strings in the results
+            for c in df.get_columns() {
+                assert!(matches!(c.dtype(), DataType::Utf8 | DataType::Null));
+            }
+            let mut rows: Vec<String> = (0..df.height())
+                .map(|i| {
+                    df.get_row(i)
+                        .unwrap()
+                        .0
+                        .iter()
+                        .map(|x| x.to_string().replace("http://example.net/ns#", ""))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+            rows.sort();
+            rows
+        } else {
+            panic!("Expected a select result")
+        }
+    };
+
+    let mut mapping = Mapping::from_str(&stottr, Some(caching_folder.clone())).unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Person",
+            person_df(&["ann", "bob", "cid", "dan"], &["Ann", "Bob", "Cid", "Dan"]),
+            Default::default(),
+        )
+        .unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Knows",
+            knows_df(&["ann", "bob", "cid"], &["bob", "cid", "dan"]),
+            Default::default(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?a ?c WHERE { ?a ex:knows ?b . ?b ex:knows ?c }"
+        ),
+        vec![r#""ann" "cid""#, r#""bob" "dan""#]
+    );
+    assert_eq!(
+        query_rows(&mut mapping, "SELECT ?b WHERE { ex:ann ex:knows ?b }"),
+        vec![r#""bob""#]
+    );
+    assert_eq!(
+        query_rows(&mut mapping, "SELECT ?b WHERE { ex:eve ex:knows ?b }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        query_rows(&mut mapping, "SELECT ?a WHERE { ?a ex:knows ex:dan }"),
+        vec![r#""cid""#]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?a WHERE { ?a ex:knows ?b FILTER(?b = ex:bob) }"
+        ),
+        vec![r#""ann""#]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?p ?f WHERE { ?p ex:hasName ?n OPTIONAL { ?p ex:knows ?f } }"
+        ),
+        vec![
+            r#""ann" "bob""#,
+            r#""bob" "cid""#,
+            r#""cid" "dan""#,
+            r#""dan" null"#,
+        ]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?x WHERE { { ?x ex:knows ex:bob } UNION { VALUES ?x { ex:eve } } }"
+        ),
+        vec![r#""ann""#, r#""eve""#]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?x ?n WHERE { VALUES ?x { ex:bob ex:eve } ?x ex:hasName ?n }"
+        ),
+        vec![r#""bob" "Bob""#]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            r#"SELECT ?y WHERE { ?x ex:knows+ ?y . ?x ex:hasName "Ann" }"#
+        ),
+        vec![r#""bob""#, r#""cid""#, r#""dan""#]
+    );
+    assert_eq!(
+        query_rows(&mut mapping, "SELECT ?x ?y WHERE { ?x ex:knows+ ?y }").len(),
+        6
+    );
+    drop(mapping);
+
+    //The term dictionary is kept in the caching folder, and new terms are added after opening
+    let doc = document_from_str(stottr).unwrap();
+    let dataset = TemplateDataset::new(vec![doc]).unwrap();
+    let mut mapping = Mapping::open(&dataset, caching_folder.clone()).unwrap();
+    mapping
+        .expand(
+            "http://example.net/ns#Knows",
+            knows_df(&["eve"], &["ann"]),
+            Default::default(),
+        )
+        .unwrap();
+    drop(mapping);
+    let n_dictionary_files = std::fs::read_dir(&caching_folder)
+        .unwrap()
+        .filter(|f| {
+            f.as_ref()
+                .unwrap()
+                .file_name()
+                .to_str()
+                .unwrap()
+                .starts_with("dictionary_")
+        })
+        .count();
+    assert_eq!(n_dictionary_files, 2);
+    let mut mapping = Mapping::open(&dataset, caching_folder.clone()).unwrap();
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?a ?c WHERE { ?a ex:knows ?b . ?b ex:knows ?c }"
+        ),
+        vec![r#""ann" "cid""#, r#""bob" "dan""#, r#""eve" "bob""#]
+    );
+    assert_eq!(
+        query_rows(
+            &mut mapping,
+            "SELECT ?n WHERE { ?p ex:knows ex:ann . ?p ex:hasName ?n }"
+        ),
+        Vec::<String>::new()
+    );
+    drop(mapping);
+    std::fs::remove_dir_all(caching_folder).unwrap();
+}
+
+#[rstest]
+#[serial]
+fn test_query_literals_are_parsed_without_panics() {
+    let stottr = r#"
+@prefix ex:<http://example.net/ns#>.
+@prefix xsd:<http://www.w3.org/2001/XMLSchema#>.
+ex:Item [?item] :: {
+    ottr:Triple(?item, ex:hasPrice, "1.50"^^xsd:decimal),
+    ottr:Triple(?item, ex:hasTime, "12:00:00Z"^^xsd:time),
+    ottr:Triple(?item, ex:hasDouble, "2.0"^^xsd:double)
+} .
+"#;
+    let mut mapping = Mapping::from_str(&stottr, None).unwrap();
+    let mut item = Series::from_iter(["http://example.net/ns#a"]);
+    item.rename("item");
+    mapping
+        .expand(
+            "http://example.net/ns#Item",
+            DataFrame::from_iter([item]),
+            ExpandOptions::default(),
+        )
+        .unwrap();
+    let prefixes =
+        "PREFIX ex:<http://example.net/ns#> PREFIX xsd:<http://www.w3.org/2001/XMLSchema#>";
+    for pattern in [
+        r#"?i ex:hasDouble ?p FILTER(?p > 1.0)"#,
+        r#"?i ex:hasPrice ?p FILTER(?p > 1.0)"#,
+        r#"?i ex:hasPrice ?p FILTER(?p = 1.50)"#,
+        r#"?i ex:hasPrice "1.50"^^xsd:decimal"#,
+        r#"?i ex:hasPrice "1.5"^^xsd:decimal"#,
+        r#"?i ex:hasTime "12:00:00Z"^^xsd:time"#,
+    ] {
+        let query = format!("{} SELECT ?i WHERE {{ {} }}", prefixes, pattern);
+        if let QueryResult::Select(df) = mapping.triplestore.query(&query).unwrap() {
+            assert_eq!(df.height(), 1, "{}", pattern);
+        } else {
+            panic!("Expected select")
+        }
+    }
+    //Durations with years have no fixed length, so they are not supported
+    let query = format!(
+        r#"{} SELECT ?i WHERE {{ ?i ex:hasTime ?t FILTER(?t = "P1Y"^^xsd:duration) }}"#,
+        prefixes
+    );
+    assert!(matches!(
+        mapping.triplestore.query(&query),
+        Err(SparqlError::InvalidLiteral(_))
+    ));
+}
@@ -1,4 +1,6 @@
-use polars::prelude::{LazyFrame, ParallelStrategy, ParquetWriter, ScanArgsParquet};
+use polars::prelude::{
+    LazyFrame, ParallelStrategy, ParquetCompression, ParquetWriter, ScanArgsParquet,
+};
 use polars_core::frame::DataFrame;
 use std::cmp::min;
 use std::fs::File;
@@ -43,9 +45,19 @@ pub fn property_to_filename(property_name: &str) -> String {
 }
 
 pub fn write_parquet(df: &mut DataFrame, file_path: &Path) -> Result<(), ParquetIOError> {
+    write_parquet_with_options(df, file_path, ParquetCompression::default(), Some(1_000))
+}
+
+//The row group size defaults to the one of the parquet writer when it is None
+pub fn write_parquet_with_options(
+    df: &mut DataFrame,
+    file_path: &Path,
+    compression: ParquetCompression,
+    row_group_size: Option<usize>,
+) -> Result<(), ParquetIOError> {
     let file = File::create(file_path).map_err(|x| ParquetIOError::FileCreateIOError(x))?;
-    let mut writer = ParquetWriter::new(file);
-    writer = writer.with_row_group_size(Some(1_000));
+    let mut writer = ParquetWriter::new(file).with_compression(compression);
+    writer = writer.with_row_group_size(row_group_size);
     writer
         .finish(df)
         .map_err(|x| ParquetIOError::WriteParquetError(x))?;
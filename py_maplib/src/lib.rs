@@ -7,10 +7,13 @@ use arrow_python_utils::to_rust::polars_df_to_rust_df;
 
 use maplib::document::document_from_str;
 use maplib::mapping::ExpandOptions as RustExpandOptions;
+use maplib::mapping::minting::{MintingOptions as RustMintingOptions, MintingStrategy};
 use maplib::mapping::Mapping as InnerMapping;
+use maplib::mapping::MappingReport;
 use maplib::templates::TemplateDataset;
 use pyo3::basic::CompareOp;
 use pyo3::prelude::PyModule;
+use pyo3::types::PyDict;
 use pyo3::*;
 use std::collections::{HashMap};
 use std::path::PathBuf;
@@ -19,6 +22,8 @@ use arrow_python_utils::to_python::{df_to_py_df, df_vec_to_py_df_list};
 use maplib::errors::MaplibError;
 use maplib::mapping::errors::MappingError;
 use oxrdf::NamedNode;
+use representation::RDFNodeType;
+use triplestore::native_parquet_write::NativeParquetWriteOptions;
 use triplestore::sparql::QueryResult;
 
 #[pyclass]
@@ -164,18 +169,100 @@ pub struct Mapping {
 pub struct ExpandOptions {
     pub language_tags: Option<HashMap<String, String>>,
     pub unique_subsets: Option<Vec<Vec<String>>>,
-    pub caching_folder: Option<String>
+    pub caching_folder: Option<String>,
+    pub minting: Option<HashMap<String, MintingOptions>>,
+    pub coerce_datatypes: Option<bool>,
+    pub quarantine_invalid_rows: Option<bool>,
+    pub language_tag_columns: Option<HashMap<String, String>>,
+    pub datatype_columns: Option<HashMap<String, String>>,
+    pub batch_size: Option<usize>,
+    pub upsert: Option<bool>,
+    pub graph: Option<String>,
 }
 
 impl ExpandOptions {
-    fn to_rust_expand_options(self) -> RustExpandOptions {
-        RustExpandOptions {
+    fn to_rust_expand_options(self) -> Result<RustExpandOptions, MappingError> {
+        let minting = if let Some(minting) = self.minting {
+            let mut rust_minting = HashMap::new();
+            for (k, v) in minting {
+                let rust_options = v.to_rust_minting_options(&k)?;
+                rust_minting.insert(k, rust_options);
+            }
+            Some(rust_minting)
+        } else {
+            None
+        };
+        Ok(RustExpandOptions {
             language_tags: self.language_tags,
             unique_subsets: self.unique_subsets,
+            minting,
+            coerce_datatypes: self.coerce_datatypes.unwrap_or(false),
+            quarantine_invalid_rows: self.quarantine_invalid_rows.unwrap_or(false),
+            language_tag_columns: self.language_tag_columns,
+            datatype_columns: self.datatype_columns,
+            batch_size: self.batch_size,
+            upsert: self.upsert.unwrap_or(false),
+            graph: self.graph,
+            rdf_node_types: None,
+        })
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct MintingOptions {
+    #[pyo3(get)]
+    pub namespace: String,
+    #[pyo3(get)]
+    pub strategy: String,
+    #[pyo3(get)]
+    pub columns: Option<Vec<String>>,
+    #[pyo3(get)]
+    pub format: Option<String>,
+}
+
+#[pymethods]
+impl MintingOptions {
+    #[new]
+    pub fn new(namespace: String, strategy: Option<String>, columns: Option<Vec<String>>, format: Option<String>) -> MintingOptions {
+        MintingOptions {
+            namespace,
+            strategy: strategy.unwrap_or("uuid".to_string()),
+            columns,
+            format,
         }
     }
 }
 
+impl MintingOptions {
+    fn to_rust_minting_options(self, column_name: &str) -> Result<RustMintingOptions, MappingError> {
+        let strategy = match self.strategy.as_str() {
+            "uuid" => MintingStrategy::Uuid,
+            "hash" => MintingStrategy::Hash(self.columns.unwrap_or(vec![])),
+            "format" => {
+                if let Some(format) = self.format {
+                    MintingStrategy::Format(format)
+                } else {
+                    return Err(MappingError::InvalidMintingStrategy(
+                        column_name.to_string(),
+                        "format strategy requires a format".to_string(),
+                    ));
+                }
+            }
+            s => {
+                return Err(MappingError::InvalidMintingStrategy(
+                    column_name.to_string(),
+                    format!("unknown strategy {}, expected uuid, hash or format", s),
+                ));
+            }
+        };
+        Ok(RustMintingOptions {
+            namespace: self.namespace,
+            strategy,
+        })
+    }
+}
+
 #[pymethods]
 impl Mapping {
     #[new]
@@ -195,14 +282,53 @@ impl Mapping {
         })
     }
 
-    #[pyo3(text_signature = "(template, df, unique_subset, language_tags, caching_folder)")]
+    #[staticmethod]
+    #[pyo3(text_signature = "(documents, caching_folder)")]
+    pub fn open(documents: Option<Vec<&str>>, caching_folder: String) -> PyResult<Mapping> {
+        let mut parsed_documents = vec![];
+        if let Some(documents) = documents {
+            for ds in documents {
+                let parsed_doc = document_from_str(ds).map_err(PyMaplibError::from)?;
+                parsed_documents.push(parsed_doc);
+            }
+        }
+        let template_dataset = TemplateDataset::new(parsed_documents)
+            .map_err(MaplibError::from)
+            .map_err(PyMaplibError::from)?;
+        Ok(Mapping {
+            inner: InnerMapping::open(&template_dataset, caching_folder).map_err(PyMaplibError::from)?,
+        })
+    }
+
+    #[pyo3(text_signature = "(documents)")]
+    pub fn add_documents(&mut self, documents: Vec<&str>) -> PyResult<()> {
+        let mut parsed_documents = vec![];
+        for ds in documents {
+            let parsed_doc = document_from_str(ds).map_err(PyMaplibError::from)?;
+            parsed_documents.push(parsed_doc);
+        }
+        self.inner
+            .add_documents(parsed_documents)
+            .map_err(PyMaplibError::from)?;
+        Ok(())
+    }
+
+    #[pyo3(text_signature = "(template, df, unique_subset, language_tags, caching_folder, minting, coerce_datatypes, quarantine_invalid_rows, language_tag_columns, datatype_columns, upsert, graph)")]
     pub fn expand(
         &mut self,
+        py: Python<'_>,
         template: &str,
         df: &PyAny,
         unique_subset: Option<Vec<String>>,
         language_tags: Option<HashMap<String, String>>,
-        caching_folder: Option<String>
+        caching_folder: Option<String>,
+        minting: Option<HashMap<String, MintingOptions>>,
+        coerce_datatypes: Option<bool>,
+        quarantine_invalid_rows: Option<bool>,
+        language_tag_columns: Option<HashMap<String, String>>,
+        datatype_columns: Option<HashMap<String, String>>,
+        upsert: Option<bool>,
+        graph: Option<String>
     ) -> PyResult<Option<PyObject>> {
         let df = polars_df_to_rust_df(&df)?;
         let unique_subsets = if let Some(unique_subset) = unique_subset {
@@ -213,18 +339,72 @@ impl Mapping {
         let options = ExpandOptions {
             language_tags,
             unique_subsets,
-            caching_folder
+            caching_folder,
+            minting,
+            coerce_datatypes,
+            quarantine_invalid_rows,
+            language_tag_columns,
+            datatype_columns,
+            batch_size: None,
+            upsert,
+            graph,
+        };
+        let rust_options = options.to_rust_expand_options().map_err(PyMaplibError::from)?;
+
+        let report = self
+            .inner
+            .expand(template, df, rust_options)
+            .map_err(MaplibError::from)
+            .map_err(PyMaplibError::from)?;
+        Ok(Some(mapping_report_to_py_dict(&report, py)?))
+    }
+
+    #[pyo3(text_signature = "(template, path, batch_size, unique_subset, language_tags, minting, coerce_datatypes, quarantine_invalid_rows, language_tag_columns, datatype_columns, upsert, graph)")]
+    pub fn expand_from_files(
+        &mut self,
+        py: Python<'_>,
+        template: &str,
+        path: &str,
+        batch_size: Option<usize>,
+        unique_subset: Option<Vec<String>>,
+        language_tags: Option<HashMap<String, String>>,
+        minting: Option<HashMap<String, MintingOptions>>,
+        coerce_datatypes: Option<bool>,
+        quarantine_invalid_rows: Option<bool>,
+        language_tag_columns: Option<HashMap<String, String>>,
+        datatype_columns: Option<HashMap<String, String>>,
+        upsert: Option<bool>,
+        graph: Option<String>
+    ) -> PyResult<Option<PyObject>> {
+        let unique_subsets = if let Some(unique_subset) = unique_subset {
+            Some(vec![unique_subset.into_iter().collect()])
+        } else {
+            None
+        };
+        let options = ExpandOptions {
+            language_tags,
+            unique_subsets,
+            caching_folder: None,
+            minting,
+            coerce_datatypes,
+            quarantine_invalid_rows,
+            language_tag_columns,
+            datatype_columns,
+            batch_size,
+            upsert,
+            graph,
         };
+        let rust_options = options.to_rust_expand_options().map_err(PyMaplibError::from)?;
 
-        let mut _report = self
+        let report = self
             .inner
-            .expand(template, df, options.to_rust_expand_options())
+            .expand_from_files(template, path, rust_options)
             .map_err(MaplibError::from)
             .map_err(PyMaplibError::from)?;
-        Ok(None)
+        Ok(Some(mapping_report_to_py_dict(&report, py)?))
     }
 
-    #[pyo3(text_signature = "(template, primary_key_column, foreign_key_column, template_prefix, predicate_uri_prefix, language_tags, caching_folder)")]
+    #[pyo3(text_signature = "(template, primary_key_column, foreign_key_column, template_prefix, predicate_uri_prefix, language_tags, caching_folder, minting)")]
     pub fn expand_default(
         &mut self,
         df: &PyAny,
@@ -233,14 +413,24 @@ impl Mapping {
         template_prefix: Option<String>,
         predicate_uri_prefix: Option<String>,
         language_tags: Option<HashMap<String, String>>,
-        caching_folder: Option<String>
+        caching_folder: Option<String>,
+        minting: Option<HashMap<String, MintingOptions>>,
     ) -> PyResult<String> {
         let df = polars_df_to_rust_df(&df)?;
         let options = ExpandOptions {
             language_tags,
             unique_subsets:Some(vec![vec![primary_key_column.clone()]]),
             caching_folder,
+            minting,
+            coerce_datatypes: None,
+            quarantine_invalid_rows: None,
+            language_tag_columns: None,
+            datatype_columns: None,
+            batch_size: None,
+            upsert: None,
+            graph: None,
         };
+        let rust_options = options.to_rust_expand_options().map_err(PyMaplibError::from)?;
 
         let fk_cols = if let Some(fk_cols) = foreign_key_columns {
             fk_cols
@@ -248,16 +438,17 @@ impl Mapping {
             vec![]
         };
 
-        let tmpl = self.inner.expand_default(
+        let templates = self.inner.expand_default(
             df,
             primary_key_column,
             fk_cols,
             template_prefix,
             predicate_uri_prefix,
-            options.to_rust_expand_options()
+            rust_options
         ).map_err(MaplibError::from)
             .map_err(PyMaplibError::from)?;
-        return Ok(format!("{}", tmpl))
+        let formatted: Vec<String> = templates.iter().map(|t| format!("{}", t)).collect();
+        return Ok(formatted.join("\n"))
     }
 
     #[pyo3(text_signature = "(query)")]
@@ -274,6 +465,36 @@ impl Mapping {
         }
     }
 
+    #[pyo3(text_signature = "(template)")]
+    pub fn lower_template(&self, template: &str) -> PyResult<String> {
+        let query = self
+            .inner
+            .lower_template(template)
+            .map_err(MaplibError::from)
+            .map_err(PyMaplibError::from)?;
+        Ok(query.to_string())
+    }
+
+    #[pyo3(text_signature = "(template)")]
+    pub fn query_template(&mut self, py: Python<'_>, template: &str) -> PyResult<PyObject> {
+        let (df, _) = self
+            .inner
+            .query_template(template)
+            .map_err(MaplibError::from)
+            .map_err(PyMaplibError::from)?;
+        df_to_py_df(df, py)
+    }
+
+    #[pyo3(text_signature = "(call_uuid)")]
+    pub fn retract(&mut self, call_uuid: &str) -> PyResult<usize> {
+        let n_retracted = self
+            .inner
+            .retract(call_uuid)
+            .map_err(MaplibError::from)
+            .map_err(PyMaplibError::from)?;
+        Ok(n_retracted)
+    }
+
     #[pyo3(text_signature = "(query)")]
     pub fn insert(&mut self, query:String) -> PyResult<()> {
         self.inner.triplestore.insert(&query).map_err(PyMaplibError::from)?;
@@ -404,16 +625,62 @@ impl Mapping {
         Ok(())
     }
 
+    #[pyo3(text_signature = "(folder_path, compression, row_group_size, partition_by_predicate)")]
+    pub fn write_native_parquet(&mut self, path:&str, compression: Option<&str>, row_group_size: Option<usize>, partition_by_predicate: Option<bool>) -> PyResult<()> {
+        let mut options = NativeParquetWriteOptions::default();
+        if let Some(compression) = compression {
+            options.compression = NativeParquetWriteOptions::parse_compression(compression).map_err(PyMaplibError::from)?;
+        }
+        if let Some(row_group_size) = row_group_size {
+            options.row_group_size = Some(row_group_size);
+        }
+        options.partition_by_predicate = partition_by_predicate.unwrap_or(false);
+        self.inner.write_native_parquet(path, &options).map_err(|x| PyMaplibError::MappingError(x))?;
+        Ok(())
+    }
+
     #[pyo3(text_signature = "(folder_path)")]
-    pub fn write_native_parquet(&mut self, path:&str) -> PyResult<()> {
-        self.inner.write_native_parquet(path).map_err(|x| PyMaplibError::MappingError(x))?;
+    pub fn read_native_parquet(&mut self, path:&str) -> PyResult<()> {
+        self.inner.read_native_parquet(path).map_err(|x| PyMaplibError::MappingError(x))?;
         Ok(())
     }
 }
 
+fn mapping_report_to_py_dict(report: &MappingReport, py: Python<'_>) -> PyResult<PyObject> {
+    let triples_added = PyDict::new(py);
+    for (predicate, m) in &report.triples_added {
+        let object_types = PyDict::new(py);
+        for (object_type, n) in m {
+            let object_type_str = match object_type {
+                RDFNodeType::IRI => "IRI".to_string(),
+                RDFNodeType::BlankNode => "BlankNode".to_string(),
+                RDFNodeType::Literal(l) => l.as_str().to_string(),
+                RDFNodeType::None => "None".to_string(),
+            };
+            object_types.set_item(object_type_str, n)?;
+        }
+        triples_added.set_item(predicate, object_types)?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("call_uuid", &report.call_uuid)?;
+    dict.set_item("triples_added", triples_added)?;
+    dict.set_item("rows_dropped_null", report.rows_dropped_null)?;
+    dict.set_item("duplicates_removed", report.duplicates_removed)?;
+    dict.set_item("triples_retracted", report.triples_retracted)?;
+    dict.set_item("validation_seconds", report.validation_seconds)?;
+    dict.set_item("expansion_seconds", report.expansion_seconds)?;
+    dict.set_item("storage_seconds", report.storage_seconds)?;
+    dict.set_item("used_caching", report.used_caching)?;
+    if let Some(rejected_rows) = &report.rejected_rows {
+        dict.set_item("rejected_rows", df_to_py_df(rejected_rows.clone(), py)?)?;
+    }
+    Ok(dict.into())
+}
+
 #[pymodule]
 fn _maplib(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<Mapping>()?;
+    m.add_class::<MintingOptions>()?;
 
     Ok(())
 }
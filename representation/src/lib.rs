@@ -37,7 +37,7 @@ impl RDFNodeType {
     }
 
     pub fn find_triple_type(&self) -> TripleType {
-        let triple_type = if let RDFNodeType::IRI = self {
+        let triple_type = if let RDFNodeType::IRI | RDFNodeType::BlankNode = self {
             TripleType::ObjectProperty
         } else if let RDFNodeType::Literal(lit) = self {
             if lit.as_ref() == xsd::STRING {
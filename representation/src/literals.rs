@@ -1,42 +1,79 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Timelike, Utc};
 use oxrdf::vocab::xsd;
 use oxrdf::NamedNode;
 use polars_core::datatypes::TimeUnit;
 use polars_core::prelude::AnyValue;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+#[derive(Debug, PartialEq)]
+pub struct LiteralParsingError {
+    pub value: String,
+    pub datatype: String,
+}
+
+impl Display for LiteralParsingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Could not parse literal \"{}\" with datatype {}",
+            self.value, self.datatype
+        )
+    }
+}
+
+impl std::error::Error for LiteralParsingError {}
+
 //This code is copied from Chrontext, which has identical licensing
 pub fn sparql_literal_to_any_value(
     value: &String,
     datatype: &Option<NamedNode>,
-) -> (AnyValue<'static>, NamedNode) {
+) -> Result<(AnyValue<'static>, NamedNode), LiteralParsingError> {
     let (anyv, dt) = if let Some(nn) = datatype {
         let datatype = nn.as_ref();
+        let parse_error = || LiteralParsingError {
+            value: value.to_string(),
+            datatype: datatype.to_string(),
+        };
         let literal_value = if datatype == xsd::STRING {
             AnyValue::Utf8Owned(value.into())
         } else if datatype == xsd::UNSIGNED_INT {
-            let u = u32::from_str(value).expect("Integer parsing error");
+            let u = u32::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(u)
         } else if datatype == xsd::UNSIGNED_LONG {
-            let u = u64::from_str(value).expect("Integer parsing error");
+            let u = u64::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(u)
         } else if datatype == xsd::INTEGER {
-            let i = i64::from_str(value).expect("Integer parsing error");
+            let i = i64::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(i)
         } else if datatype == xsd::LONG {
-            let i = i64::from_str(value).expect("Integer parsing error");
+            let i = i64::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(i)
         } else if datatype == xsd::INT {
-            let i = i32::from_str(value).expect("Integer parsing error");
+            let i = i32::from_str(value).map_err(|_| parse_error())?;
+            AnyValue::from(i)
+        } else if datatype == xsd::SHORT {
+            let i = i16::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(i)
+        } else if datatype == xsd::UNSIGNED_SHORT {
+            let u = u16::from_str(value).map_err(|_| parse_error())?;
+            AnyValue::from(u)
+        } else if datatype == xsd::BYTE {
+            let i = i8::from_str(value).map_err(|_| parse_error())?;
+            AnyValue::from(i)
+        } else if datatype == xsd::UNSIGNED_BYTE {
+            let u = u8::from_str(value).map_err(|_| parse_error())?;
+            AnyValue::from(u)
         } else if datatype == xsd::DOUBLE {
-            let d = f64::from_str(value).expect("Integer parsing error");
+            let d = f64::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(d)
         } else if datatype == xsd::FLOAT {
-            let f = f32::from_str(value).expect("Integer parsing error");
+            let f = f32::from_str(value).map_err(|_| parse_error())?;
             AnyValue::from(f)
         } else if datatype == xsd::BOOLEAN {
-            let b = bool::from_str(value).expect("Boolean parsing error");
+            let b = bool::from_str(value).map_err(|_| parse_error())?;
             AnyValue::Boolean(b)
         } else if datatype == xsd::DATE_TIME {
             let dt_without_tz = value.parse::<NaiveDateTime>();
@@ -51,18 +88,108 @@ pub fn sparql_literal_to_any_value(
                         &None,
                     )
                 } else {
-                    panic!("Could not parse datetime: {}", value);
+                    return Err(parse_error());
                 }
             }
         } else if datatype == xsd::DECIMAL {
-            let d = f64::from_str(value).expect("Decimal parsing error");
-            AnyValue::from(d)
+            let (v, scale) = parse_xsd_decimal(value).ok_or_else(parse_error)?;
+            AnyValue::Decimal(v, scale)
+        } else if datatype == xsd::TIME {
+            let t = parse_xsd_time(value).ok_or_else(parse_error)?;
+            AnyValue::Time(
+                t.num_seconds_from_midnight() as i64 * 1_000_000_000 + t.nanosecond() as i64,
+            )
+        } else if datatype == xsd::DURATION {
+            let d = parse_xsd_duration(value).ok_or_else(parse_error)?;
+            AnyValue::Duration(d, TimeUnit::Nanoseconds)
+        } else if datatype == xsd::BASE_64_BINARY {
+            let b = general_purpose::STANDARD
+                .decode(value)
+                .map_err(|_| parse_error())?;
+            AnyValue::BinaryOwned(b)
         } else {
-            todo!("Not implemented!")
+            return Err(parse_error());
         };
         (literal_value, nn.clone())
     } else {
         (AnyValue::Utf8Owned(value.into()), xsd::STRING.into_owned())
     };
-    return (anyv.into_static().unwrap(), dt);
+    return Ok((anyv.into_static().unwrap(), dt));
+}
+
+//Times are kept without a timezone, so only UTC times are supported
+pub fn parse_xsd_time(value: &str) -> Option<NaiveTime> {
+    value
+        .strip_suffix('Z')
+        .unwrap_or(value)
+        .parse::<NaiveTime>()
+        .ok()
+}
+
+//Decimals are kept exact as an integer and a scale, e.g. 1.50 is (150, 2)
+pub fn parse_xsd_decimal(value: &str) -> Option<(i128, usize)> {
+    let (negative, unsigned) = if let Some(v) = value.strip_prefix('-') {
+        (true, v)
+    } else {
+        (false, value.strip_prefix('+').unwrap_or(value))
+    };
+    let (integer_part, fraction_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    if integer_part.is_empty() && fraction_part.is_empty() {
+        return None;
+    }
+    let digits = format!("{}{}", integer_part, fraction_part);
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let v = i128::from_str(&digits).ok()?;
+    Some((if negative { -v } else { v }, fraction_part.len()))
+}
+
+//Durations are kept in nanoseconds, so years and months are not supported as their length varies
+pub fn parse_xsd_duration(value: &str) -> Option<i64> {
+    let (negative, unsigned) = if let Some(v) = value.strip_prefix('-') {
+        (true, v)
+    } else {
+        (false, value)
+    };
+    let mut rest = unsigned.strip_prefix('P')?;
+    let mut nanos = 0i64;
+    let mut in_time = false;
+    let mut any_component = false;
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('T') {
+            in_time = true;
+            rest = r;
+            continue;
+        }
+        let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, designator) = (&rest[..end], &rest[end..end + 1]);
+        let component = match (in_time, designator) {
+            (false, "D") => i64::from_str(number).ok()? * 86_400_000_000_000,
+            (true, "H") => i64::from_str(number).ok()? * 3_600_000_000_000,
+            (true, "M") => i64::from_str(number).ok()? * 60_000_000_000,
+            (true, "S") => {
+                let (seconds, fraction) = number.split_once('.').unwrap_or((number, ""));
+                if fraction.len() > 9 {
+                    return None;
+                }
+                let fraction_nanos = if fraction.is_empty() {
+                    0
+                } else {
+                    i64::from_str(&format!("{:0<9}", fraction)).ok()?
+                };
+                i64::from_str(seconds).ok()? * 1_000_000_000 + fraction_nanos
+            }
+            _ => {
+                return None;
+            }
+        };
+        nanos += component;
+        any_component = true;
+        rest = &rest[end + 1..];
+    }
+    if !any_component {
+        return None;
+    }
+    Some(if negative { -nanos } else { nanos })
 }
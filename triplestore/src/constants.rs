@@ -1,3 +1,4 @@
 pub const XSD_DATETIME_WITHOUT_TZ_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
 pub const XSD_DATETIME_WITH_TZ_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f%:z";
 pub const XSD_DATE_WITHOUT_TZ_FORMAT: &str = "%Y-%m-%d";
+pub const XSD_TIME_FORMAT: &str = "%H:%M:%S%.f";
@@ -1,9 +1,12 @@
 use crate::constants::{
     XSD_DATETIME_WITHOUT_TZ_FORMAT, XSD_DATETIME_WITH_TZ_FORMAT, XSD_DATE_WITHOUT_TZ_FORMAT,
+    XSD_TIME_FORMAT,
 };
+use base64::engine::general_purpose;
+use base64::Engine as _;
 use chrono::TimeZone as ChronoTimeZone;
 use chrono::{Datelike, Timelike};
-use polars_core::datatypes::{DataType, TimeZone};
+use polars_core::datatypes::{DataType, TimeUnit, TimeZone, Utf8Chunked};
 use polars_core::series::{IntoSeries, Series};
 
 pub fn convert_to_string(series: &Series) -> Option<Series> {
@@ -34,16 +37,53 @@ pub fn convert_to_string(series: &Series) -> Option<Series> {
                 );
             }
         }
-        DataType::Duration(_) => {
-            todo!()
+        DataType::Duration(tu) => {
+            let nanos_per_unit = match tu {
+                TimeUnit::Nanoseconds => 1,
+                TimeUnit::Microseconds => 1_000,
+                TimeUnit::Milliseconds => 1_000_000,
+            };
+            let mut durations: Utf8Chunked = series
+                .duration()
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|x| x.map(|x| format_xsd_duration(x as i128 * nanos_per_unit)))
+                .collect();
+            durations.rename(series.name());
+            return Some(durations.into_series());
         }
         DataType::Time => {
-            todo!()
+            return Some(
+                series
+                    .time()
+                    .unwrap()
+                    .strftime(XSD_TIME_FORMAT)
+                    .into_series(),
+            )
         }
-        DataType::List(_) => {
-            panic!("Not supported")
+        DataType::Decimal(_, _) => {
+            let decimal = series.decimal().unwrap();
+            let scale = decimal.scale();
+            let mut decimals: Utf8Chunked = decimal
+                .0
+                .into_iter()
+                .map(|x| x.map(|x| format_xsd_decimal(x, scale)))
+                .collect();
+            decimals.rename(series.name());
+            return Some(decimals.into_series());
+        }
+        DataType::Binary => {
+            let mut encoded: Utf8Chunked = series
+                .binary()
+                .unwrap()
+                .into_iter()
+                .map(|x| x.map(|x| general_purpose::STANDARD.encode(x)))
+                .collect();
+            encoded.rename(series.name());
+            return Some(encoded.into_series());
         }
-        DataType::Categorical(_) => {
+        DataType::List(_) => {
             panic!("Not supported")
         }
         DataType::Struct(_) => {
@@ -57,6 +97,49 @@ pub fn convert_to_string(series: &Series) -> Option<Series> {
     Some(series.cast(&DataType::Utf8).unwrap())
 }
 
+fn format_xsd_decimal(v: i128, scale: usize) -> String {
+    let digits = format!("{:0>width$}", v.unsigned_abs(), width = scale + 1);
+    let (integer_part, fraction_part) = digits.split_at(digits.len() - scale);
+    let sign = if v < 0 { "-" } else { "" };
+    if scale == 0 {
+        format!("{}{}", sign, integer_part)
+    } else {
+        format!("{}{}.{}", sign, integer_part, fraction_part)
+    }
+}
+
+fn format_xsd_duration(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    let days = nanos / 86_400_000_000_000;
+    let hours = (nanos / 3_600_000_000_000) % 24;
+    let minutes = (nanos / 60_000_000_000) % 60;
+    let seconds = (nanos / 1_000_000_000) % 60;
+    let fraction = nanos % 1_000_000_000;
+    let mut out = format!("{}P", sign);
+    if days > 0 {
+        out.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || fraction > 0 || days == 0 {
+        out.push('T');
+        if hours > 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || fraction > 0 || (days == 0 && hours == 0 && minutes == 0) {
+            if fraction > 0 {
+                let fraction = format!("{:09}", fraction);
+                out.push_str(&format!("{}.{}S", seconds, fraction.trim_end_matches('0')));
+            } else {
+                out.push_str(&format!("{}S", seconds));
+            }
+        }
+    }
+    out
+}
+
 fn hack_format_timestamp_with_timezone(series: &Series, tz: &mut TimeZone) -> Series {
     let timezone_opt: Result<chrono_tz::Tz, _> = tz.parse();
     if let Ok(timezone) = timezone_opt {
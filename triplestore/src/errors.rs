@@ -12,6 +12,12 @@ pub enum TriplestoreError {
     FolderCreateIOError(io::Error),
     ReadCachingDirectoryError(io::Error),
     ReadCachingDirectoryEntryError(io::Error),
+    ManifestDoesNotExist(String),
+    InvalidManifest(String),
+    WriteManifestError(io::Error),
+    InvalidParquetCompression(String),
+    InvalidTermDictionary(String),
+    WriteTermDictionaryError(io::Error),
 }
 
 impl Display for TriplestoreError {
@@ -38,6 +44,28 @@ impl Display for TriplestoreError {
             TriplestoreError::ReadCachingDirectoryEntryError(e) => {
                 write!(f, "Read caching directory entry error {}", e)
             }
+            TriplestoreError::ManifestDoesNotExist(p) => {
+                write!(f, "Manifest {} does not exist", p)
+            }
+            TriplestoreError::InvalidManifest(s) => {
+                write!(f, "Invalid manifest: {}", s)
+            }
+            TriplestoreError::WriteManifestError(e) => {
+                write!(f, "Error writing manifest {}", e)
+            }
+            TriplestoreError::InvalidParquetCompression(c) => {
+                write!(
+                    f,
+                    "Invalid parquet compression {}, expected one of uncompressed, snappy, gzip, lzo, brotli, zstd and lz4",
+                    c
+                )
+            }
+            TriplestoreError::InvalidTermDictionary(s) => {
+                write!(f, "Invalid term dictionary: {}", s)
+            }
+            TriplestoreError::WriteTermDictionaryError(e) => {
+                write!(f, "Error writing term dictionary {}", e)
+            }
         }
     }
 }
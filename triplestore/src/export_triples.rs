@@ -2,7 +2,7 @@ use super::Triplestore;
 
 use crate::conversion::convert_to_string;
 use crate::errors::TriplestoreError;
-use oxrdf::{Literal, NamedNode, Subject, Term, Triple};
+use oxrdf::{BlankNode, Literal, NamedNode, Subject, Term, Triple};
 use polars_core::prelude::AnyValue;
 use representation::{RDFNodeType, TripleType};
 
@@ -15,11 +15,11 @@ impl Triplestore {
     where
         F: Fn(&str, &str, &str) -> T,
     {
-        for (verb, map) in &mut self.df_map {
+        for (verb, map) in self.graph_df_maps.get_mut(&None).into_iter().flatten() {
             for (k, v) in map {
                 if k.find_triple_type() == TripleType::ObjectProperty {
                     for i in 0..v.len() {
-                        let df = v.get_df(i)?;
+                        let df = self.term_dictionary.decode_triples(v.get_df(i)?, k);
                         if df.height() == 0 {
                             return Ok(());
                         }
@@ -47,11 +47,11 @@ impl Triplestore {
         F: Fn(&str, &str, &str, Option<&str>) -> T,
     {
         //subject, verb, lexical_form, language_tag, datatype
-        for (verb, map) in &mut self.df_map {
+        for (verb, map) in self.graph_df_maps.get_mut(&None).into_iter().flatten() {
             for (k, v) in map {
                 if k.find_triple_type() == TripleType::StringProperty {
                     for i in 0..v.len() {
-                        let df = v.get_df(i)?;
+                        let df = self.term_dictionary.decode_triples(v.get_df(i)?, k);
                         if df.height() == 0 {
                             return Ok(());
                         }
@@ -87,7 +87,7 @@ impl Triplestore {
         F: Fn(&str, &str, &str, &NamedNode) -> T,
     {
         //subject, verb, lexical_form, datatype
-        for (verb, map) in &mut self.df_map {
+        for (verb, map) in self.graph_df_maps.get_mut(&None).into_iter().flatten() {
             for (k, v) in map {
                 if k.find_triple_type() == TripleType::NonStringProperty {
                     let object_type = if let RDFNodeType::Literal(l) = k {
@@ -96,7 +96,7 @@ impl Triplestore {
                         panic!("Should never happen")
                     };
                     for i in 0..v.len() {
-                        let df = v.get_df(i)?;
+                        let df = self.term_dictionary.decode_triples(v.get_df(i)?, k);
                         if df.height() == 0 {
                             return Ok(());
                         }
@@ -125,13 +125,22 @@ impl Triplestore {
         Ok(())
     }
 
+    //Only the triples of the default graph are exported
     pub fn export_oxrdf_triples(&mut self) -> Result<Vec<Triple>, TriplestoreError> {
         self.deduplicate()?;
         fn subject_from_str(s: &str) -> Subject {
-            Subject::NamedNode(NamedNode::new_unchecked(s))
+            if let Some(id) = s.strip_prefix("_:") {
+                Subject::BlankNode(BlankNode::new_unchecked(id))
+            } else {
+                Subject::NamedNode(NamedNode::new_unchecked(s))
+            }
         }
         fn object_term_from_str(s: &str) -> Term {
-            Term::NamedNode(NamedNode::new_unchecked(s))
+            if let Some(id) = s.strip_prefix("_:") {
+                Term::BlankNode(BlankNode::new_unchecked(id))
+            } else {
+                Term::NamedNode(NamedNode::new_unchecked(s))
+            }
         }
 
         fn object_triple_func(s: &str, v: &str, o: &str) -> Triple {
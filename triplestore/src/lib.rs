@@ -3,27 +3,34 @@ pub(crate) mod conversion;
 pub mod errors;
 mod export_triples;
 mod io_funcs;
+mod manifest;
+pub mod native_parquet_read;
 pub mod native_parquet_write;
 mod ntriples_write;
+mod retract;
 pub mod sparql;
+pub mod term_dictionary;
 
+use crate::conversion::convert_to_string;
 use crate::errors::TriplestoreError;
 use crate::io_funcs::{create_folder_if_not_exists, delete_tmp_parquets_in_caching_folder};
+use crate::manifest::delete_manifest;
+use crate::term_dictionary::{delete_term_dictionary, TermDictionary};
 use log::debug;
 use oxrdf::vocab::xsd;
 use parquet_io::{
     property_to_filename, read_parquet, split_write_tmp_df, write_parquet, ParquetIOError,
 };
-use polars::prelude::{concat, IntoLazy, LazyFrame, UnionArgs};
+use polars::prelude::{col, concat, lit, IntoLazy, LazyFrame, UnionArgs};
 use polars_core::datatypes::AnyValue;
 use polars_core::frame::{DataFrame, UniqueKeepStrategy};
-use polars_core::prelude::DataType;
+use polars_core::prelude::{DataType, TakeRandom};
 use polars_core::series::Series;
 use polars_core::utils::concat_df;
 use rayon::iter::ParallelIterator;
 use rayon::iter::{IntoParallelRefIterator, ParallelDrainRange};
 use representation::RDFNodeType;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::remove_file;
 use std::io;
 use std::path::Path;
@@ -31,16 +38,31 @@ use std::time::Instant;
 use uuid::Uuid;
 
 const LANGUAGE_TAG_COLUMN: &str = "language_tag";
+const UNIQUE_KEY_COLUMN: &str = "unique_key";
+const CALL_UUID_COLUMN: &str = "call_uuid";
+const ROW_INDEX_COLUMN: &str = "row_index";
 
 pub struct Triplestore {
     deduplicated: bool,
     pub(crate) caching_folder: Option<String>,
-    df_map: HashMap<String, HashMap<RDFNodeType, TripleTable>>,
+    //Triple tables per graph, predicate and object type. The default graph is None.
+    graph_df_maps: HashMap<Option<String>, HashMap<String, HashMap<RDFNodeType, TripleTable>>>,
+    //The ids of the IRIs and blank nodes in the triple tables
+    pub(crate) term_dictionary: TermDictionary,
+    //What added the triples of each call, e.g. the template a mapping expanded
+    call_sources: HashMap<String, String>,
 }
 
 pub struct TripleTable {
     dfs: Option<Vec<DataFrame>>,
     df_paths: Option<Vec<String>>,
+    //The call that added each of the dfs or df_paths
+    df_call_uuids: Vec<String>,
+    //Triples of a call that another call added first. They are not queried, but are kept so that
+    //they are restored when the other call is retracted.
+    shadowed_dfs: Option<Vec<DataFrame>>,
+    shadowed_df_paths: Option<Vec<String>>,
+    shadowed_call_uuids: Vec<String>,
     unique: bool,
     call_uuid: String,
     tmp_df: Option<DataFrame>,
@@ -72,6 +94,61 @@ impl TripleTable {
         }
     }
 
+    //Keeps the triples of each call that no earlier call added, the other triples are shadowed.
+    //Returns the number of shadowed triples that are no longer shadowed.
+    pub(crate) fn deduplicate(
+        &mut self,
+        caching_folder: &Option<String>,
+        predicate: &str,
+    ) -> Result<usize, TriplestoreError> {
+        let n_visible = self.df_call_uuids.len();
+        let mut call_uuids = self.df_call_uuids.clone();
+        call_uuids.extend(self.shadowed_call_uuids.iter().cloned());
+        let n_promoted = if let Some(caching_folder) = caching_folder {
+            let mut paths = self.df_paths.take().unwrap();
+            paths.extend(self.shadowed_df_paths.take().unwrap());
+            let df_results: Vec<Result<DataFrame, ParquetIOError>> = paths
+                .par_iter()
+                .map(|x| read_parquet(x).map(|lf| lf.collect().unwrap()))
+                .collect();
+            let mut dfs = vec![];
+            for df_res in df_results {
+                dfs.push(df_res.map_err(|x| TriplestoreError::ParquetIOError(x))?);
+            }
+            let call_triples = unique_triples_per_call(dfs, &call_uuids, n_visible);
+            //TODO: Implement trick with len to avoid IO
+            let removed: Vec<Result<(), io::Error>> = paths
+                .par_iter()
+                .map(|x| remove_file(Path::new(x)))
+                .collect();
+            for r in removed {
+                r.map_err(|x| TriplestoreError::RemoveParquetFileError(x))?
+            }
+            let (df_call_uuids, df_paths) =
+                write_call_triples(caching_folder, call_triples.unique, predicate)?;
+            let (shadowed_call_uuids, shadowed_df_paths) =
+                write_call_triples(caching_folder, call_triples.shadowed, predicate)?;
+            self.df_paths = Some(df_paths);
+            self.df_call_uuids = df_call_uuids;
+            self.shadowed_df_paths = Some(shadowed_df_paths);
+            self.shadowed_call_uuids = shadowed_call_uuids;
+            call_triples.n_promoted
+        } else {
+            let mut dfs = self.dfs.take().unwrap();
+            dfs.extend(self.shadowed_dfs.take().unwrap());
+            let call_triples = unique_triples_per_call(dfs, &call_uuids, n_visible);
+            let (df_call_uuids, dfs) = call_triples.unique.into_iter().unzip();
+            let (shadowed_call_uuids, shadowed_dfs) = call_triples.shadowed.into_iter().unzip();
+            self.dfs = Some(dfs);
+            self.df_call_uuids = df_call_uuids;
+            self.shadowed_dfs = Some(shadowed_dfs);
+            self.shadowed_call_uuids = shadowed_call_uuids;
+            call_triples.n_promoted
+        };
+        self.unique = true;
+        Ok(n_promoted)
+    }
+
     pub(crate) fn forget_tmp_df(&mut self) {
         self.tmp_df = None;
     }
@@ -97,88 +174,104 @@ pub struct TriplesToAdd {
     pub df: DataFrame,
     pub object_type: RDFNodeType,
     pub language_tag: Option<String>,
+    //Column with a language tag per row, takes precedence over language_tag
+    pub language_tag_column: Option<String>,
     pub static_verb_column: Option<String>,
     pub has_unique_subset: bool,
+    //The named graph to add the triples to, None is the default graph
+    pub graph: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AddTriplesReport {
+    pub triples_added: HashMap<String, HashMap<RDFNodeType, usize>>,
+    pub rows_dropped_null: usize,
+    pub duplicates_removed: usize,
+}
+
+impl AddTriplesReport {
+    pub fn merge(&mut self, other: AddTriplesReport) {
+        let AddTriplesReport {
+            triples_added,
+            rows_dropped_null,
+            duplicates_removed,
+        } = other;
+        for (predicate, m) in triples_added {
+            let self_m = self.triples_added.entry(predicate).or_default();
+            for (object_type, n) in m {
+                *self_m.entry(object_type).or_insert(0) += n;
+            }
+        }
+        self.rows_dropped_null += rows_dropped_null;
+        self.duplicates_removed += duplicates_removed;
+    }
 }
 
 pub struct TripleDF {
     df: DataFrame,
     predicate: String,
     object_type: RDFNodeType,
+    graph: Option<String>,
 }
 
 impl Triplestore {
+    //Creates an empty triplestore, any triples in the caching folder are deleted.
+    //Use open to continue with the triples in the caching folder.
     pub fn new(caching_folder: Option<String>) -> Result<Triplestore, TriplestoreError> {
         if let Some(caching_folder) = &caching_folder {
             let path = Path::new(caching_folder);
             create_folder_if_not_exists(path)?;
             delete_tmp_parquets_in_caching_folder(path)?;
+            delete_manifest(path)?;
+            delete_term_dictionary(path)?;
         }
         Ok(Triplestore {
-            df_map: HashMap::new(),
+            graph_df_maps: HashMap::new(),
+            term_dictionary: TermDictionary::default(),
+            call_sources: HashMap::new(),
             deduplicated: true,
             caching_folder,
         })
     }
 
+    //Records what added the triples of the call, it is kept in the manifest of the caching folder
+    pub fn set_call_source(&mut self, call_uuid: &str, source: &str) {
+        self.call_sources
+            .insert(call_uuid.to_string(), source.to_string());
+    }
+
+    pub fn calls_with_source(&self, source: &str) -> HashSet<String> {
+        self.call_sources
+            .iter()
+            .filter(|(_, s)| s.as_str() == source)
+            .map(|(call_uuid, _)| call_uuid.clone())
+            .collect()
+    }
+
+    //The object types of the triples with the predicate in the graph
+    pub fn predicate_object_types(
+        &self,
+        predicate: &str,
+        graph: &Option<String>,
+    ) -> Vec<RDFNodeType> {
+        if let Some(m) = self.graph_df_maps.get(graph).and_then(|x| x.get(predicate)) {
+            m.keys().cloned().collect()
+        } else {
+            vec![]
+        }
+    }
+
     pub fn deduplicate(&mut self) -> Result<(), TriplestoreError> {
         let now = Instant::now();
-        for (predicate, map) in &mut self.df_map {
+        for (predicate, map) in self.graph_df_maps.values_mut().flatten() {
             for (_, v) in map {
                 if !v.unique {
-                    if self.caching_folder.is_some() {
-                        let lf_results: Vec<Result<LazyFrame, ParquetIOError>> = v
-                            .df_paths
-                            .as_ref()
-                            .unwrap()
-                            .par_iter()
-                            .map(|x| read_parquet(x))
-                            .collect();
-                        let mut lfs = vec![];
-                        for lf_res in lf_results {
-                            lfs.push(lf_res.map_err(|x| TriplestoreError::ParquetIOError(x))?);
-                        }
-                        let unique_df = concat(lfs, UnionArgs::default())
-                            .unwrap()
-                            .unique(None, UniqueKeepStrategy::First)
-                            .collect()
-                            .unwrap();
-                        //TODO: Implement trick with len to avoid IO
-                        let removed: Vec<Result<(), io::Error>> = v
-                            .df_paths
-                            .as_ref()
-                            .unwrap()
-                            .par_iter()
-                            .map(|x| remove_file(Path::new(x)))
-                            .collect();
-                        for r in removed {
-                            r.map_err(|x| TriplestoreError::RemoveParquetFileError(x))?
-                        }
-                        let paths = split_write_tmp_df(
-                            self.caching_folder.as_ref().unwrap(),
-                            unique_df,
-                            predicate,
-                        )
-                        .map_err(|x| TriplestoreError::ParquetIOError(x))?;
-                        v.df_paths = Some(paths);
-                        v.unique = true;
-                    } else {
-                        let drained: Vec<LazyFrame> = v
-                            .dfs
-                            .as_mut()
-                            .unwrap()
-                            .drain(..)
-                            .map(|x| x.lazy())
-                            .collect();
-                        let mut lf = concat(drained.as_slice(), UnionArgs::default()).unwrap();
-                        lf = lf.unique(None, UniqueKeepStrategy::First);
-                        v.dfs.as_mut().unwrap().push(lf.collect().unwrap());
-                        v.unique = true;
-                    }
+                    v.deduplicate(&self.caching_folder, predicate)?;
                 }
             }
         }
         self.deduplicated = true;
+        self.write_manifest()?;
         debug!("Deduplication took {} seconds", now.elapsed().as_secs_f64());
         Ok(())
     }
@@ -187,37 +280,56 @@ impl Triplestore {
         &mut self,
         mut ts: Vec<TriplesToAdd>,
         call_uuid: &String,
-    ) -> Result<(), TriplestoreError> {
-        let df_vecs_to_add: Vec<Vec<TripleDF>> = ts
+    ) -> Result<AddTriplesReport, TriplestoreError> {
+        let df_vecs_to_add: Vec<(Vec<TripleDF>, AddTriplesReport)> = ts
             .par_drain(..)
             .map(|t| {
                 let TriplesToAdd {
                     df,
                     object_type,
                     language_tag,
+                    language_tag_column,
                     static_verb_column,
                     has_unique_subset,
+                    graph,
                 } = t;
                 let prepared_triples = prepare_triples(
                     df,
                     &object_type,
                     &language_tag,
+                    &language_tag_column,
                     static_verb_column,
                     has_unique_subset,
+                    &graph,
                 );
                 prepared_triples
             })
             .collect();
-        let dfs_to_add = flatten(df_vecs_to_add);
+        let mut report = AddTriplesReport::default();
+        let mut df_vecs = vec![];
+        for (dfs, r) in df_vecs_to_add {
+            report.merge(r);
+            df_vecs.push(dfs);
+        }
+        let dfs_to_add = flatten(df_vecs);
         self.add_triples_df(dfs_to_add, call_uuid)?;
-        Ok(())
+        Ok(report)
     }
 
     fn add_triples_df(
         &mut self,
-        triples_df: Vec<TripleDF>,
+        mut triples_df: Vec<TripleDF>,
         call_uuid: &String,
     ) -> Result<(), TriplestoreError> {
+        let now = Instant::now();
+        for tdf in &mut triples_df {
+            self.term_dictionary
+                .encode_triples(&mut tdf.df, &tdf.object_type);
+        }
+        debug!(
+            "Encoding terms took {} seconds",
+            now.elapsed().as_secs_f32()
+        );
         if let Some(_) = &self.caching_folder {
             self.add_triples_df_with_caching_folder(triples_df, call_uuid)?;
         } else {
@@ -232,36 +344,41 @@ impl Triplestore {
         call_uuid: &String,
     ) -> Result<(), TriplestoreError> {
         let folder_path = Path::new(self.caching_folder.as_ref().unwrap());
-        let file_paths: Vec<(String, Result<_, _>, String, RDFNodeType)> = triples_df
-            .par_drain(..)
-            .map(|tdf| {
-                let TripleDF {
-                    mut df,
-                    predicate,
-                    object_type,
-                } = tdf;
-                let file_name = format!(
-                    "tmp_{}_{}.parquet",
-                    property_to_filename(&predicate),
-                    Uuid::new_v4()
-                );
-                let mut file_path_buf = folder_path.to_path_buf();
-                file_path_buf.push(file_name);
-                let file_path = file_path_buf.as_path();
-                (
-                    file_path.to_str().unwrap().to_string(),
-                    write_parquet(&mut df, &file_path),
-                    predicate,
-                    object_type,
-                )
-            })
-            .collect();
-        for (file_path, res, predicate, object_type) in file_paths {
+        let file_paths: Vec<(String, Result<_, _>, String, RDFNodeType, Option<String>)> =
+            triples_df
+                .par_drain(..)
+                .map(|tdf| {
+                    let TripleDF {
+                        mut df,
+                        predicate,
+                        object_type,
+                        graph,
+                    } = tdf;
+                    let file_name = format!(
+                        "tmp_{}_{}.parquet",
+                        property_to_filename(&predicate),
+                        Uuid::new_v4()
+                    );
+                    let mut file_path_buf = folder_path.to_path_buf();
+                    file_path_buf.push(file_name);
+                    let file_path = file_path_buf.as_path();
+                    (
+                        file_path.to_str().unwrap().to_string(),
+                        write_parquet(&mut df, &file_path),
+                        predicate,
+                        object_type,
+                        graph,
+                    )
+                })
+                .collect();
+        for (file_path, res, predicate, object_type, graph) in file_paths {
             res.map_err(|x| TriplestoreError::ParquetIOError(x))?;
+            let df_map = self.graph_df_maps.entry(graph).or_default();
             //Safe to assume everything is unique
-            if let Some(m) = self.df_map.get_mut(&predicate) {
+            if let Some(m) = df_map.get_mut(&predicate) {
                 if let Some(v) = m.get_mut(&object_type) {
                     v.df_paths.as_mut().unwrap().push(file_path);
+                    v.df_call_uuids.push(call_uuid.clone());
                     v.unique = v.unique && (call_uuid == &v.call_uuid);
                     if !v.unique {
                         self.deduplicated = false;
@@ -272,6 +389,10 @@ impl Triplestore {
                         TripleTable {
                             dfs: None,
                             df_paths: Some(vec![file_path]),
+                            df_call_uuids: vec![call_uuid.clone()],
+                            shadowed_dfs: None,
+                            shadowed_df_paths: Some(vec![]),
+                            shadowed_call_uuids: vec![],
                             unique: true,
                             call_uuid: call_uuid.clone(),
                             tmp_df: None,
@@ -279,13 +400,17 @@ impl Triplestore {
                     );
                 }
             } else {
-                self.df_map.insert(
+                df_map.insert(
                     predicate,
                     HashMap::from([(
                         object_type,
                         TripleTable {
                             dfs: None,
                             df_paths: Some(vec![file_path]),
+                            df_call_uuids: vec![call_uuid.clone()],
+                            shadowed_dfs: None,
+                            shadowed_df_paths: Some(vec![]),
+                            shadowed_call_uuids: vec![],
                             unique: true,
                             call_uuid: call_uuid.clone(),
                             tmp_df: None,
@@ -294,6 +419,7 @@ impl Triplestore {
                 );
             }
         }
+        self.write_manifest()?;
         Ok(())
     }
 
@@ -302,12 +428,15 @@ impl Triplestore {
             df,
             predicate,
             object_type,
+            graph,
         } in triples_df
         {
+            let df_map = self.graph_df_maps.entry(graph).or_default();
             //Safe to assume everything is unique
-            if let Some(m) = self.df_map.get_mut(&predicate) {
+            if let Some(m) = df_map.get_mut(&predicate) {
                 if let Some(v) = m.get_mut(&object_type) {
                     v.dfs.as_mut().unwrap().push(df);
+                    v.df_call_uuids.push(call_uuid.clone());
                     v.unique = v.unique && (call_uuid == &v.call_uuid);
                     if !v.unique {
                         self.deduplicated = false;
@@ -318,6 +447,10 @@ impl Triplestore {
                         TripleTable {
                             dfs: Some(vec![df]),
                             df_paths: None,
+                            df_call_uuids: vec![call_uuid.clone()],
+                            shadowed_dfs: Some(vec![]),
+                            shadowed_df_paths: None,
+                            shadowed_call_uuids: vec![],
                             unique: true,
                             call_uuid: call_uuid.clone(),
                             tmp_df: None,
@@ -325,13 +458,17 @@ impl Triplestore {
                     );
                 }
             } else {
-                self.df_map.insert(
+                df_map.insert(
                     predicate,
                     HashMap::from([(
                         object_type,
                         TripleTable {
                             dfs: Some(vec![df]),
                             df_paths: None,
+                            df_call_uuids: vec![call_uuid.clone()],
+                            shadowed_dfs: Some(vec![]),
+                            shadowed_df_paths: None,
+                            shadowed_call_uuids: vec![],
                             unique: true,
                             call_uuid: call_uuid.clone(),
                             tmp_df: None,
@@ -347,22 +484,32 @@ pub fn prepare_triples(
     mut df: DataFrame,
     object_type: &RDFNodeType,
     language_tag: &Option<String>,
+    language_tag_column: &Option<String>,
     static_verb_column: Option<String>,
     has_unique_subset: bool,
-) -> Vec<TripleDF> {
+    graph: &Option<String>,
+) -> (Vec<TripleDF>, AddTriplesReport) {
     let now = Instant::now();
     let mut out_df_vec = vec![];
+    let mut report = AddTriplesReport::default();
     if df.height() == 0 {
-        return vec![];
+        return (vec![], report);
+    }
+    let mut keep_cols = vec!["subject", "object"];
+    if let Some(language_tag_column) = language_tag_column {
+        df.rename(language_tag_column, LANGUAGE_TAG_COLUMN).unwrap();
+        keep_cols.push(LANGUAGE_TAG_COLUMN);
     }
     if let Some(static_verb_column) = static_verb_column {
-        df = df.select(["subject", "object"]).unwrap();
+        df = df.select(keep_cols).unwrap();
         if let Some(tdf) = prepare_triples_df(
             df,
             static_verb_column,
             object_type,
             language_tag,
             has_unique_subset,
+            graph,
+            &mut report,
         ) {
             out_df_vec.push(tdf);
         }
@@ -378,13 +525,15 @@ pub fn prepare_triples(
                     panic!()
                 }
             }
-            part = part.select(["subject", "object"]).unwrap();
+            part = part.select(keep_cols.as_slice()).unwrap();
             if let Some(tdf) = prepare_triples_df(
                 part,
                 predicate,
                 object_type,
                 language_tag,
                 has_unique_subset,
+                graph,
+                &mut report,
             ) {
                 out_df_vec.push(tdf);
             }
@@ -394,7 +543,7 @@ pub fn prepare_triples(
         "Adding triples took {} seconds",
         now.elapsed().as_secs_f32()
     );
-    out_df_vec
+    (out_df_vec, report)
 }
 
 fn prepare_triples_df(
@@ -403,9 +552,20 @@ fn prepare_triples_df(
     object_type: &RDFNodeType,
     language_tag: &Option<String>,
     has_unique_subset: bool,
+    graph: &Option<String>,
+    report: &mut AddTriplesReport,
 ) -> Option<TripleDF> {
     let now = Instant::now();
-    df = df.drop_nulls::<String>(None).unwrap();
+    let height_before_drop = df.height();
+    df = df
+        .drop_nulls(Some(&["subject".to_string(), "object".to_string()]))
+        .unwrap();
+    report.rows_dropped_null += height_before_drop - df.height();
+    //Only strings have language tags
+    if !object_type.is_lit_type(xsd::STRING) && df.get_column_names().contains(&LANGUAGE_TAG_COLUMN)
+    {
+        df = df.drop(LANGUAGE_TAG_COLUMN).unwrap();
+    }
     if df.height() == 0 {
         return None;
     }
@@ -414,15 +574,24 @@ fn prepare_triples_df(
         now.elapsed().as_secs_f32()
     );
     if !has_unique_subset {
-        df = df.unique(None, UniqueKeepStrategy::First, None).unwrap();
+        let height_before_unique = df.height();
+        df = unique_triples(df);
+        report.duplicates_removed += height_before_unique - df.height();
     }
+    *report
+        .triples_added
+        .entry(predicate.clone())
+        .or_default()
+        .entry(object_type.clone())
+        .or_insert(0) += df.height();
     debug!(
         "Prepare single triple df unique before it is added took {} seconds",
         now.elapsed().as_secs_f32()
     );
 
-    if let RDFNodeType::Literal(lit) = object_type {
-        if lit.as_ref() == xsd::STRING {
+    if object_type.is_lit_type(xsd::STRING) {
+        //Language tags given per row are already in the language tag column
+        if !df.get_column_names().contains(&LANGUAGE_TAG_COLUMN) {
             if let Some(tag) = language_tag {
                 let lt_ser = Series::new_empty(LANGUAGE_TAG_COLUMN, &DataType::Utf8)
                     .extend_constant(AnyValue::Utf8(tag), df.height())
@@ -443,9 +612,146 @@ fn prepare_triples_df(
         df,
         predicate,
         object_type: object_type.clone(),
+        graph: graph.clone(),
     })
 }
 
+fn unique_triples(df: DataFrame) -> DataFrame {
+    unique_triples_in(df, false)
+}
+
+//Decimals can not be hashed, so duplicates are found using their string representation instead.
+//With per_call, triples are only duplicates of triples of the same call.
+fn unique_triples_in(df: DataFrame, per_call: bool) -> DataFrame {
+    let mut subset: Vec<String> = df
+        .get_column_names()
+        .into_iter()
+        .filter(|x| (per_call || *x != CALL_UUID_COLUMN) && *x != ROW_INDEX_COLUMN)
+        .map(|x| x.to_string())
+        .collect();
+    let object = df.column("object").unwrap();
+    if let DataType::Decimal(_, _) = object.dtype() {
+        let mut key = convert_to_string(object).unwrap();
+        key.rename(UNIQUE_KEY_COLUMN);
+        subset.retain(|x| x != "object");
+        subset.push(UNIQUE_KEY_COLUMN.to_string());
+        let mut df = df;
+        df.with_column(key).unwrap();
+        df.unique(Some(&subset), UniqueKeepStrategy::First, None)
+            .unwrap()
+            .drop(UNIQUE_KEY_COLUMN)
+            .unwrap()
+    } else {
+        df.unique(Some(&subset), UniqueKeepStrategy::First, None)
+            .unwrap()
+    }
+}
+
+struct CallTriples {
+    unique: Vec<(String, DataFrame)>,
+    //Duplicates of triples of an earlier call
+    shadowed: Vec<(String, DataFrame)>,
+    //Triples among the shadowed dfs that are no longer shadowed
+    n_promoted: usize,
+}
+
+//Duplicates are kept by the call that first added them, so that each call keeps its own dfs. The
+//first n_visible dfs are the ones that are not shadowed, they come before the shadowed dfs.
+fn unique_triples_per_call(
+    dfs: Vec<DataFrame>,
+    call_uuids: &[String],
+    n_visible: usize,
+) -> CallTriples {
+    let n_visible_rows: usize = dfs[..n_visible].iter().map(|x| x.height()).sum();
+    let lfs: Vec<LazyFrame> = dfs
+        .into_iter()
+        .zip(call_uuids)
+        .map(|(df, call_uuid)| {
+            df.lazy()
+                .with_column(lit(call_uuid.clone()).alias(CALL_UUID_COLUMN))
+        })
+        .collect();
+    let df = concat(lfs, UnionArgs::default())
+        .unwrap()
+        .collect()
+        .unwrap()
+        .with_row_count(ROW_INDEX_COLUMN, None)
+        .unwrap();
+    let df = unique_triples_in(df, true);
+    let unique = unique_triples_in(df.clone(), false);
+    let unique_rows = unique.column(ROW_INDEX_COLUMN).unwrap().clone();
+    let n_promoted = unique_rows
+        .idx()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .filter(|x| *x as usize >= n_visible_rows)
+        .count();
+    let shadowed = df
+        .lazy()
+        .filter(col(ROW_INDEX_COLUMN).is_in(lit(unique_rows)).not())
+        .collect()
+        .unwrap();
+    let mut unique = partition_by_call(unique);
+    //Tables are never left without dfs
+    if unique.is_empty() {
+        let df = shadowed
+            .drop(CALL_UUID_COLUMN)
+            .unwrap()
+            .drop(ROW_INDEX_COLUMN)
+            .unwrap();
+        unique.push((call_uuids.first().unwrap().clone(), df));
+    }
+    CallTriples {
+        unique,
+        shadowed: partition_by_call(shadowed),
+        n_promoted,
+    }
+}
+
+fn partition_by_call(df: DataFrame) -> Vec<(String, DataFrame)> {
+    if df.height() == 0 {
+        return vec![];
+    }
+    df.partition_by_stable([CALL_UUID_COLUMN], true)
+        .unwrap()
+        .into_iter()
+        .map(|part| {
+            let call_uuid = part
+                .column(CALL_UUID_COLUMN)
+                .unwrap()
+                .utf8()
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .to_string();
+            let part = part
+                .drop(CALL_UUID_COLUMN)
+                .unwrap()
+                .drop(ROW_INDEX_COLUMN)
+                .unwrap();
+            (call_uuid, part)
+        })
+        .collect()
+}
+
+//Writes the dfs of each call, returns the call of each of the written files
+fn write_call_triples(
+    caching_folder: &String,
+    call_triples: Vec<(String, DataFrame)>,
+    predicate: &str,
+) -> Result<(Vec<String>, Vec<String>), TriplestoreError> {
+    let mut call_uuids = vec![];
+    let mut paths = vec![];
+    for (call_uuid, df) in call_triples {
+        let call_paths = split_write_tmp_df(caching_folder, df, &property_to_filename(predicate))
+            .map_err(|x| TriplestoreError::ParquetIOError(x))?;
+        call_uuids.extend(vec![call_uuid; call_paths.len()]);
+        paths.extend(call_paths);
+    }
+    Ok((call_uuids, paths))
+}
+
 //From: https://users.rust-lang.org/t/flatten-a-vec-vec-t-to-a-vec-t/24526/3
 fn flatten<T>(nested: Vec<Vec<T>>) -> Vec<T> {
     nested.into_iter().flatten().collect()
@@ -0,0 +1,350 @@
+use super::{TripleTable, Triplestore};
+use crate::errors::TriplestoreError;
+use crate::io_funcs::create_folder_if_not_exists;
+use crate::term_dictionary::TermDictionary;
+use oxrdf::vocab::xsd;
+use oxrdf::NamedNode;
+use parquet_io::{read_parquet, write_parquet};
+use polars_core::frame::DataFrame;
+use polars_core::prelude::{NamedFrom, TakeRandom};
+use polars_core::series::Series;
+use representation::RDFNodeType;
+use std::collections::{HashMap, HashSet};
+use std::fs::{read_dir, remove_file, rename};
+use std::path::Path;
+
+pub(crate) const MANIFEST_FILENAME: &str = "manifest.parquet";
+const MANIFEST_TMP_FILENAME: &str = "manifest.parquet.tmp";
+const GRAPH_COLUMN: &str = "graph";
+const PREDICATE_COLUMN: &str = "predicate";
+const OBJECT_TYPE_COLUMN: &str = "object_type";
+const HAS_LANGUAGE_TAG_COLUMN: &str = "has_language_tag";
+const FILE_COLUMN: &str = "file";
+const CALL_UUID_COLUMN: &str = "call_uuid";
+const UNIQUE_COLUMN: &str = "unique";
+const SOURCE_COLUMN: &str = "source";
+const SHADOWED_COLUMN: &str = "shadowed";
+const IRI_OBJECT_TYPE: &str = "IRI";
+const BLANK_NODE_OBJECT_TYPE: &str = "BlankNode";
+
+type GraphDfMaps = HashMap<Option<String>, HashMap<String, HashMap<RDFNodeType, TripleTable>>>;
+
+impl Triplestore {
+    //Opens a triplestore previously written to the caching folder, using the manifest in the folder.
+    //Parquet files in the folder that are not in the manifest are left over from an interrupted
+    //session, and are deleted.
+    pub fn open(caching_folder: String) -> Result<Triplestore, TriplestoreError> {
+        let folder_path = Path::new(&caching_folder);
+        let entries = read_manifest_file(folder_path)?;
+        let mut call_sources = HashMap::new();
+        for e in &entries {
+            if let Some(source) = &e.source {
+                call_sources.insert(e.call_uuid.clone(), source.clone());
+            }
+        }
+        let graph_df_maps = manifest_entries_to_graph_df_maps(entries, folder_path);
+        let term_dictionary = TermDictionary::read(folder_path)?;
+
+        let mut manifest_files = HashSet::new();
+        for df_map in graph_df_maps.values() {
+            for v in df_map.values().flat_map(|m| m.values()) {
+                manifest_files.extend(v.df_paths.as_ref().unwrap().iter().cloned());
+                manifest_files.extend(v.shadowed_df_paths.as_ref().unwrap().iter().cloned());
+            }
+        }
+        delete_parquets_not_in_manifest(folder_path, &manifest_files)?;
+
+        let deduplicated = graph_df_maps
+            .values()
+            .flat_map(|m| m.values())
+            .flat_map(|m| m.values())
+            .all(|v| v.unique);
+        Ok(Triplestore {
+            deduplicated,
+            caching_folder: Some(caching_folder),
+            graph_df_maps,
+            term_dictionary,
+            call_sources,
+        })
+    }
+
+    //Writes the manifest of the triple tables in the caching folder, if there is one.
+    //New terms are written first, as the triple tables in the manifest refer to their ids.
+    pub(crate) fn write_manifest(&mut self) -> Result<(), TriplestoreError> {
+        let caching_folder = if let Some(caching_folder) = &self.caching_folder {
+            caching_folder
+        } else {
+            return Ok(());
+        };
+        self.term_dictionary
+            .write_new_terms(Path::new(caching_folder))?;
+        let mut entries = vec![];
+        for (graph, df_map) in &self.graph_df_maps {
+            for (predicate, map) in df_map {
+                for (object_type, v) in map {
+                    let visible = v.df_paths.as_ref().unwrap().iter().zip(&v.df_call_uuids);
+                    let shadowed = v
+                        .shadowed_df_paths
+                        .as_ref()
+                        .unwrap()
+                        .iter()
+                        .zip(&v.shadowed_call_uuids);
+                    for (shadowed, (path, call_uuid)) in visible
+                        .map(|x| (false, x))
+                        .chain(shadowed.map(|x| (true, x)))
+                    {
+                        //Files are stored relative to the caching folder, so that it can be moved
+                        let file_name = Path::new(path)
+                            .file_name()
+                            .unwrap()
+                            .to_str()
+                            .unwrap()
+                            .to_string();
+                        entries.push(ManifestEntry {
+                            graph: graph.clone(),
+                            predicate: predicate.clone(),
+                            object_type: object_type.clone(),
+                            file: file_name,
+                            call_uuid: call_uuid.clone(),
+                            unique: v.unique,
+                            source: self.call_sources.get(call_uuid).cloned(),
+                            shadowed,
+                        });
+                    }
+                }
+            }
+        }
+        write_manifest_file(Path::new(caching_folder), entries)
+    }
+}
+
+pub(crate) fn delete_manifest(caching_folder: &Path) -> Result<(), TriplestoreError> {
+    for file_name in [MANIFEST_FILENAME, MANIFEST_TMP_FILENAME] {
+        let path = caching_folder.join(file_name);
+        if path.exists() {
+            remove_file(path).map_err(|x| TriplestoreError::RemoveParquetFileError(x))?;
+        }
+    }
+    Ok(())
+}
+
+//A parquet file in a folder of triples, with what is needed to interpret its contents.
+//The language tags of string literals are in the language tag column of the file.
+pub(crate) struct ManifestEntry {
+    pub(crate) graph: Option<String>,
+    pub(crate) predicate: String,
+    pub(crate) object_type: RDFNodeType,
+    //Relative to the folder of the manifest
+    pub(crate) file: String,
+    pub(crate) call_uuid: String,
+    pub(crate) unique: bool,
+    //What added the triples of the call, see Triplestore::set_call_source
+    pub(crate) source: Option<String>,
+    //The triples are duplicates of triples of another call, see TripleTable
+    pub(crate) shadowed: bool,
+}
+
+//The manifest is written to a temporary file first, so that an interrupted write does not leave
+//a broken manifest behind.
+pub(crate) fn write_manifest_file(
+    folder_path: &Path,
+    entries: Vec<ManifestEntry>,
+) -> Result<(), TriplestoreError> {
+    create_folder_if_not_exists(folder_path)?;
+    let mut graphs = vec![];
+    let mut predicates = vec![];
+    let mut object_types = vec![];
+    let mut has_language_tags = vec![];
+    let mut files = vec![];
+    let mut call_uuids = vec![];
+    let mut uniques = vec![];
+    let mut sources = vec![];
+    let mut shadoweds = vec![];
+    for e in entries {
+        has_language_tags.push(e.object_type.is_lit_type(xsd::STRING));
+        object_types.push(object_type_to_string(&e.object_type));
+        graphs.push(e.graph);
+        predicates.push(e.predicate);
+        files.push(e.file);
+        call_uuids.push(e.call_uuid);
+        uniques.push(e.unique);
+        sources.push(e.source);
+        shadoweds.push(e.shadowed);
+    }
+    let mut manifest = DataFrame::new(vec![
+        Series::new(GRAPH_COLUMN, graphs),
+        Series::new(PREDICATE_COLUMN, predicates),
+        Series::new(OBJECT_TYPE_COLUMN, object_types),
+        Series::new(HAS_LANGUAGE_TAG_COLUMN, has_language_tags),
+        Series::new(FILE_COLUMN, files),
+        Series::new(CALL_UUID_COLUMN, call_uuids),
+        Series::new(UNIQUE_COLUMN, uniques),
+        Series::new(SOURCE_COLUMN, sources),
+        Series::new(SHADOWED_COLUMN, shadoweds),
+    ])
+    .unwrap();
+    let tmp_path = folder_path.join(MANIFEST_TMP_FILENAME);
+    write_parquet(&mut manifest, &tmp_path).map_err(|x| TriplestoreError::ParquetIOError(x))?;
+    rename(&tmp_path, folder_path.join(MANIFEST_FILENAME))
+        .map_err(|x| TriplestoreError::WriteManifestError(x))?;
+    Ok(())
+}
+
+pub(crate) fn read_manifest_file(
+    folder_path: &Path,
+) -> Result<Vec<ManifestEntry>, TriplestoreError> {
+    let manifest_path = folder_path.join(MANIFEST_FILENAME);
+    if !manifest_path.exists() {
+        return Err(TriplestoreError::ManifestDoesNotExist(
+            manifest_path.to_str().unwrap().to_string(),
+        ));
+    }
+    let manifest = read_parquet(&manifest_path.to_str().unwrap().to_string())
+        .map_err(|x| TriplestoreError::ParquetIOError(x))?
+        .collect()
+        .map_err(|x| TriplestoreError::InvalidManifest(x.to_string()))?;
+    let utf8_column = |name: &str| {
+        manifest
+            .column(name)
+            .map_err(|x| TriplestoreError::InvalidManifest(x.to_string()))?
+            .utf8()
+            .map_err(|x| TriplestoreError::InvalidManifest(x.to_string()))
+    };
+    let graphs = utf8_column(GRAPH_COLUMN)?;
+    let predicates = utf8_column(PREDICATE_COLUMN)?;
+    let object_types = utf8_column(OBJECT_TYPE_COLUMN)?;
+    let files = utf8_column(FILE_COLUMN)?;
+    let call_uuids = utf8_column(CALL_UUID_COLUMN)?;
+    let bool_column = |name: &str| {
+        manifest
+            .column(name)
+            .map_err(|x| TriplestoreError::InvalidManifest(x.to_string()))?
+            .bool()
+            .map_err(|x| TriplestoreError::InvalidManifest(x.to_string()))
+    };
+    let uniques = bool_column(UNIQUE_COLUMN)?;
+    //Manifests written before sources were recorded have no source column
+    let sources = if manifest.get_column_names().contains(&SOURCE_COLUMN) {
+        Some(utf8_column(SOURCE_COLUMN)?)
+    } else {
+        None
+    };
+    //Neither do manifests written before shadowed triples were kept
+    let shadoweds = if manifest.get_column_names().contains(&SHADOWED_COLUMN) {
+        Some(bool_column(SHADOWED_COLUMN)?)
+    } else {
+        None
+    };
+
+    let mut entries = vec![];
+    for i in 0..manifest.height() {
+        let (predicate, object_type, file, call_uuid, unique) = match (
+            predicates.get(i),
+            object_types.get(i),
+            files.get(i),
+            call_uuids.get(i),
+            uniques.get(i),
+        ) {
+            (Some(p), Some(o), Some(f), Some(c), Some(u)) => (p, o, f, c, u),
+            _ => {
+                return Err(TriplestoreError::InvalidManifest(format!(
+                    "row {} has missing values",
+                    i
+                )))
+            }
+        };
+        let path = folder_path.join(file);
+        if !path.exists() {
+            return Err(TriplestoreError::PathDoesNotExist(
+                path.to_str().unwrap().to_string(),
+            ));
+        }
+        entries.push(ManifestEntry {
+            graph: graphs.get(i).map(|x| x.to_string()),
+            predicate: predicate.to_string(),
+            object_type: object_type_from_string(object_type)?,
+            file: file.to_string(),
+            call_uuid: call_uuid.to_string(),
+            unique,
+            source: sources.and_then(|x| x.get(i)).map(|x| x.to_string()),
+            shadowed: shadoweds.and_then(|x| x.get(i)).unwrap_or(false),
+        });
+    }
+    Ok(entries)
+}
+
+fn manifest_entries_to_graph_df_maps(
+    entries: Vec<ManifestEntry>,
+    folder_path: &Path,
+) -> GraphDfMaps {
+    let mut graph_df_maps: GraphDfMaps = HashMap::new();
+    for e in entries {
+        let path = folder_path.join(&e.file).to_str().unwrap().to_string();
+        let v = graph_df_maps
+            .entry(e.graph)
+            .or_default()
+            .entry(e.predicate)
+            .or_default()
+            .entry(e.object_type)
+            .or_insert_with(|| TripleTable {
+                dfs: None,
+                df_paths: Some(vec![]),
+                df_call_uuids: vec![],
+                shadowed_dfs: None,
+                shadowed_df_paths: Some(vec![]),
+                shadowed_call_uuids: vec![],
+                unique: e.unique,
+                call_uuid: e.call_uuid.clone(),
+                tmp_df: None,
+            });
+        if e.shadowed {
+            v.shadowed_df_paths.as_mut().unwrap().push(path);
+            v.shadowed_call_uuids.push(e.call_uuid);
+        } else {
+            v.df_paths.as_mut().unwrap().push(path);
+            v.df_call_uuids.push(e.call_uuid);
+        }
+    }
+    graph_df_maps
+}
+
+fn delete_parquets_not_in_manifest(
+    caching_folder: &Path,
+    manifest_files: &HashSet<String>,
+) -> Result<(), TriplestoreError> {
+    let contents =
+        read_dir(caching_folder).map_err(|x| TriplestoreError::ReadCachingDirectoryError(x))?;
+    for f in contents {
+        let entry = f.map_err(|x| TriplestoreError::ReadCachingDirectoryEntryError(x))?;
+        let fname = entry.file_name().to_str().unwrap().to_string();
+        let path = entry.path().to_str().unwrap().to_string();
+        if fname.starts_with("tmp_")
+            && fname.ends_with(".parquet")
+            && !manifest_files.contains(&path)
+        {
+            remove_file(entry.path()).map_err(|x| TriplestoreError::RemoveParquetFileError(x))?;
+        }
+    }
+    Ok(())
+}
+
+fn object_type_to_string(object_type: &RDFNodeType) -> String {
+    match object_type {
+        RDFNodeType::IRI => IRI_OBJECT_TYPE.to_string(),
+        RDFNodeType::BlankNode => BLANK_NODE_OBJECT_TYPE.to_string(),
+        RDFNodeType::Literal(l) => l.as_str().to_string(),
+        RDFNodeType::None => {
+            panic!("Triples should never have object type None")
+        }
+    }
+}
+
+fn object_type_from_string(s: &str) -> Result<RDFNodeType, TriplestoreError> {
+    match s {
+        IRI_OBJECT_TYPE => Ok(RDFNodeType::IRI),
+        BLANK_NODE_OBJECT_TYPE => Ok(RDFNodeType::BlankNode),
+        s => Ok(RDFNodeType::Literal(NamedNode::new(s).map_err(|_| {
+            TriplestoreError::InvalidManifest(format!("unknown object type {}", s))
+        })?)),
+    }
+}
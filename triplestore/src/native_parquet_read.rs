@@ -0,0 +1,64 @@
+use super::{unique_triples, TripleDF, Triplestore};
+use crate::errors::TriplestoreError;
+use crate::manifest::read_manifest_file;
+use log::debug;
+use parquet_io::{read_parquet, ParquetIOError};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::path::Path;
+use std::time::Instant;
+use uuid::Uuid;
+
+impl Triplestore {
+    //Reads triples written with write_native_parquet into the triplestore.
+    //The manifest in the folder tells the predicate, object type and graph of each file.
+    pub fn read_native_parquet(&mut self, path: &Path) -> Result<(), TriplestoreError> {
+        let now = Instant::now();
+        if !path.exists() {
+            return Err(TriplestoreError::PathDoesNotExist(
+                path.to_str().unwrap().to_string(),
+            ));
+        }
+        let mut entries = read_manifest_file(path)?;
+        //Shadowed triples are duplicates of the triples of other entries
+        entries.retain(|e| !e.shadowed);
+        let triple_df_results: Vec<Result<TripleDF, TriplestoreError>> = entries
+            .into_par_iter()
+            .map(|e| {
+                let file_path = path.join(&e.file).to_str().unwrap().to_string();
+                let mut df = read_parquet(&file_path)
+                    .map_err(|x| TriplestoreError::ParquetIOError(x))?
+                    .collect()
+                    .map_err(|x| {
+                        TriplestoreError::ParquetIOError(ParquetIOError::ReadParquetError(x))
+                    })?;
+                let column_names = df.get_column_names();
+                if !column_names.contains(&"subject") || !column_names.contains(&"object") {
+                    return Err(TriplestoreError::InvalidManifest(format!(
+                        "file {} should have a subject and an object column",
+                        e.file
+                    )));
+                }
+                if !e.unique {
+                    df = unique_triples(df);
+                }
+                Ok(TripleDF {
+                    df,
+                    predicate: e.predicate,
+                    object_type: e.object_type,
+                    graph: e.graph,
+                })
+            })
+            .collect();
+        let mut triples_df = vec![];
+        for r in triple_df_results {
+            triples_df.push(r?);
+        }
+        //The triples read are added as a single call
+        self.add_triples_df(triples_df, &Uuid::new_v4().to_string())?;
+        debug!(
+            "Reading native parquet took {} seconds",
+            now.elapsed().as_secs_f64()
+        );
+        Ok(())
+    }
+}
@@ -1,15 +1,68 @@
 use super::Triplestore;
 use crate::errors::TriplestoreError;
+use crate::io_funcs::create_folder_if_not_exists;
+use crate::manifest::{write_manifest_file, ManifestEntry};
+use crate::term_dictionary::TermDictionary;
 use log::debug;
-use parquet_io::{property_to_filename, write_parquet, ParquetIOError};
+use parquet_io::{property_to_filename, read_parquet, write_parquet_with_options, ParquetIOError};
+use polars::prelude::ParquetCompression;
+use polars_core::frame::DataFrame;
 use rayon::iter::ParallelDrainRange;
 use rayon::iter::ParallelIterator;
 use representation::RDFNodeType;
+use std::collections::HashSet;
 use std::path::Path;
 use std::time::Instant;
 
+const PREDICATE_PARTITION_KEY: &str = "predicate";
+
+#[derive(Clone, Debug)]
+pub struct NativeParquetWriteOptions {
+    pub compression: ParquetCompression,
+    //Rows per row group, None uses the default of the parquet writer
+    pub row_group_size: Option<usize>,
+    //Write the files of each predicate to a hive-style predicate=<percent-encoded IRI> folder
+    pub partition_by_predicate: bool,
+}
+
+impl Default for NativeParquetWriteOptions {
+    fn default() -> Self {
+        NativeParquetWriteOptions {
+            compression: ParquetCompression::default(),
+            row_group_size: Some(1_000),
+            partition_by_predicate: false,
+        }
+    }
+}
+
+impl NativeParquetWriteOptions {
+    //Parses one of uncompressed, snappy, gzip, lzo, brotli, zstd and lz4 with the default level
+    pub fn parse_compression(compression: &str) -> Result<ParquetCompression, TriplestoreError> {
+        match compression {
+            "uncompressed" => Ok(ParquetCompression::Uncompressed),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip(None)),
+            "lzo" => Ok(ParquetCompression::Lzo),
+            "brotli" => Ok(ParquetCompression::Brotli(None)),
+            "zstd" => Ok(ParquetCompression::Zstd(None)),
+            "lz4" => Ok(ParquetCompression::Lz4Raw),
+            c => Err(TriplestoreError::InvalidParquetCompression(c.to_string())),
+        }
+    }
+}
+
+//The parts of a triple table, in memory or in the caching folder
+enum PartToWrite<'a> {
+    InMemory(&'a DataFrame),
+    Cached(&'a String),
+}
+
 impl Triplestore {
-    pub fn write_native_parquet(&mut self, path: &Path) -> Result<(), TriplestoreError> {
+    pub fn write_native_parquet(
+        &mut self,
+        path: &Path,
+        options: &NativeParquetWriteOptions,
+    ) -> Result<(), TriplestoreError> {
         let now = Instant::now();
         if !path.exists() {
             return Err(TriplestoreError::PathDoesNotExist(
@@ -20,40 +73,100 @@ impl Triplestore {
 
         self.deduplicate()?;
 
-        let mut dfs_to_write = vec![];
+        let mut parts_to_write = vec![];
+        let mut manifest_entries = vec![];
+        //Different predicates may have the same file name, the manifest tells them apart
+        let mut used_filenames = HashSet::new();
 
-        for (property, tts) in &mut self.df_map {
-            for (rdf_node_type, tt) in tts {
-                let filename;
-                if let RDFNodeType::Literal(literal_type) = rdf_node_type {
-                    filename = format!(
-                        "{}_{}",
-                        property_to_filename(property),
-                        property_to_filename(literal_type.as_str())
-                    );
-                } else {
-                    filename = format!("{}_object_property", property_to_filename(property),)
-                }
-                let file_path = path_buf.clone();
-                if let Some(_) = &self.caching_folder {
-                } else {
-                    for (i, df) in tt.dfs.as_mut().unwrap().iter_mut().enumerate() {
+        for (graph, df_map) in &mut self.graph_df_maps {
+            for (property, tts) in df_map {
+                for (rdf_node_type, tt) in tts {
+                    let mut filename;
+                    if let RDFNodeType::Literal(literal_type) = rdf_node_type {
+                        filename = format!(
+                            "{}_{}",
+                            property_to_filename(property),
+                            property_to_filename(literal_type.as_str())
+                        );
+                    } else {
+                        filename = format!("{}_object_property", property_to_filename(property),)
+                    }
+                    //Triples in named graphs are written to files prefixed by the graph
+                    if let Some(graph) = graph {
+                        filename = format!("{}_{}", property_to_filename(graph), filename);
+                    }
+                    let mut n = 1;
+                    let mut unique_filename = filename.clone();
+                    while !used_filenames.insert(unique_filename.clone()) {
+                        n += 1;
+                        unique_filename = format!("{filename}_{n}");
+                    }
+                    let filename = unique_filename;
+                    let mut folder_path = path_buf.clone();
+                    let mut folder_name = None;
+                    if options.partition_by_predicate {
+                        let name =
+                            format!("{}={}", PREDICATE_PARTITION_KEY, percent_encode(property));
+                        folder_path.push(&name);
+                        create_folder_if_not_exists(folder_path.as_path())?;
+                        folder_name = Some(name);
+                    }
+                    let parts: Vec<PartToWrite> = if let Some(_) = &self.caching_folder {
+                        tt.df_paths
+                            .as_ref()
+                            .unwrap()
+                            .iter()
+                            .map(|x| PartToWrite::Cached(x))
+                            .collect()
+                    } else {
+                        tt.dfs
+                            .as_ref()
+                            .unwrap()
+                            .iter()
+                            .map(|x| PartToWrite::InMemory(x))
+                            .collect()
+                    };
+                    for (i, (part, call_uuid)) in
+                        parts.into_iter().zip(&tt.df_call_uuids).enumerate()
+                    {
                         let filename = format!("{filename}_part_{i}.parquet");
-                        let mut file_path = file_path.clone();
-                        file_path.push(filename);
-                        dfs_to_write.push((df, file_path));
+                        let mut file_path = folder_path.clone();
+                        file_path.push(&filename);
+                        //Files are stored relative to the folder of the manifest
+                        let file = if let Some(folder_name) = &folder_name {
+                            format!("{}/{}", folder_name, filename)
+                        } else {
+                            filename
+                        };
+                        manifest_entries.push(ManifestEntry {
+                            graph: graph.clone(),
+                            predicate: property.clone(),
+                            object_type: rdf_node_type.clone(),
+                            file,
+                            call_uuid: call_uuid.clone(),
+                            unique: tt.unique,
+                            source: self.call_sources.get(call_uuid).cloned(),
+                            shadowed: false,
+                        });
+                        parts_to_write.push((part, rdf_node_type, file_path));
                     }
                 }
             }
         }
 
-        let results: Vec<Result<(), ParquetIOError>> = dfs_to_write
+        //Parts are written in parallel. Each thread reads and writes one cached part at a time, so
+        //that at most one cached part per thread is loaded into memory, not the whole triplestore
+        let term_dictionary = &self.term_dictionary;
+        let results: Vec<Result<(), ParquetIOError>> = parts_to_write
             .par_drain(..)
-            .map(|(df, file_path)| write_parquet(df, file_path.as_path()))
+            .map(|(part, object_type, file_path)| {
+                write_part(part, object_type, term_dictionary, &file_path, options)
+            })
             .collect();
         for r in results {
             r.map_err(|x| TriplestoreError::ParquetIOError(x))?;
         }
+        write_manifest_file(path, manifest_entries)?;
 
         debug!(
             "Writing native parquet took {} seconds",
@@ -62,3 +175,49 @@ impl Triplestore {
         Ok(())
     }
 }
+
+//The ids of IRIs and blank nodes are decoded, so that the export can be read into other triplestores
+fn write_part(
+    part: PartToWrite,
+    object_type: &RDFNodeType,
+    term_dictionary: &TermDictionary,
+    file_path: &Path,
+    options: &NativeParquetWriteOptions,
+) -> Result<(), ParquetIOError> {
+    match part {
+        PartToWrite::InMemory(df) => {
+            let mut df = term_dictionary.decode_triples(df, object_type);
+            write_parquet_with_options(
+                &mut df,
+                file_path,
+                options.compression,
+                options.row_group_size,
+            )
+        }
+        PartToWrite::Cached(path) => {
+            let df = read_parquet(path)?
+                .collect()
+                .map_err(|x| ParquetIOError::ReadParquetError(x))?;
+            let mut df = term_dictionary.decode_triples(&df, object_type);
+            write_parquet_with_options(
+                &mut df,
+                file_path,
+                options.compression,
+                options.row_group_size,
+            )
+        }
+    }
+}
+
+//Hive partition values are percent-encoded, so that the predicate IRI can be recovered from them
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
@@ -44,6 +44,7 @@ impl<'a> std::fmt::Write for StringWrap<'a> {
 }
 
 impl Triplestore {
+    //Only the triples of the default graph are written
     pub fn write_n_triples_all_dfs<W: Write + ?Sized>(
         &mut self,
         writer: &mut W,
@@ -54,7 +55,7 @@ impl Triplestore {
         let mut any_value_iter_pool = LowContentionPool::<Vec<_>>::new(n_threads);
         let mut write_buffer_pool = LowContentionPool::<Vec<_>>::new(n_threads);
 
-        for (property, map) in &mut self.df_map {
+        for (property, map) in self.graph_df_maps.get_mut(&None).into_iter().flatten() {
             for (rdf_node_type, tt) in map {
                 let dt = if let RDFNodeType::Literal(dt) = rdf_node_type {
                     Some(dt.clone())
@@ -62,11 +63,12 @@ impl Triplestore {
                     None
                 };
                 let triple_type = rdf_node_type.find_triple_type();
-                if let Some(dfs) = &mut tt.dfs {
+                if let Some(dfs) = &tt.dfs {
                     for df in dfs {
+                        let mut df = self.term_dictionary.decode_triples(df, rdf_node_type);
                         df.as_single_chunk_par();
                         write_ntriples_for_df(
-                            df,
+                            &df,
                             property,
                             &dt,
                             writer,
@@ -83,6 +85,7 @@ impl Triplestore {
                             .map_err(|x| TriplestoreError::ParquetIOError(x))?
                             .collect()
                             .unwrap();
+                        let df = self.term_dictionary.decode_triples(&df, rdf_node_type);
                         write_ntriples_for_df(
                             &df,
                             property,
@@ -230,7 +233,7 @@ fn write_string_property_triple(f: &mut Vec<u8>, mut any_values: Vec<AnyValue>,
     } else {
         panic!()
     };
-    write!(f, "<{}>", s).unwrap();
+    write_node(f, s);
     write!(f, " <{}>", v).unwrap();
     write!(f, " \"{}\"", lex).unwrap();
     if let Some(lang) = lang_opt {
@@ -257,7 +260,7 @@ fn write_non_string_property_triple(
     } else {
         panic!()
     };
-    write!(f, "<{}>", s).unwrap();
+    write_node(f, s);
     write!(f, " <{}>", v).unwrap();
     write!(f, " \"{}\"", lex).unwrap();
     writeln!(f, "^^<{}> .", dt).unwrap();
@@ -274,7 +277,17 @@ fn write_object_property_triple(f: &mut Vec<u8>, mut any_values: Vec<AnyValue>,
     } else {
         panic!()
     };
-    write!(f, "<{}>", s).unwrap();
+    write_node(f, s);
     write!(f, " <{}>", v).unwrap();
-    writeln!(f, " <{}> .", o).unwrap();
+    write!(f, " ").unwrap();
+    write_node(f, o);
+    writeln!(f, " .").unwrap();
+}
+
+fn write_node(f: &mut Vec<u8>, n: &str) {
+    if n.starts_with("_:") {
+        write!(f, "{}", n).unwrap();
+    } else {
+        write!(f, "<{}>", n).unwrap();
+    }
 }
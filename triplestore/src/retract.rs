@@ -0,0 +1,227 @@
+use super::Triplestore;
+use crate::errors::TriplestoreError;
+use parquet_io::{read_parquet, write_parquet};
+use polars::prelude::{col, lit, IntoLazy};
+use polars_core::datatypes::DataType;
+use polars_core::frame::DataFrame;
+use polars_core::series::Series;
+use representation::RDFNodeType;
+use std::collections::HashSet;
+use std::fs::remove_file;
+use std::path::Path;
+
+impl Triplestore {
+    //Removes the triples added by the given calls with the given subjects in the graph. The
+    //triples of the calls with the blank nodes among the objects of the removed triples as
+    //subjects are removed as well, and so on. Triples that other calls also added are kept.
+    //Returns the number of triples removed.
+    pub fn retract_subjects(
+        &mut self,
+        subjects: &Series,
+        call_uuids: &HashSet<String>,
+        graph: &Option<String>,
+    ) -> Result<usize, TriplestoreError> {
+        let mut n_retracted = 0;
+        //Subjects that are not in the term dictionary are not in any triple table
+        let mut subjects = self.term_dictionary.lookup_series(subjects).drop_nulls();
+        let caching_folder = self.caching_folder.clone();
+        let df_map = if let Some(df_map) = self.graph_df_maps.get_mut(graph) {
+            df_map
+        } else {
+            return Ok(0);
+        };
+        while !subjects.is_empty() {
+            let mut blank_node_objects = Series::new_empty("subject", &DataType::UInt32);
+            for (predicate, map) in df_map.iter_mut() {
+                for (object_type, v) in map.iter_mut() {
+                    if !v
+                        .df_call_uuids
+                        .iter()
+                        .chain(&v.shadowed_call_uuids)
+                        .any(|x| call_uuids.contains(x))
+                    {
+                        continue;
+                    }
+                    //The removed triples are counted once
+                    if !v.unique {
+                        v.deduplicate(&caching_folder, predicate)?;
+                    }
+                    let removed = split_call_parts(
+                        &mut v.dfs,
+                        &v.df_paths,
+                        &v.df_call_uuids,
+                        call_uuids,
+                        &subjects,
+                    )?;
+                    split_call_parts(
+                        &mut v.shadowed_dfs,
+                        &v.shadowed_df_paths,
+                        &v.shadowed_call_uuids,
+                        call_uuids,
+                        &subjects,
+                    )?;
+                    let n_removed: usize = removed.iter().map(|x| x.height()).sum();
+                    n_retracted += n_removed;
+                    //Triples that other calls also added are restored
+                    if n_removed > 0 && !v.shadowed_call_uuids.is_empty() {
+                        n_retracted -= v.deduplicate(&caching_folder, predicate)?;
+                    }
+                    if object_type == &RDFNodeType::BlankNode {
+                        for df in removed {
+                            blank_node_objects
+                                .append(df.column("object").unwrap())
+                                .unwrap();
+                        }
+                    }
+                }
+            }
+            subjects = blank_node_objects.unique().unwrap();
+        }
+        self.write_manifest()?;
+        Ok(n_retracted)
+    }
+
+    //Removes the triples added by the given call. Triples that other calls also added are kept.
+    //Returns the number of triples removed.
+    pub fn retract_call(&mut self, call_uuid: &String) -> Result<usize, TriplestoreError> {
+        let mut n_retracted = 0;
+        for (predicate, map) in self.graph_df_maps.values_mut().flatten() {
+            for v in map.values_mut() {
+                if !v.df_call_uuids.contains(call_uuid)
+                    && !v.shadowed_call_uuids.contains(call_uuid)
+                {
+                    continue;
+                }
+                //The removed triples are counted once
+                if !v.unique {
+                    v.deduplicate(&self.caching_folder, predicate)?;
+                }
+                let n_removed = remove_call_parts(
+                    &mut v.dfs,
+                    &mut v.df_paths,
+                    &mut v.df_call_uuids,
+                    call_uuid,
+                )?;
+                remove_call_parts(
+                    &mut v.shadowed_dfs,
+                    &mut v.shadowed_df_paths,
+                    &mut v.shadowed_call_uuids,
+                    call_uuid,
+                )?;
+                n_retracted += n_removed;
+                //Triples that other calls also added are restored
+                if n_removed > 0 && !v.shadowed_call_uuids.is_empty() {
+                    n_retracted -= v.deduplicate(&self.caching_folder, predicate)?;
+                }
+            }
+            map.retain(|_, v| !v.df_call_uuids.is_empty());
+        }
+        for df_map in self.graph_df_maps.values_mut() {
+            df_map.retain(|_, map| !map.is_empty());
+        }
+        self.graph_df_maps.retain(|_, df_map| !df_map.is_empty());
+        self.call_sources.remove(call_uuid);
+        self.write_manifest()?;
+        Ok(n_retracted)
+    }
+}
+
+//Removes the dfs or df_paths of the call, returns the number of triples removed
+fn remove_call_parts(
+    dfs: &mut Option<Vec<DataFrame>>,
+    paths: &mut Option<Vec<String>>,
+    call_uuids: &mut Vec<String>,
+    call_uuid: &String,
+) -> Result<usize, TriplestoreError> {
+    let mut n_removed = 0;
+    let keep: Vec<bool> = call_uuids.iter().map(|x| x != call_uuid).collect();
+    if let Some(dfs) = dfs {
+        for (df, keep_df) in dfs.iter().zip(&keep) {
+            if !keep_df {
+                n_removed += df.height();
+            }
+        }
+        *dfs = dfs
+            .drain(..)
+            .zip(&keep)
+            .filter(|(_, keep_df)| **keep_df)
+            .map(|(df, _)| df)
+            .collect();
+    } else if let Some(paths) = paths {
+        for (path, keep_path) in paths.iter().zip(&keep) {
+            if !keep_path {
+                n_removed += read_parquet(path)
+                    .map_err(|x| TriplestoreError::ParquetIOError(x))?
+                    .collect()
+                    .unwrap()
+                    .height();
+                remove_file(Path::new(path))
+                    .map_err(|x| TriplestoreError::RemoveParquetFileError(x))?;
+            }
+        }
+        *paths = paths
+            .drain(..)
+            .zip(&keep)
+            .filter(|(_, keep_path)| **keep_path)
+            .map(|(path, _)| path)
+            .collect();
+    }
+    call_uuids.retain(|x| x != call_uuid);
+    Ok(n_removed)
+}
+
+//Removes the triples with the given subjects from the dfs or df_paths of the calls, returns the
+//removed triples
+fn split_call_parts(
+    dfs: &mut Option<Vec<DataFrame>>,
+    paths: &Option<Vec<String>>,
+    part_call_uuids: &[String],
+    call_uuids: &HashSet<String>,
+    subjects: &Series,
+) -> Result<Vec<DataFrame>, TriplestoreError> {
+    let mut removed_dfs = vec![];
+    for (i, part_call_uuid) in part_call_uuids.iter().enumerate() {
+        if !call_uuids.contains(part_call_uuid) {
+            continue;
+        }
+        let removed = if let Some(dfs) = dfs {
+            let df = dfs.get_mut(i).unwrap();
+            let (retained, removed) = split_by_subjects(df.clone(), subjects);
+            *df = retained;
+            removed
+        } else {
+            let path = paths.as_ref().unwrap().get(i).unwrap();
+            let df = read_parquet(path)
+                .map_err(|x| TriplestoreError::ParquetIOError(x))?
+                .collect()
+                .unwrap();
+            let (mut retained, removed) = split_by_subjects(df, subjects);
+            if removed.height() > 0 {
+                write_parquet(&mut retained, Path::new(path))
+                    .map_err(|x| TriplestoreError::ParquetIOError(x))?;
+            }
+            removed
+        };
+        removed_dfs.push(removed);
+    }
+    Ok(removed_dfs)
+}
+
+//Splits the triples into those with other subjects and those with the given subjects
+fn split_by_subjects(df: DataFrame, subjects: &Series) -> (DataFrame, DataFrame) {
+    let is_subject = df
+        .clone()
+        .lazy()
+        .select([col("subject").is_in(lit(subjects.clone()))])
+        .collect()
+        .unwrap()
+        .column("subject")
+        .unwrap()
+        .bool()
+        .unwrap()
+        .clone();
+    (
+        df.filter(&!&is_subject).unwrap(),
+        df.filter(&is_subject).unwrap(),
+    )
+}
@@ -1,3 +1,4 @@
+mod encoded_terms;
 pub mod errors;
 pub(crate) mod lazy_aggregate;
 mod lazy_expressions;
@@ -7,7 +8,7 @@ mod query_context;
 pub mod solution_mapping;
 mod sparql_to_polars;
 
-use crate::sparql::query_context::Context;
+use crate::sparql::query_context::{Context, Dataset};
 use oxrdf::{NamedNode, Variable};
 use std::collections::HashMap;
 
@@ -21,6 +22,7 @@ use polars_core::enable_string_cache;
 use polars_core::prelude::{DataType, Series, UniqueKeepStrategy};
 use representation::literals::sparql_literal_to_any_value;
 use representation::RDFNodeType;
+use spargebra::algebra::{GraphPattern, QueryDataset};
 use spargebra::term::{NamedNodePattern, TermPattern, TriplePattern};
 use spargebra::Query;
 use uuid::Uuid;
@@ -36,41 +38,81 @@ impl Triplestore {
         self.query_parsed(&query)
     }
 
-    fn query_parsed(&mut self, query: &Query) -> Result<QueryResult, SparqlError> {
+    //Runs a SELECT query, the solutions are returned with the RDF node types of their columns
+    pub fn query_select(
+        &mut self,
+        query: &str,
+    ) -> Result<(DataFrame, HashMap<String, RDFNodeType>), SparqlError> {
+        let query = Query::parse(query, None).map_err(|x| SparqlError::ParseError(x))?;
+        if let Query::Select {
+            dataset,
+            pattern,
+            base_iri: _,
+        } = &query
+        {
+            self.prepare_query()?;
+            self.select(dataset, pattern)
+        } else {
+            Err(SparqlError::QueryTypeNotSupported)
+        }
+    }
+
+    fn prepare_query(&mut self) -> Result<(), SparqlError> {
         if !self.deduplicated {
             self.deduplicate()
                 .map_err(|x| SparqlError::DeduplicationError(x))?;
         }
         enable_string_cache(true);
-        let context = Context::new();
+        self.term_dictionary.rechunk();
+        Ok(())
+    }
+
+    fn select(
+        &self,
+        dataset: &Option<QueryDataset>,
+        pattern: &GraphPattern,
+    ) -> Result<(DataFrame, HashMap<String, RDFNodeType>), SparqlError> {
+        let context = Context::with_dataset(create_dataset(dataset));
+        let SolutionMappings {
+            mappings,
+            columns: _,
+            rdf_node_types,
+        } = self.lazy_graph_pattern(pattern, None, &context)?;
+        let mut df = self
+            .decode_columns(mappings, &rdf_node_types)
+            .collect()
+            .unwrap();
+        df = cats_to_utf8s(df);
+        Ok((df, rdf_node_types))
+    }
+
+    fn query_parsed(&mut self, query: &Query) -> Result<QueryResult, SparqlError> {
+        self.prepare_query()?;
         match query {
             Query::Select {
-                dataset: _,
+                dataset,
                 pattern,
                 base_iri: _,
             } => {
-                let SolutionMappings {
-                    mappings,
-                    columns: _,
-                    rdf_node_types: _,
-                } = self.lazy_graph_pattern(&pattern, None, &context)?;
-                let mut df = mappings.collect().unwrap();
-                df = cats_to_utf8s(df);
-
+                let (df, _) = self.select(dataset, pattern)?;
                 Ok(QueryResult::Select(df))
             }
             Query::Construct {
                 template,
-                dataset: _,
+                dataset,
                 pattern,
                 base_iri: _,
             } => {
+                let context = Context::with_dataset(create_dataset(dataset));
                 let SolutionMappings {
                     mappings,
                     columns: _,
                     rdf_node_types,
                 } = self.lazy_graph_pattern(&pattern, None, &context)?;
-                let mut df = mappings.collect().unwrap();
+                let mut df = self
+                    .decode_columns(mappings, &rdf_node_types)
+                    .collect()
+                    .unwrap();
                 df = cats_to_utf8s(df);
                 let mut dfs = vec![];
                 for t in template {
@@ -98,11 +140,14 @@ impl Triplestore {
                             df,
                             object_type: dt,
                             language_tag: None,
+                            language_tag_column: None,
                             static_verb_column: None,
                             has_unique_subset: false,
+                            graph: None,
                         });
                     }
-                    self.add_triples_vec(all_triples_to_add, &call_uuid)
+                    let _report = self
+                        .add_triples_vec(all_triples_to_add, &call_uuid)
                         .map_err(|x| SparqlError::StoreTriplesError(x))?;
                     Ok(())
                 }
@@ -113,6 +158,27 @@ impl Triplestore {
     }
 }
 
+//Without FROM or FROM NAMED, the stored default graph and all named graphs are queried
+fn create_dataset(dataset: &Option<QueryDataset>) -> Dataset {
+    if let Some(QueryDataset { default, named }) = dataset {
+        Dataset {
+            default: default
+                .iter()
+                .map(|x| Some(x.as_str().to_string()))
+                .collect(),
+            named: Some(
+                named
+                    .iter()
+                    .flatten()
+                    .map(|x| x.as_str().to_string())
+                    .collect(),
+            ),
+        }
+    } else {
+        Dataset::default()
+    }
+}
+
 fn triple_to_df(
     df: &DataFrame,
     rdf_node_types: &HashMap<String, RDFNodeType>,
@@ -123,9 +189,9 @@ fn triple_to_df(
     } else {
         1
     };
-    let (subj_ser, _) = term_pattern_series(df, rdf_node_types, &t.subject, "subject", len);
+    let (subj_ser, _) = term_pattern_series(df, rdf_node_types, &t.subject, "subject", len)?;
     let (verb_ser, _) = named_node_pattern_series(df, rdf_node_types, &t.predicate, "verb", len);
-    let (obj_ser, dt) = term_pattern_series(df, rdf_node_types, &t.object, "object", len);
+    let (obj_ser, dt) = term_pattern_series(df, rdf_node_types, &t.object, "object", len)?;
     let mut unique_subset = vec![];
     if subj_ser.dtype() != &DataType::Null {
         unique_subset.push("subject".to_string());
@@ -159,8 +225,8 @@ fn term_pattern_series(
     tp: &TermPattern,
     name: &str,
     len: usize,
-) -> (Series, RDFNodeType) {
-    match tp {
+) -> Result<(Series, RDFNodeType), SparqlError> {
+    Ok(match tp {
         TermPattern::NamedNode(nn) => named_node_series(nn, name, len),
         TermPattern::BlankNode(_) => {
             unimplemented!("Blank node term pattern not supported")
@@ -169,7 +235,8 @@ fn term_pattern_series(
             let (anyvalue, dt) = sparql_literal_to_any_value(
                 &lit.value().to_string(),
                 &Some(lit.datatype().into_owned()),
-            );
+            )
+            .map_err(|x| SparqlError::InvalidLiteral(x))?;
             let mut any_values = vec![];
             for _ in 0..len {
                 any_values.push(anyvalue.clone())
@@ -180,7 +247,7 @@ fn term_pattern_series(
             )
         }
         TermPattern::Variable(v) => variable_series(df, rdf_node_types, v, name),
-    }
+    })
 }
 
 fn named_node_pattern_series(
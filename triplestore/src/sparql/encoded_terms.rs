@@ -0,0 +1,167 @@
+use super::Triplestore;
+use crate::sparql::errors::SparqlError;
+use crate::sparql::solution_mapping::is_string_col;
+use crate::sparql::sparql_to_polars::{
+    sparql_decimal_equals, sparql_literal_to_polars_literal_value,
+    sparql_named_node_to_polars_literal_value,
+};
+use oxrdf::vocab::xsd;
+use polars::prelude::{col, lit, Expr, LazyFrame};
+use polars_core::datatypes::DataType;
+use polars_core::prelude::SchemaRef;
+use representation::RDFNodeType;
+use spargebra::term::TermPattern;
+use std::collections::{HashMap, HashSet};
+
+//IRIs and blank nodes read from the triple tables are ids in the term dictionary. They stay ids
+//through joins, and are decoded where their strings are needed, e.g. in expressions and results.
+pub(crate) fn is_encoded(rdf_node_type: &RDFNodeType, dtype: &DataType) -> bool {
+    matches!(rdf_node_type, RDFNodeType::IRI | RDFNodeType::BlankNode) && dtype == &DataType::UInt32
+}
+
+pub(crate) fn encoded_columns(
+    mappings: &LazyFrame,
+    rdf_node_types: &HashMap<String, RDFNodeType>,
+) -> HashSet<String> {
+    let schema = mappings.schema().unwrap();
+    schema
+        .iter()
+        .filter(|(c, dtype)| {
+            if let Some(rdf_node_type) = rdf_node_types.get(c.as_str()) {
+                is_encoded(rdf_node_type, dtype)
+            } else {
+                false
+            }
+        })
+        .map(|(c, _)| c.to_string())
+        .collect()
+}
+
+impl Triplestore {
+    pub(crate) fn decode_column(&self, c: &str) -> Expr {
+        self.term_dictionary.decode_expr(col(c))
+    }
+
+    pub(crate) fn decode_columns(
+        &self,
+        mut mappings: LazyFrame,
+        rdf_node_types: &HashMap<String, RDFNodeType>,
+    ) -> LazyFrame {
+        for c in encoded_columns(&mappings, rdf_node_types) {
+            mappings = mappings.with_column(self.decode_column(&c));
+        }
+        mappings
+    }
+
+    //IRIs and blank nodes are joined on their ids when they are encoded on both sides.
+    //Otherwise the encoded side is decoded, and strings are joined as categoricals.
+    pub(crate) fn harmonize_join_columns(
+        &self,
+        mut left: LazyFrame,
+        left_rdf_node_types: &HashMap<String, RDFNodeType>,
+        mut right: LazyFrame,
+        right_rdf_node_types: &HashMap<String, RDFNodeType>,
+        join_on: &[&String],
+    ) -> (LazyFrame, LazyFrame) {
+        let left_encoded = encoded_columns(&left, left_rdf_node_types);
+        let right_encoded = encoded_columns(&right, right_rdf_node_types);
+        for c in join_on {
+            let c = c.as_str();
+            if left_encoded.contains(c) && right_encoded.contains(c) {
+                continue;
+            }
+            if left_encoded.contains(c) {
+                left = left.with_column(self.decode_column(c));
+            }
+            if right_encoded.contains(c) {
+                right = right.with_column(self.decode_column(c));
+            }
+            let rdf_node_type = left_rdf_node_types.get(c).unwrap();
+            if rdf_node_type != &RDFNodeType::None && is_string_col(rdf_node_type) {
+                left = left.with_column(col(c).cast(DataType::Categorical(None)));
+                right = right.with_column(col(c).cast(DataType::Categorical(None)));
+            }
+        }
+        (left, right)
+    }
+
+    //Columns of IRIs and blank nodes that are encoded in some of the mappings and not in the
+    //others are decoded, so that the mappings can be concatenated.
+    pub(crate) fn harmonize_concat(
+        &self,
+        lfs: Vec<LazyFrame>,
+        rdf_node_types: &HashMap<String, RDFNodeType>,
+    ) -> Vec<LazyFrame> {
+        let schemas: Vec<SchemaRef> = lfs.iter().map(|lf| lf.schema().unwrap()).collect();
+        let mut to_decode = vec![];
+        for (c, rdf_node_type) in rdf_node_types {
+            if !matches!(rdf_node_type, RDFNodeType::IRI | RDFNodeType::BlankNode) {
+                continue;
+            }
+            let dtypes: Vec<&DataType> = schemas
+                .iter()
+                .filter_map(|s| s.get(c))
+                .filter(|dtype| *dtype != &DataType::Null)
+                .collect();
+            if dtypes.iter().any(|dtype| *dtype == &DataType::UInt32)
+                && dtypes.iter().any(|dtype| *dtype != &DataType::UInt32)
+            {
+                to_decode.push(c);
+            }
+        }
+        lfs.into_iter()
+            .zip(schemas)
+            .map(|(mut lf, schema)| {
+                for c in &to_decode {
+                    match schema.get(c) {
+                        Some(DataType::UInt32) => {
+                            lf = lf.with_column(self.decode_column(c));
+                        }
+                        Some(DataType::Null) | None => {}
+                        Some(_) => {
+                            lf = lf.with_column(col(c).cast(DataType::Utf8));
+                        }
+                    }
+                }
+                lf
+            })
+            .collect()
+    }
+
+    //Constant terms are compared with their ids in encoded columns, terms that are not in the
+    //term dictionary match nothing.
+    pub(crate) fn term_pattern_filter(
+        &self,
+        term_pattern: &TermPattern,
+        target_col: &str,
+        encoded: bool,
+    ) -> Result<Option<Expr>, SparqlError> {
+        Ok(match term_pattern {
+            TermPattern::NamedNode(nn) => {
+                if encoded {
+                    Some(self.encoded_term_filter(target_col, nn.as_str()))
+                } else {
+                    Some(col(target_col).eq(lit(sparql_named_node_to_polars_literal_value(nn))))
+                }
+            }
+            TermPattern::Literal(l) => {
+                if encoded {
+                    Some(lit(false))
+                } else if l.datatype() == xsd::DECIMAL {
+                    Some(sparql_decimal_equals(col(target_col), l)?)
+                } else {
+                    Some(col(target_col).eq(lit(sparql_literal_to_polars_literal_value(l)?)))
+                }
+            }
+            _ => None,
+        })
+    }
+
+    pub(crate) fn encoded_term_filter(&self, target_col: &str, term: &str) -> Expr {
+        if let Some(id) = self.term_dictionary.lookup(term) {
+            col(target_col).eq(lit(id))
+        } else {
+            lit(false)
+        }
+    }
+}
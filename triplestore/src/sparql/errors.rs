@@ -1,4 +1,5 @@
 use crate::errors::TriplestoreError;
+use representation::literals::LiteralParsingError;
 use representation::RDFNodeType;
 use spargebra::ParseError;
 use thiserror::Error;
@@ -19,4 +20,8 @@ pub enum SparqlError {
     TripleTableReadError(TriplestoreError),
     #[error("Error storing triples {}", .0)]
     StoreTriplesError(TriplestoreError),
+    #[error("{}", .0)]
+    InvalidLiteral(LiteralParsingError),
+    #[error("Predicate {} has objects of several types {:?}, which is not supported", .0, .1)]
+    MultipleObjectTypes(String, Vec<RDFNodeType>),
 }
@@ -3,6 +3,7 @@ mod exists_helper;
 use super::Triplestore;
 use std::collections::HashMap;
 
+use crate::sparql::encoded_terms::encoded_columns;
 use crate::sparql::errors::SparqlError;
 use crate::sparql::lazy_expressions::exists_helper::rewrite_exists_graph_pattern;
 use crate::sparql::query_context::{Context, PathEntry};
@@ -39,10 +40,14 @@ impl Triplestore {
                 solution_mappings
             }
             Expression::Literal(lit) => {
-                solution_mappings.mappings = solution_mappings.mappings.with_column(
-                    Expr::Literal(sparql_literal_to_polars_literal_value(lit))
-                        .alias(context.as_str()),
-                );
+                let mut expr = Expr::Literal(sparql_literal_to_polars_literal_value(lit)?);
+                //Polars does not compute with decimals, so they are evaluated as doubles
+                if lit.datatype() == xsd::DECIMAL {
+                    expr = expr.cast(DataType::Float64);
+                }
+                solution_mappings.mappings = solution_mappings
+                    .mappings
+                    .with_column(expr.alias(context.as_str()));
                 solution_mappings.rdf_node_types.insert(
                     context.as_str().to_string(),
                     RDFNodeType::Literal(lit.datatype().into_owned()),
@@ -56,9 +61,24 @@ impl Triplestore {
                         context.as_str().to_string(),
                     ));
                 }
+                //Expressions work on the strings of IRIs and blank nodes
+                let expr = if encoded_columns(
+                    &solution_mappings.mappings,
+                    &solution_mappings.rdf_node_types,
+                )
+                .contains(v.as_str())
+                {
+                    self.decode_column(v.as_str())
+                } else if solution_mappings.rdf_node_types.get(v.as_str())
+                    == Some(&RDFNodeType::Literal(xsd::DECIMAL.into_owned()))
+                {
+                    col(v.as_str()).cast(DataType::Float64)
+                } else {
+                    col(v.as_str())
+                };
                 solution_mappings.mappings = solution_mappings
                     .mappings
-                    .with_column(col(v.as_str()).alias(context.as_str()));
+                    .with_column(expr.alias(context.as_str()));
                 let existing_type = solution_mappings.rdf_node_types.get(v.as_str()).unwrap();
                 solution_mappings
                     .rdf_node_types
@@ -1,6 +1,7 @@
 mod distinct;
 mod extend;
 mod filter;
+mod graph;
 mod group;
 mod join;
 mod left_join;
@@ -61,8 +62,8 @@ impl Triplestore {
             GraphPattern::Union { left, right } => {
                 self.lazy_union(left, right, solution_mappings, context)
             }
-            GraphPattern::Graph { name: _, inner: _ } => {
-                unimplemented!("Graphs not supported")
+            GraphPattern::Graph { name, inner } => {
+                self.lazy_graph(name, inner, solution_mappings, context)
             }
             GraphPattern::Extend {
                 inner,
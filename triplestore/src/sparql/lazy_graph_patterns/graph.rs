@@ -0,0 +1,182 @@
+use super::Triplestore;
+use crate::sparql::encoded_terms::encoded_columns;
+use crate::sparql::errors::SparqlError;
+use crate::sparql::query_context::{Context, Dataset, PathEntry};
+use crate::sparql::solution_mapping::SolutionMappings;
+use log::debug;
+use polars::prelude::{col, diag_concat_lf, lit, Expr, IntoLazy, LazyFrame, NULL};
+use polars_core::prelude::DataType;
+use representation::RDFNodeType;
+use spargebra::algebra::GraphPattern;
+use spargebra::term::NamedNodePattern;
+use std::collections::{HashMap, HashSet};
+
+impl Triplestore {
+    pub(crate) fn lazy_graph(
+        &self,
+        name: &NamedNodePattern,
+        inner: &GraphPattern,
+        solution_mappings: Option<SolutionMappings>,
+        context: &Context,
+    ) -> Result<SolutionMappings, SparqlError> {
+        debug!("Processing graph graph pattern");
+        let inner_context = context.extension_with(PathEntry::GraphInner);
+        let named_graphs = self.named_graphs(&context.dataset);
+        let variable = match name {
+            NamedNodePattern::NamedNode(nn) => {
+                //Graphs outside of the dataset match nothing
+                let active_graphs = if named_graphs.iter().any(|x| x == nn.as_str()) {
+                    vec![Some(nn.as_str().to_string())]
+                } else {
+                    vec![]
+                };
+                return self.lazy_graph_pattern(
+                    inner,
+                    solution_mappings,
+                    &inner_context.with_active_graphs(active_graphs),
+                );
+            }
+            NamedNodePattern::Variable(v) => v.as_str(),
+        };
+
+        //The inner pattern is evaluated against each named graph, and the graph is bound to the variable
+        let mut dfs = vec![];
+        let mut empty_output = None;
+        let mut columns = HashSet::new();
+        let mut rdf_node_types: HashMap<String, RDFNodeType> = HashMap::new();
+        for g in &named_graphs {
+            let graph_solution_mappings = solution_mappings.clone().map(|mut sm| {
+                if sm.columns.contains(variable) {
+                    let filter = self.graph_filter(&sm.mappings, &sm.rdf_node_types, variable, g);
+                    sm.mappings = sm.mappings.filter(filter);
+                }
+                sm
+            });
+            let SolutionMappings {
+                mut mappings,
+                columns: graph_columns,
+                rdf_node_types: graph_rdf_node_types,
+            } = self.lazy_graph_pattern(
+                inner,
+                graph_solution_mappings,
+                &inner_context.with_active_graphs(vec![Some(g.clone())]),
+            )?;
+            if graph_columns.contains(variable) {
+                let filter = self.graph_filter(&mappings, &graph_rdf_node_types, variable, g);
+                mappings = mappings.filter(filter);
+            } else {
+                mappings = mappings.with_column(lit(g.clone()).alias(variable));
+            }
+            let df = mappings.collect().unwrap();
+            //Graphs without matches may have untyped columns, so they are left out
+            if df.height() == 0 {
+                if empty_output.is_none() {
+                    empty_output = Some(SolutionMappings::new(
+                        df.lazy(),
+                        graph_columns,
+                        graph_rdf_node_types,
+                    ));
+                }
+                continue;
+            }
+            columns.extend(graph_columns);
+            for (v, dt) in graph_rdf_node_types {
+                match rdf_node_types.get(&v) {
+                    Some(RDFNodeType::None) | None => {
+                        rdf_node_types.insert(v, dt);
+                    }
+                    Some(existing) => {
+                        if dt != RDFNodeType::None && existing != &dt {
+                            return Err(SparqlError::InconsistentDatatypes(
+                                v,
+                                existing.clone(),
+                                dt,
+                                context.as_str().to_string(),
+                            ));
+                        }
+                    }
+                }
+            }
+            dfs.push(df);
+        }
+
+        if dfs.is_empty() {
+            let mut output = if let Some(output) = empty_output {
+                output
+            } else {
+                let mut output = self.lazy_graph_pattern(
+                    inner,
+                    solution_mappings,
+                    &inner_context.with_active_graphs(vec![]),
+                )?;
+                if !output.columns.contains(variable) {
+                    output.mappings = output
+                        .mappings
+                        .with_column(lit(NULL).cast(DataType::Utf8).alias(variable));
+                }
+                output
+            };
+            output.mappings = output.mappings.filter(lit(false));
+            output.columns.insert(variable.to_string());
+            output
+                .rdf_node_types
+                .insert(variable.to_string(), RDFNodeType::IRI);
+            return Ok(output);
+        }
+        columns.insert(variable.to_string());
+        rdf_node_types.insert(variable.to_string(), RDFNodeType::IRI);
+
+        //Columns without values in some graphs are null typed, and are cast to the type in the others
+        let mut dtypes = HashMap::new();
+        for df in &dfs {
+            for s in df.get_columns() {
+                if s.dtype() != &DataType::Null {
+                    dtypes
+                        .entry(s.name().to_string())
+                        .or_insert(s.dtype().clone());
+                }
+            }
+        }
+        let mut lfs = vec![];
+        for mut df in dfs {
+            for (c, dt) in &dtypes {
+                if let Ok(s) = df.column(c) {
+                    if s.dtype() == &DataType::Null {
+                        let cast = s.cast(dt).unwrap();
+                        df.with_column(cast).unwrap();
+                    }
+                }
+            }
+            lfs.push(df.lazy());
+        }
+        let lfs = self.harmonize_concat(lfs, &rdf_node_types);
+        let mappings = diag_concat_lf(lfs, true, true).expect("Concat problem");
+        Ok(SolutionMappings::new(mappings, columns, rdf_node_types))
+    }
+
+    //The graph variable may also be bound to terms from the triple tables, which are encoded
+    fn graph_filter(
+        &self,
+        mappings: &LazyFrame,
+        rdf_node_types: &HashMap<String, RDFNodeType>,
+        variable: &str,
+        graph: &str,
+    ) -> Expr {
+        if encoded_columns(mappings, rdf_node_types).contains(variable) {
+            self.encoded_term_filter(variable, graph)
+        } else {
+            col(variable).cast(DataType::Utf8).eq(lit(graph))
+        }
+    }
+
+    //The named graphs of the dataset, by default all the stored named graphs
+    fn named_graphs(&self, dataset: &Dataset) -> Vec<String> {
+        if let Some(named) = &dataset.named {
+            named.clone()
+        } else {
+            let mut named: Vec<String> = self.graph_df_maps.keys().flatten().cloned().collect();
+            named.sort();
+            named
+        }
+    }
+}
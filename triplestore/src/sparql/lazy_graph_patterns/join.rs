@@ -1,10 +1,9 @@
 use super::Triplestore;
 use crate::sparql::errors::SparqlError;
 use crate::sparql::query_context::{Context, PathEntry};
-use crate::sparql::solution_mapping::{is_string_col, SolutionMappings};
+use crate::sparql::solution_mapping::SolutionMappings;
 use log::debug;
 use polars::prelude::{col, Expr};
-use polars_core::datatypes::DataType;
 use polars_core::prelude::{JoinArgs, JoinType};
 use spargebra::algebra::GraphPattern;
 
@@ -43,15 +42,15 @@ impl Triplestore {
                 JoinArgs::new(JoinType::Cross),
             )
         } else {
-            for c in join_on {
-                if is_string_col(right_datatypes.get(c).unwrap()) {
-                    right_mappings =
-                        right_mappings.with_column(col(c).cast(DataType::Categorical(None)));
-                    left_solution_mappings.mappings = left_solution_mappings
-                        .mappings
-                        .with_column(col(c).cast(DataType::Categorical(None)));
-                }
-            }
+            let (left_mappings, harmonized_right_mappings) = self.harmonize_join_columns(
+                left_solution_mappings.mappings,
+                &left_solution_mappings.rdf_node_types,
+                right_mappings,
+                &right_datatypes,
+                join_on.as_slice(),
+            );
+            left_solution_mappings.mappings = left_mappings;
+            right_mappings = harmonized_right_mappings;
             let all_false = [false].repeat(join_on_cols.len());
 
             right_mappings = right_mappings.sort_by_exprs(
@@ -1,12 +1,10 @@
 use super::Triplestore;
+use crate::sparql::encoded_terms::is_encoded;
 use crate::sparql::errors::SparqlError;
 use crate::sparql::query_context::Context;
 use crate::sparql::solution_mapping::SolutionMappings;
-use crate::sparql::sparql_to_polars::{
-    sparql_literal_to_polars_literal_value, sparql_named_node_to_polars_literal_value,
-};
 use oxrdf::NamedNode;
-use polars::prelude::{col, concat, DataFrameJoinOps, Expr, IntoLazy, UnionArgs};
+use polars::prelude::{col, DataFrameJoinOps, Expr, IntoLazy};
 use polars_core::datatypes::{AnyValue, DataType};
 use polars_core::frame::{DataFrame, UniqueKeepStrategy};
 use polars_core::prelude::{ChunkAgg, JoinArgs, JoinType};
@@ -62,7 +60,30 @@ impl Triplestore {
         let out_soo;
         let out_dt;
 
-        let cat_df_map = self.create_unique_cat_dfs(ppe, Some(subject), Some(object))?;
+        let graphs = &context.active_graphs;
+        let term_df_map = self.create_unique_term_dfs(graphs, ppe, Some(subject), Some(object))?;
+        //When the subjects and objects are encoded, their ids are numbered from zero in the lookup,
+        //so that the sparse matrices only span the terms of the path.
+        //Otherwise, e.g. with literal objects, the terms are decoded and their categorical ids are used.
+        let use_ids = term_df_map
+            .values()
+            .all(|df| df.column("subject").unwrap().dtype() == &DataType::UInt32);
+        let id_lookup_df;
+        let cat_df_map: HashMap<String, DataFrame>;
+        if use_ids {
+            let lookup_df = find_id_lookup(&term_df_map);
+            cat_df_map = term_df_map
+                .into_iter()
+                .map(|(k, df)| (k, ids_to_keys(df, &lookup_df)))
+                .collect();
+            id_lookup_df = Some(lookup_df);
+        } else {
+            cat_df_map = term_df_map
+                .into_iter()
+                .map(|(k, df)| (k, self.df_with_cats(df)))
+                .collect();
+            id_lookup_df = None;
+        }
         let max_index = find_max_index(cat_df_map.values());
         if create_sparse {
             let SparsePathReturn { sparmat, soo, dt } =
@@ -77,7 +98,11 @@ impl Triplestore {
                     }
                 }
             }
-            let mut lookup_df = find_lookup(&cat_df_map);
+            let mut lookup_df = if let Some(lookup_df) = &id_lookup_df {
+                lookup_df.clone()
+            } else {
+                find_lookup(&cat_df_map)
+            };
             let mut subject_series = Series::from_iter(subject_vec.into_iter());
             subject_series.rename("subject_key");
             let mut object_series = Series::from_iter(object_vec.into_iter());
@@ -103,8 +128,12 @@ impl Triplestore {
                 .unwrap();
             out_df = out_df.select(["subject", "object"]).unwrap();
         } else {
-            let DFPathReturn { df, soo, dt } = df_path(ppe, &cat_df_map, max_index);
-            out_df = df;
+            let DFPathReturn { df, soo, dt } = df_path(ppe, &cat_df_map, max_index, use_ids);
+            out_df = if let Some(lookup_df) = &id_lookup_df {
+                keys_to_ids(df, lookup_df)
+            } else {
+                df
+            };
             out_soo = soo;
             out_dt = dt;
         }
@@ -129,17 +158,26 @@ impl Triplestore {
                 .filter(|x| mappings.columns.contains(x))
                 .collect();
 
-            for j in &join_cols {
-                mappings.mappings = mappings
-                    .mappings
-                    .with_column(col(j).cast(DataType::Categorical(None)));
-            }
+            //The terms of the path are IRIs, and are joined as such
+            let path_rdf_node_types: HashMap<String, RDFNodeType> = join_cols
+                .iter()
+                .map(|x| (x.clone(), RDFNodeType::IRI))
+                .collect();
+            let join_on: Vec<&String> = join_cols.iter().collect();
+            let (harmonized_mappings, harmonized_path_lf) = self.harmonize_join_columns(
+                mappings.mappings,
+                &mappings.rdf_node_types,
+                out_df.lazy(),
+                &path_rdf_node_types,
+                join_on.as_slice(),
+            );
+            mappings.mappings = harmonized_mappings;
 
             let join_on: Vec<Expr> = join_cols.iter().map(|x| col(x)).collect();
 
             if join_on.is_empty() {
                 mappings.mappings = mappings.mappings.join(
-                    out_df.lazy(),
+                    harmonized_path_lf,
                     join_on.as_slice(),
                     join_on.as_slice(),
                     JoinArgs::new(JoinType::Cross),
@@ -147,7 +185,7 @@ impl Triplestore {
             } else {
                 let join_col_exprs: Vec<Expr> = join_cols.iter().map(|x| col(x)).collect();
                 let all_false = [false].repeat(join_cols.len());
-                let lf = out_df.lazy().sort_by_exprs(
+                let lf = harmonized_path_lf.sort_by_exprs(
                     join_col_exprs.as_slice(),
                     all_false.as_slice(),
                     false,
@@ -199,65 +237,68 @@ impl Triplestore {
         }
     }
 
-    fn create_unique_cat_dfs(
+    //The subjects and objects of the predicates in the path. They are ids when the objects are
+    //IRIs or blank nodes, otherwise the subjects are decoded.
+    fn create_unique_term_dfs(
         &self,
+        graphs: &[Option<String>],
         ppe: &PropertyPathExpression,
         subject: Option<&TermPattern>,
         object: Option<&TermPattern>,
     ) -> Result<HashMap<String, DataFrame>, SparqlError> {
         match ppe {
             PropertyPathExpression::NamedNode(nn) => {
-                let df = self.get_single_nn_df(nn.as_str(), subject, object)?;
+                let df = self.get_single_nn_df(graphs, nn.as_str(), subject, object)?;
                 if let Some(df) = df {
-                    let unique_cat_df = df_with_cats(df)
-                        .unique(None, UniqueKeepStrategy::First, None)
-                        .unwrap();
-                    Ok(HashMap::from([(nn.as_str().to_string(), unique_cat_df)]))
+                    let unique_df = df.unique(None, UniqueKeepStrategy::First, None).unwrap();
+                    Ok(HashMap::from([(nn.as_str().to_string(), unique_df)]))
                 } else {
-                    let df = DataFrame::new(vec![
-                        Series::new_empty("subject", &DataType::Categorical(None)),
-                        Series::new_empty("object", &DataType::Categorical(None)),
-                    ])
-                    .unwrap();
-                    Ok(HashMap::from([(nn.as_str().to_string(), df)]))
+                    Ok(HashMap::from([(nn.as_str().to_string(), empty_id_df())]))
                 }
             }
             PropertyPathExpression::Reverse(inner) => {
-                self.create_unique_cat_dfs(inner, object, subject)
+                self.create_unique_term_dfs(graphs, inner, object, subject)
             }
             PropertyPathExpression::Sequence(left, right) => {
-                let mut left_df_map = self.create_unique_cat_dfs(left, subject, None)?;
-                let right_df_map = self.create_unique_cat_dfs(right, None, object)?;
+                let mut left_df_map = self.create_unique_term_dfs(graphs, left, subject, None)?;
+                let right_df_map = self.create_unique_term_dfs(graphs, right, None, object)?;
                 left_df_map.extend(right_df_map);
                 Ok(left_df_map)
             }
             PropertyPathExpression::Alternative(left, right) => {
                 let mut left_df_map =
-                    self.create_unique_cat_dfs(left, subject.clone(), object.clone())?;
-                let right_df_map = self.create_unique_cat_dfs(right, subject, object)?;
+                    self.create_unique_term_dfs(graphs, left, subject.clone(), object.clone())?;
+                let right_df_map = self.create_unique_term_dfs(graphs, right, subject, object)?;
                 left_df_map.extend(right_df_map);
                 Ok(left_df_map)
             }
             PropertyPathExpression::ZeroOrMore(inner) => {
-                self.create_unique_cat_dfs(inner, subject, object)
+                self.create_unique_term_dfs(graphs, inner, subject, object)
             }
             PropertyPathExpression::OneOrMore(inner) => {
-                self.create_unique_cat_dfs(inner, subject, object)
+                self.create_unique_term_dfs(graphs, inner, subject, object)
             }
             PropertyPathExpression::ZeroOrOne(inner) => {
-                self.create_unique_cat_dfs(inner, subject, object)
+                self.create_unique_term_dfs(graphs, inner, subject, object)
             }
             PropertyPathExpression::NegatedPropertySet(nns) => {
                 let lookup: Vec<_> = nns.iter().map(|x| x.as_str().to_string()).collect();
                 let mut dfs = vec![];
-                for nn in self.df_map.keys() {
-                    if !lookup.contains(nn) {
-                        let df = self.get_single_nn_df(nn, subject, object)?;
+                for nn in self.all_predicates(graphs) {
+                    if !lookup.contains(&nn) {
+                        let df = self.get_single_nn_df(graphs, &nn, subject, object)?;
                         if let Some(df) = df {
-                            dfs.push(df_with_cats(df));
+                            dfs.push(df);
                         }
                     }
                 }
+                //Predicates with literal objects can only be concatenated with the others as categoricals
+                if !dfs
+                    .iter()
+                    .all(|df| df.column("subject").unwrap().dtype() == &DataType::UInt32)
+                {
+                    dfs = dfs.into_iter().map(|df| self.df_with_cats(df)).collect();
+                }
                 let df;
                 if dfs.len() > 0 {
                     df = concat_df(dfs.as_slice())
@@ -265,11 +306,7 @@ impl Triplestore {
                         .unique(None, UniqueKeepStrategy::First, None)
                         .unwrap();
                 } else {
-                    df = DataFrame::new(vec![
-                        Series::new_empty("subject", &DataType::Categorical(None)),
-                        Series::new_empty("object", &DataType::Categorical(None)),
-                    ])
-                    .unwrap();
+                    df = empty_id_df();
                 }
                 Ok(HashMap::from([(nns_name(nns), df)]))
             }
@@ -278,58 +315,104 @@ impl Triplestore {
 
     fn get_single_nn_df(
         &self,
+        graphs: &[Option<String>],
         nn: &str,
         subject: Option<&TermPattern>,
         object: Option<&TermPattern>,
     ) -> Result<Option<DataFrame>, SparqlError> {
-        let map_opt = self.df_map.get(nn);
-        if let Some(m) = map_opt {
-            if m.is_empty() {
-                panic!("Empty map should never happen");
-            } else if m.len() > 1 {
-                todo!("Multiple datatypes not supported yet")
-            } else {
-                let (dt, tt) = m.iter().next().unwrap();
-                assert!(tt.unique, "Should be deduplicated");
-                let mut lf = concat(
-                    tt.get_lazy_frames()
-                        .map_err(|x| SparqlError::TripleTableReadError(x))?,
-                    UnionArgs::default(),
-                )
-                .unwrap()
-                .select([col("subject"), col("object")]);
-                if let Some(subject) = subject {
-                    if let TermPattern::NamedNode(nn) = subject {
-                        lf = lf.filter(
-                            col("subject")
-                                .eq(Expr::Literal(sparql_named_node_to_polars_literal_value(nn))),
-                        )
-                    } else if let TermPattern::Literal(l) = subject {
-                        lf = lf.filter(
-                            col("subject")
-                                .eq(Expr::Literal(sparql_literal_to_polars_literal_value(l))),
-                        )
-                    }
+        if let Some((dt, mut lf)) = self.get_predicate_lazy_frame(graphs, nn)? {
+            let schema = lf.schema().unwrap();
+            let subject_encoded = is_encoded(&RDFNodeType::IRI, schema.get("subject").unwrap());
+            let object_encoded = is_encoded(&dt, schema.get("object").unwrap());
+            if let Some(subject) = subject {
+                if let Some(f) = self.term_pattern_filter(subject, "subject", subject_encoded)? {
+                    lf = lf.filter(f);
                 }
-                if let Some(object) = object {
-                    if let TermPattern::NamedNode(nn) = object {
-                        lf = lf.filter(
-                            col("object")
-                                .eq(Expr::Literal(sparql_named_node_to_polars_literal_value(nn))),
-                        )
-                    } else if let TermPattern::Literal(l) = object {
-                        lf = lf.filter(
-                            col("object")
-                                .eq(Expr::Literal(sparql_literal_to_polars_literal_value(l))),
-                        )
-                    }
+            }
+            if let Some(object) = object {
+                if let Some(f) = self.term_pattern_filter(object, "object", object_encoded)? {
+                    lf = lf.filter(f);
                 }
-                Ok(Some(lf.collect().unwrap()))
             }
+            if subject_encoded && !object_encoded {
+                lf = lf.with_column(self.decode_column("subject"));
+            }
+            Ok(Some(lf.collect().unwrap()))
         } else {
             Ok(None)
         }
     }
+
+    fn df_with_cats(&self, df: DataFrame) -> DataFrame {
+        let mut subject = df.column("subject").unwrap().clone();
+        let mut object = df.column("object").unwrap().clone();
+        //The subjects are only ids when the objects are ids as well
+        if subject.dtype() == &DataType::UInt32 {
+            subject = self.term_dictionary.decode(&subject);
+            object = self.term_dictionary.decode(&object);
+        }
+        DataFrame::new(vec![
+            subject.cast(&DataType::Categorical(None)).unwrap(),
+            object.cast(&DataType::Categorical(None)).unwrap(),
+        ])
+        .unwrap()
+    }
+}
+
+fn empty_id_df() -> DataFrame {
+    DataFrame::new(vec![
+        Series::new_empty("subject", &DataType::UInt32),
+        Series::new_empty("object", &DataType::UInt32),
+    ])
+    .unwrap()
+}
+
+//The ids of the terms in the path, with keys numbered from zero
+fn find_id_lookup(map: &HashMap<String, DataFrame>) -> DataFrame {
+    let mut all_values = vec![];
+    for v in map.values() {
+        for c in ["subject", "object"] {
+            let mut values = v.column(c).unwrap().unique().unwrap();
+            values.rename("value");
+            all_values.push(DataFrame::new(vec![values]).unwrap());
+        }
+    }
+    concat_df(all_values.as_slice())
+        .unwrap()
+        .unique(None, UniqueKeepStrategy::First, None)
+        .unwrap()
+        .with_row_count("key", None)
+        .unwrap()
+}
+
+fn ids_to_keys(df: DataFrame, lookup_df: &DataFrame) -> DataFrame {
+    replace_with_lookup(df, lookup_df, "value", "key")
+}
+
+fn keys_to_ids(df: DataFrame, lookup_df: &DataFrame) -> DataFrame {
+    replace_with_lookup(df, lookup_df, "key", "value")
+}
+
+fn replace_with_lookup(df: DataFrame, lookup_df: &DataFrame, from: &str, to: &str) -> DataFrame {
+    let mut lf = df.lazy();
+    for c in ["subject", "object"] {
+        let lookup_lf = lookup_df
+            .clone()
+            .lazy()
+            .select([col(from).alias(c), col(to).alias("replacement")]);
+        lf = lf
+            .join(
+                lookup_lf,
+                [col(c)],
+                [col(c)],
+                JoinArgs::new(JoinType::Inner),
+            )
+            .drop_columns([c])
+            .rename(["replacement"], [c]);
+    }
+    lf.select([col("subject"), col("object")])
+        .collect()
+        .unwrap()
 }
 
 fn find_lookup(map: &HashMap<String, DataFrame>) -> DataFrame {
@@ -346,14 +429,7 @@ fn find_lookup(map: &HashMap<String, DataFrame>) -> DataFrame {
         .unwrap()
         .unique(None, UniqueKeepStrategy::First, None)
         .unwrap();
-    let mut key_col = df
-        .column("value")
-        .unwrap()
-        .categorical()
-        .unwrap()
-        .logical()
-        .clone()
-        .into_series();
+    let mut key_col = index_series(df.column("value").unwrap());
     key_col.rename("key");
     df.with_column(key_col).unwrap();
     df
@@ -363,6 +439,7 @@ fn df_path(
     ppe: &PropertyPathExpression,
     cat_df_map: &HashMap<String, DataFrame>,
     max_index: u32,
+    use_ids: bool,
 ) -> DFPathReturn {
     match ppe {
         PropertyPathExpression::NamedNode(nn) => {
@@ -374,7 +451,7 @@ fn df_path(
             }
         }
         PropertyPathExpression::Reverse(inner) => {
-            let DFPathReturn { df, soo, dt } = df_path(inner, cat_df_map, max_index);
+            let DFPathReturn { df, soo, dt } = df_path(inner, cat_df_map, max_index, use_ids);
             let df = df
                 .lazy()
                 .rename(["subject", "object"], ["object", "subject"])
@@ -391,12 +468,12 @@ fn df_path(
                 df: mut df_left,
                 soo: _,
                 dt: _,
-            } = df_path(left, cat_df_map, max_index);
+            } = df_path(left, cat_df_map, max_index, use_ids);
             let DFPathReturn {
                 df: mut df_right,
                 soo: soo_right,
                 dt: dt_right,
-            } = df_path(left, cat_df_map, max_index);
+            } = df_path(left, cat_df_map, max_index, use_ids);
             df_left.rename("object", "on").unwrap();
             df_right.rename("subject", "on").unwrap();
             df_left = df_left.sort(vec!["on"], vec![false], false).unwrap();
@@ -415,12 +492,12 @@ fn df_path(
                 df: df_left,
                 soo: soo_left,
                 dt: dt_left,
-            } = df_path(left, cat_df_map, max_index);
+            } = df_path(left, cat_df_map, max_index, use_ids);
             let DFPathReturn {
                 df: df_right,
                 soo: _soo_right,
                 dt: _dt_right,
-            } = df_path(left, cat_df_map, max_index);
+            } = df_path(left, cat_df_map, max_index, use_ids);
             let df = df_left
                 .vstack(&df_right)
                 .unwrap()
@@ -433,14 +510,14 @@ fn df_path(
             }
         }
         PropertyPathExpression::ZeroOrOne(inner) => {
-            let DFPathReturn { df, soo, dt } = df_path(inner, cat_df_map, max_index);
-            let mut all_subjects = Series::from_iter(0..max_index)
-                .cast(&DataType::Categorical(None))
-                .unwrap();
+            let DFPathReturn { df, soo, dt } = df_path(inner, cat_df_map, max_index, use_ids);
+            //With ids, the keys of the lookup are used
+            let mut all_subjects = Series::from_iter(0..max_index);
+            if !use_ids {
+                all_subjects = all_subjects.cast(&DataType::Categorical(None)).unwrap();
+            }
             all_subjects.rename("subject");
-            let mut all_objects = Series::from_iter(0..max_index)
-                .cast(&DataType::Categorical(None))
-                .unwrap();
+            let mut all_objects = all_subjects.clone();
             all_objects.rename("object");
             let id_df = DataFrame::new(vec![all_subjects, all_objects]).unwrap();
             let df = concat_df([&df, &id_df]).unwrap();
@@ -464,39 +541,28 @@ fn df_path(
     }
 }
 
-fn df_with_cats(df: DataFrame) -> DataFrame {
-    let subject = df
-        .column("subject")
-        .unwrap()
-        .cast(&DataType::Categorical(None))
-        .unwrap();
-    let object = df
-        .column("object")
-        .unwrap()
-        .cast(&DataType::Categorical(None))
-        .unwrap();
-    DataFrame::new(vec![subject, object]).unwrap()
+//The categorical ids, or the keys of the lookup when ids are used
+fn index_series(s: &Series) -> Series {
+    if let DataType::Categorical(_) = s.dtype() {
+        s.categorical().unwrap().logical().clone().into_series()
+    } else {
+        s.clone()
+    }
 }
 
 fn find_max_index(vals: Values<String, DataFrame>) -> u32 {
     let mut max_index = 0u32;
     for df in vals {
-        if let Some(max_subject) = df
-            .column("subject")
-            .unwrap()
-            .categorical()
+        if let Some(max_subject) = index_series(df.column("subject").unwrap())
+            .u32()
             .unwrap()
-            .logical()
             .max()
         {
             max_index = max(max_index, max_subject);
         }
-        if let Some(max_object) = df
-            .column("object")
+        if let Some(max_object) = index_series(df.column("object").unwrap())
+            .u32()
             .unwrap()
-            .categorical()
-            .unwrap()
-            .logical()
             .max()
         {
             max_index = max(max_index, max_object);
@@ -506,22 +572,8 @@ fn find_max_index(vals: Values<String, DataFrame>) -> u32 {
 }
 
 fn to_csr(df: &DataFrame, max_index: usize) -> SparseMatrix {
-    let sub = df
-        .column("subject")
-        .unwrap()
-        .categorical()
-        .unwrap()
-        .logical()
-        .clone()
-        .into_series();
-    let obj = df
-        .column("object")
-        .unwrap()
-        .categorical()
-        .unwrap()
-        .logical()
-        .clone()
-        .into_series();
+    let sub = index_series(df.column("subject").unwrap());
+    let obj = index_series(df.column("object").unwrap());
     let df = DataFrame::new(vec![sub, obj]).unwrap();
     let df = df
         .sort(vec!["subject", "object"], vec![false, false], false)
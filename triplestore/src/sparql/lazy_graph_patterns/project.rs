@@ -4,7 +4,8 @@ use crate::sparql::query_context::{Context, PathEntry};
 use crate::sparql::solution_mapping::SolutionMappings;
 use log::{debug, warn};
 use oxrdf::Variable;
-use polars::prelude::{col, Expr};
+use polars::prelude::{col, lit, Expr, LiteralValue};
+use representation::RDFNodeType;
 use spargebra::algebra::GraphPattern;
 use std::collections::HashMap;
 
@@ -19,18 +20,30 @@ impl Triplestore {
         debug!("Processing project graph pattern");
         let SolutionMappings {
             mut mappings,
+            columns,
             rdf_node_types: mut datatypes,
-            ..
         } = self.lazy_graph_pattern(
             inner,
             solution_mappings,
             &context.extension_with(PathEntry::ProjectInner),
         )?;
-        let cols: Vec<Expr> = variables.iter().map(|c| col(c.as_str())).collect();
+        //Variables that are not in the pattern are unbound
+        let cols: Vec<Expr> = variables
+            .iter()
+            .map(|c| {
+                if columns.contains(c.as_str()) {
+                    col(c.as_str())
+                } else {
+                    lit(LiteralValue::Null).alias(c.as_str())
+                }
+            })
+            .collect();
         mappings = mappings.select(cols.as_slice());
         let mut new_datatypes = HashMap::new();
         for v in variables {
-            if !datatypes.contains_key(v.as_str()) {
+            if !columns.contains(v.as_str()) {
+                new_datatypes.insert(v.as_str().to_string(), RDFNodeType::None);
+            } else if !datatypes.contains_key(v.as_str()) {
                 warn!("Datatypes does not contain {}", v);
             } else {
                 new_datatypes.insert(
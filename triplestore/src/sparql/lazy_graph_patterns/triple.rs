@@ -1,15 +1,13 @@
 use super::Triplestore;
+use crate::sparql::encoded_terms::is_encoded;
 use crate::sparql::errors::SparqlError;
 use crate::sparql::query_context::Context;
-use crate::sparql::solution_mapping::{is_string_col, SolutionMappings};
-use crate::sparql::sparql_to_polars::{
-    sparql_literal_to_polars_literal_value, sparql_named_node_to_polars_literal_value,
-};
+use crate::sparql::solution_mapping::SolutionMappings;
 use log::warn;
-use polars::prelude::{col, concat, lit, Expr};
+use polars::prelude::{col, concat, lit, Expr, LazyFrame};
 use polars::prelude::{IntoLazy, UnionArgs};
 use polars_core::datatypes::{AnyValue, DataType};
-use polars_core::frame::DataFrame;
+use polars_core::frame::{DataFrame, UniqueKeepStrategy};
 use polars_core::prelude::{JoinArgs, JoinType, NamedFrom};
 use polars_core::series::Series;
 use representation::RDFNodeType;
@@ -21,22 +19,22 @@ impl Triplestore {
         &self,
         mut solution_mappings: Option<SolutionMappings>,
         triple_pattern: &TriplePattern,
-        _context: &Context,
+        context: &Context,
     ) -> Result<SolutionMappings, SparqlError> {
-        let subject_filter = create_term_pattern_filter(&triple_pattern.subject, "subject");
-        let object_filter = create_term_pattern_filter(&triple_pattern.object, "object");
+        let graphs = &context.active_graphs;
         let subject_rename = get_keep_rename_term_pattern(&triple_pattern.subject);
         let verb_rename = get_keep_rename_named_node_pattern(&triple_pattern.predicate);
         let object_rename = get_keep_rename_term_pattern(&triple_pattern.object);
 
         let (mut df, mut dts) = match &triple_pattern.predicate {
             NamedNodePattern::NamedNode(n) => self.get_predicate_df(
+                graphs,
                 n.as_str(),
                 &subject_rename,
                 &verb_rename,
                 &object_rename,
-                subject_filter,
-                object_filter,
+                &triple_pattern.subject,
+                &triple_pattern.object,
             )?,
             NamedNodePattern::Variable(v) => {
                 let predicates: Vec<String>;
@@ -49,7 +47,11 @@ impl Triplestore {
                     if let Some(dt) = rdf_node_types.get(v.as_str()) {
                         if let RDFNodeType::IRI = dt {
                             let mappings_df = mappings.collect().unwrap();
-                            let predicates_iter = mappings_df.column(v.as_str()).unwrap().iter();
+                            let mut predicates_series = mappings_df.column(v.as_str()).unwrap().clone();
+                            if predicates_series.dtype() == &DataType::UInt32 {
+                                predicates_series = self.term_dictionary.decode(&predicates_series);
+                            }
+                            let predicates_iter = predicates_series.iter();
                             predicates = predicates_iter
                                 .filter_map(|x| match x {
                                     AnyValue::Null => None,
@@ -76,18 +78,19 @@ impl Triplestore {
                             columns,
                             rdf_node_types,
                         });
-                        predicates = self.all_predicates();
+                        predicates = self.all_predicates(graphs);
                     }
                 } else {
-                    predicates = self.all_predicates();
+                    predicates = self.all_predicates(graphs);
                 }
                 self.get_predicates_df(
+                    graphs,
                     &predicates,
                     &subject_rename,
                     &verb_rename,
                     &object_rename,
-                    subject_filter,
-                    object_filter,
+                    &triple_pattern.subject,
+                    &triple_pattern.object,
                 )?
             }
         };
@@ -117,17 +120,14 @@ impl Triplestore {
             } else {
                 if overlap.len() > 0 {
                     let join_on: Vec<Expr> = overlap.iter().map(|x| col(x)).collect();
-                    let mut strcol = vec![];
-                    for c in overlap {
-                        if is_string_col(rdf_node_types.get(c).unwrap()) {
-                            strcol.push(c);
-                        }
-                    }
-                    let mut lf = df.lazy();
-                    for c in strcol {
-                        lf = lf.with_column(col(c).cast(DataType::Categorical(None)));
-                        mappings = mappings.with_column(col(c).cast(DataType::Categorical(None)));
-                    }
+                    let (harmonized_mappings, lf) = self.harmonize_join_columns(
+                        mappings,
+                        &rdf_node_types,
+                        df.lazy(),
+                        &dts,
+                        overlap.as_slice(),
+                    );
+                    mappings = harmonized_mappings;
 
                     mappings = mappings.join(lf, join_on.as_slice(), join_on.as_slice(), JoinType::Inner.into());
                 } else {
@@ -154,52 +154,42 @@ impl Triplestore {
 
     fn get_predicate_df(
         &self,
+        graphs: &[Option<String>],
         verb_uri: &str,
         subject_keep_rename: &Option<String>,
         verb_keep_rename: &Option<String>,
         object_keep_rename: &Option<String>,
-        subject_filter: Option<Expr>,
-        object_filter: Option<Expr>,
+        subject_pattern: &TermPattern,
+        object_pattern: &TermPattern,
     ) -> Result<(DataFrame, HashMap<String, RDFNodeType>), SparqlError> {
-        Ok(if let Some(m) = self.df_map.get(verb_uri) {
-            if m.is_empty() {
-                panic!("Empty map should never happen");
-            } else if m.len() > 1 {
-                let dts: Vec<&RDFNodeType> = m.keys().collect();
-                todo!("Multiple datatypes not supported yet {:?}", dts);
-            } else {
-                let (dt, tt) = m.iter().next().unwrap();
-                assert!(tt.unique, "Should be deduplicated");
-                let mut out_datatypes = HashMap::new();
-                let mut lf = concat(
-                    tt.get_lazy_frames()
-                        .map_err(|x| SparqlError::TripleTableReadError(x))?,
-                    UnionArgs::default(),
-                ).unwrap().select(vec![col("subject"), col("object")]);
+        Ok(if let Some((dt, mut lf)) = self.get_predicate_lazy_frame(graphs, verb_uri)? {
+            let mut out_datatypes = HashMap::new();
+            let schema = lf.schema().unwrap();
+            let subject_encoded = is_encoded(&RDFNodeType::IRI, schema.get("subject").unwrap());
+            let object_encoded = is_encoded(&dt, schema.get("object").unwrap());
+            if let Some(f) = self.term_pattern_filter(subject_pattern, "subject", subject_encoded)? {
+                lf = lf.filter(f);
+            }
+            if let Some(f) = self.term_pattern_filter(object_pattern, "object", object_encoded)? {
+                lf = lf.filter(f);
+            }
 
-                let mut drop = vec![];
-                if let Some(renamed) = subject_keep_rename {
-                    lf = lf.rename(["subject"], [renamed]);
-                    out_datatypes.insert(renamed.to_string(), RDFNodeType::IRI);
-                }
-                else {
-                    drop.push("select");
-                }
-                if let Some(renamed) = object_keep_rename {
-                    lf = lf.rename(["object"], [renamed]);
-                    out_datatypes.insert(renamed.to_string(), dt.clone());
-                } else {
-                    drop.push("object")
-                }
-                if let Some(f) = subject_filter {
-                    lf = lf.filter(f);
-                }
-                if let Some(f) = object_filter {
-                    lf = lf.filter(f);
-                }
-                lf = lf.drop_columns(drop);
-                (lf.collect().unwrap(), out_datatypes)
+            let mut drop = vec![];
+            if let Some(renamed) = subject_keep_rename {
+                lf = lf.rename(["subject"], [renamed]);
+                out_datatypes.insert(renamed.to_string(), RDFNodeType::IRI);
+            }
+            else {
+                drop.push("subject");
+            }
+            if let Some(renamed) = object_keep_rename {
+                lf = lf.rename(["object"], [renamed]);
+                out_datatypes.insert(renamed.to_string(), dt.clone());
+            } else {
+                drop.push("object")
             }
+            lf = lf.drop_columns(drop);
+            (lf.collect().unwrap(), out_datatypes)
         } else {
             create_empty_df_datatypes(subject_keep_rename, verb_keep_rename, object_keep_rename)
         })
@@ -207,23 +197,25 @@ impl Triplestore {
 
     fn get_predicates_df(
         &self,
+        graphs: &[Option<String>],
         predicate_uris: &Vec<String>,
         subject_keep_rename: &Option<String>,
         verb_keep_rename: &Option<String>,
         object_keep_rename: &Option<String>,
-        subject_filter: Option<Expr>,
-        object_filter: Option<Expr>,
+        subject_pattern: &TermPattern,
+        object_pattern: &TermPattern,
     ) -> Result<(DataFrame, HashMap<String, RDFNodeType>), SparqlError> {
         let mut out_datatypes = HashMap::new();
         let mut lfs = vec![];
         for v in predicate_uris {
             let (mut df, datatypes_map) = self.get_predicate_df(
+                graphs,
                 v,
                 subject_keep_rename,
                 verb_keep_rename,
                 object_keep_rename,
-                subject_filter.clone(),
-                object_filter.clone(),
+                subject_pattern,
+                object_pattern,
             )?;
 
             //Should be possible to skip around multiple datatypes requirement when df is empty
@@ -261,13 +253,73 @@ impl Triplestore {
             create_empty_df_datatypes(subject_keep_rename, verb_keep_rename, object_keep_rename)
         })
     }
-    fn all_predicates(&self) -> Vec<String> {
+    pub(crate) fn all_predicates(&self, graphs: &[Option<String>]) -> Vec<String> {
         let mut strs = vec![];
-        for k in self.df_map.keys() {
-            strs.push(k.clone())
+        for g in graphs {
+            if let Some(df_map) = self.graph_df_maps.get(g) {
+                for k in df_map.keys() {
+                    if !strs.contains(k) {
+                        strs.push(k.clone())
+                    }
+                }
+            }
         }
         strs
     }
+
+    //The subjects and objects of the predicate in the graphs. When there are several graphs, they
+    //are merged and the duplicates removed. IRI and blank node objects are merged like subjects,
+    //as both are ids in the term dictionary, and blank nodes are told apart by their prefix.
+    pub(crate) fn get_predicate_lazy_frame(
+        &self,
+        graphs: &[Option<String>],
+        verb_uri: &str,
+    ) -> Result<Option<(RDFNodeType, LazyFrame)>, SparqlError> {
+        let mut tables = vec![];
+        for g in graphs {
+            if let Some(m) = self.graph_df_maps.get(g).and_then(|x| x.get(verb_uri)) {
+                if m.is_empty() {
+                    panic!("Empty map should never happen");
+                }
+                tables.extend(m.iter());
+            }
+        }
+        if tables.is_empty() {
+            return Ok(None);
+        }
+        let mut dts: Vec<RDFNodeType> = vec![];
+        for (dt, _) in &tables {
+            if !dts.contains(dt) {
+                dts.push((*dt).clone());
+            }
+        }
+        let dt = if dts.len() == 1 {
+            dts.pop().unwrap()
+        } else if dts
+            .iter()
+            .all(|x| matches!(x, RDFNodeType::IRI | RDFNodeType::BlankNode))
+        {
+            RDFNodeType::IRI
+        } else {
+            //The object columns are concatenated, so they must have the same type
+            return Err(SparqlError::MultipleObjectTypes(verb_uri.to_string(), dts));
+        };
+        let mut lfs = vec![];
+        for (_, tt) in &tables {
+            assert!(tt.unique, "Should be deduplicated");
+            lfs.extend(
+                tt.get_lazy_frames()
+                    .map_err(|x| SparqlError::TripleTableReadError(x))?,
+            );
+        }
+        let mut lf = concat(lfs, UnionArgs::default())
+            .unwrap()
+            .select(vec![col("subject"), col("object")]);
+        if tables.len() > 1 {
+            lf = lf.unique(None, UniqueKeepStrategy::First);
+        }
+        Ok(Some((dt, lf)))
+    }
 }
 
 fn create_empty_df_datatypes(
@@ -286,15 +338,6 @@ fn create_empty_df_datatypes(
     (DataFrame::new(series_vec).unwrap(), out_datatypes)
 }
 
-fn create_term_pattern_filter(term_pattern: &TermPattern, target_col: &str) -> Option<Expr> {
-    if let TermPattern::Literal(l) = term_pattern {
-        return Some(col(target_col).eq(lit(sparql_literal_to_polars_literal_value(l))));
-    } else if let TermPattern::NamedNode(nn) = term_pattern {
-        return Some(col(target_col).eq(lit(sparql_named_node_to_polars_literal_value(nn))));
-    }
-    None
-}
-
 fn get_keep_rename_term_pattern(term_pattern: &TermPattern) -> Option<String> {
     if let TermPattern::Variable(v) = term_pattern {
         return Some(v.as_str().to_string());
@@ -30,8 +30,6 @@ impl Triplestore {
             rdf_node_types: mut right_datatypes,
         } = self.lazy_graph_pattern(right, solution_mappings, &right_context)?;
 
-        let output_mappings = diag_concat_lf(vec![left_mappings, right_mappings], true, true)
-            .expect("Concat problem");
         left_columns.extend(right_columns);
         for (v, dt) in right_datatypes.drain() {
             if let Some(left_dt) = left_datatypes.get(&v) {
@@ -40,6 +38,8 @@ impl Triplestore {
                 left_datatypes.insert(v, dt);
             }
         }
+        let lfs = self.harmonize_concat(vec![left_mappings, right_mappings], &left_datatypes);
+        let output_mappings = diag_concat_lf(lfs, true, true).expect("Concat problem");
         Ok(SolutionMappings::new(
             output_mappings,
             left_columns,
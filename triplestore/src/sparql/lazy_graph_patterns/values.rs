@@ -62,7 +62,8 @@ impl Triplestore {
                                 }
                             }
                             let value = lit.value().to_string();
-                            let (mut polarlit, _) = sparql_literal_to_any_value(&value, &Some(dt));
+                            let (mut polarlit, _) = sparql_literal_to_any_value(&value, &Some(dt))
+                                .map_err(|x| SparqlError::InvalidLiteral(x))?;
                             polarlit = polarlit.into_static().unwrap();
                             col_vecs.get_mut(&j).unwrap().push(polarlit);
                         }
@@ -70,6 +71,7 @@ impl Triplestore {
                             unimplemented!()
                         }
                     }
+                } else {
                     if i + 1 == bindings.len() {
                         if !datatypes.contains_key(&j) {
                             datatypes.insert(j, RDFNodeType::None);
@@ -87,6 +89,11 @@ impl Triplestore {
             all_series.push(series);
         }
         let df = DataFrame::new(all_series).unwrap();
+        let mut out_datatypes = HashMap::new();
+        for (k, v) in datatypes {
+            let var = variables.get(k).unwrap();
+            out_datatypes.insert(var.as_str().to_string(), v);
+        }
         if let Some(mut mappings) = solution_mappings {
             let join_on: Vec<String> = variables
                 .iter()
@@ -97,10 +104,6 @@ impl Triplestore {
             for v in variables {
                 mappings.columns.insert(v.as_str().to_string());
             }
-            for (k, v) in datatypes {
-                let var = variables.get(k).unwrap();
-                mappings.rdf_node_types.insert(var.as_str().to_string(), v);
-            }
             if join_on.is_empty() {
                 mappings.mappings = mappings.mappings.join(
                     df.lazy(),
@@ -109,20 +112,25 @@ impl Triplestore {
                     JoinArgs::new(JoinType::Cross),
                 );
             } else {
-                mappings.mappings = mappings.mappings.join(
+                //The values are strings, so encoded IRIs and blank nodes in the mappings are decoded
+                let join_on: Vec<&String> = join_on.iter().collect();
+                let (left, right) = self.harmonize_join_columns(
+                    mappings.mappings,
+                    &mappings.rdf_node_types,
                     df.lazy(),
+                    &out_datatypes,
+                    join_on.as_slice(),
+                );
+                mappings.mappings = left.join(
+                    right,
                     join_cols.as_slice(),
                     join_cols.as_slice(),
                     JoinArgs::new(JoinType::Inner),
                 );
             }
+            mappings.rdf_node_types.extend(out_datatypes);
             Ok(mappings)
         } else {
-            let mut out_datatypes = HashMap::new();
-            for (k, v) in datatypes {
-                let var = variables.get(k).unwrap();
-                out_datatypes.insert(var.as_str().to_string(), v);
-            }
             Ok(SolutionMappings::new(
                 df.lazy(),
                 variables.iter().map(|x| x.as_str().to_string()).collect(),
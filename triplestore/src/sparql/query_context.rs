@@ -257,10 +257,31 @@ impl fmt::Display for PathEntry {
     }
 }
 
+//The graphs of the RDF dataset a query is evaluated against, given by FROM and FROM NAMED
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct Dataset {
+    //The default graph is the merge of these graphs, where None is the stored default graph
+    pub default: Vec<Option<String>>,
+    //None means all the stored named graphs
+    pub named: Option<Vec<String>>,
+}
+
+impl Default for Dataset {
+    fn default() -> Self {
+        Dataset {
+            default: vec![None],
+            named: None,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Eq, Hash)]
 pub struct Context {
     string_rep: String,
     pub path: Vec<PathEntry>,
+    pub dataset: Dataset,
+    //The graphs that triple patterns are matched against, changed by GRAPH
+    pub active_graphs: Vec<Option<String>>,
 }
 
 impl Context {
@@ -433,12 +454,24 @@ fn maintains_full_downward_scope(path_entry: &PathEntry) -> bool {
 
 impl Context {
     pub fn new() -> Context {
+        Context::with_dataset(Dataset::default())
+    }
+
+    pub fn with_dataset(dataset: Dataset) -> Context {
         Context {
             string_rep: "".to_string(),
             path: vec![],
+            active_graphs: dataset.default.clone(),
+            dataset,
         }
     }
 
+    pub fn with_active_graphs(&self, active_graphs: Vec<Option<String>>) -> Context {
+        let mut ctx = self.clone();
+        ctx.active_graphs = active_graphs;
+        ctx
+    }
+
     pub fn from_path(path: Vec<PathEntry>) -> Context {
         let mut ctx = Context::new();
         for p in path {
@@ -460,6 +493,11 @@ impl Context {
         let entry_rep = p.to_string();
         string_rep += entry_rep.as_str();
         path.push(p);
-        Context { path, string_rep }
+        Context {
+            path,
+            string_rep,
+            dataset: self.dataset.clone(),
+            active_graphs: self.active_graphs.clone(),
+        }
     }
 }
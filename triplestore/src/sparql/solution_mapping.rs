@@ -26,7 +26,7 @@ impl SolutionMappings {
 
 pub fn is_string_col(rdf_node_type: &RDFNodeType) -> bool {
     match rdf_node_type {
-        RDFNodeType::IRI => {
+        RDFNodeType::IRI | RDFNodeType::BlankNode => {
             return true;
         }
         RDFNodeType::Literal(lit) => {
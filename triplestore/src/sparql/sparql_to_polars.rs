@@ -1,13 +1,24 @@
-use chrono::NaiveDate;
+use crate::sparql::errors::SparqlError;
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use chrono::{NaiveDate, Timelike};
 use oxrdf::vocab::xsd;
 use oxrdf::{Literal, NamedNode, Term};
 use polars::export::chrono::{DateTime, NaiveDateTime, Utc};
-use polars::prelude::{LiteralValue, NamedFrom, Series, TimeUnit};
+use polars::prelude::{
+    AnyValue, BooleanChunked, ChunkFull, DataType, Expr, GetOutput, IntoSeries, LiteralValue,
+    NamedFrom, Series, SpecialEq, TimeUnit,
+};
+use representation::literals::{
+    parse_xsd_decimal, parse_xsd_duration, parse_xsd_time, LiteralParsingError,
+};
 use std::str::FromStr;
 
-pub(crate) fn sparql_term_to_polars_literal_value(term: &Term) -> polars::prelude::LiteralValue {
+pub(crate) fn sparql_term_to_polars_literal_value(
+    term: &Term,
+) -> Result<polars::prelude::LiteralValue, SparqlError> {
     match term {
-        Term::NamedNode(named_node) => sparql_named_node_to_polars_literal_value(named_node),
+        Term::NamedNode(named_node) => Ok(sparql_named_node_to_polars_literal_value(named_node)),
         Term::Literal(lit) => sparql_literal_to_polars_literal_value(lit),
         _ => {
             panic!("Not supported")
@@ -19,34 +30,54 @@ pub(crate) fn sparql_named_node_to_polars_literal_value(named_node: &NamedNode)
     LiteralValue::Utf8(named_node.as_str().to_string())
 }
 
-pub(crate) fn sparql_literal_to_polars_literal_value(lit: &Literal) -> LiteralValue {
+pub(crate) fn sparql_literal_to_polars_literal_value(
+    lit: &Literal,
+) -> Result<LiteralValue, SparqlError> {
     let datatype = lit.datatype();
     let value = lit.value();
+    let parse_error = || {
+        SparqlError::InvalidLiteral(LiteralParsingError {
+            value: value.to_string(),
+            datatype: datatype.to_string(),
+        })
+    };
     let literal_value = if datatype == xsd::STRING {
         LiteralValue::Utf8(value.to_string())
     } else if datatype == xsd::UNSIGNED_INT {
-        let u = u32::from_str(value).expect("Integer parsing error");
+        let u = u32::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::UInt32(u)
     } else if datatype == xsd::UNSIGNED_LONG {
-        let u = u64::from_str(value).expect("Integer parsing error");
+        let u = u64::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::UInt64(u)
     } else if datatype == xsd::INTEGER {
-        let i = i64::from_str(value).expect("Integer parsing error");
+        let i = i64::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Int64(i)
     } else if datatype == xsd::LONG {
-        let i = i64::from_str(value).expect("Integer parsing error");
+        let i = i64::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Int64(i)
     } else if datatype == xsd::INT {
-        let i = i32::from_str(value).expect("Integer parsing error");
+        let i = i32::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Int32(i)
+    } else if datatype == xsd::SHORT {
+        let i = i16::from_str(value).map_err(|_| parse_error())?;
+        LiteralValue::Int16(i)
+    } else if datatype == xsd::UNSIGNED_SHORT {
+        let u = u16::from_str(value).map_err(|_| parse_error())?;
+        LiteralValue::UInt16(u)
+    } else if datatype == xsd::BYTE {
+        let i = i8::from_str(value).map_err(|_| parse_error())?;
+        LiteralValue::Int8(i)
+    } else if datatype == xsd::UNSIGNED_BYTE {
+        let u = u8::from_str(value).map_err(|_| parse_error())?;
+        LiteralValue::UInt8(u)
     } else if datatype == xsd::DOUBLE {
-        let d = f64::from_str(value).expect("Integer parsing error");
+        let d = f64::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Float64(d)
     } else if datatype == xsd::FLOAT {
-        let f = f32::from_str(value).expect("Integer parsing error");
+        let f = f32::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Float32(f)
     } else if datatype == xsd::BOOLEAN {
-        let b = bool::from_str(value).expect("Boolean parsing error");
+        let b = bool::from_str(value).map_err(|_| parse_error())?;
         LiteralValue::Boolean(b)
     } else if datatype == xsd::DATE_TIME {
         let dt_without_tz = value.parse::<NaiveDateTime>();
@@ -57,35 +88,83 @@ pub(crate) fn sparql_literal_to_polars_literal_value(lit: &Literal) -> LiteralVa
             if let Ok(dt) = dt_without_tz {
                 LiteralValue::DateTime(dt.naive_utc().timestamp(), TimeUnit::Nanoseconds, None)
             } else {
-                panic!("Could not parse datetime: {}", value);
+                return Err(parse_error());
             }
         }
     } else if datatype == xsd::DATE {
         let ymd_string: Vec<&str> = value.split("-").collect();
         if ymd_string.len() != 3 {
-            todo!("Unsupported date format {}", value)
+            return Err(parse_error());
         }
-        let y = i32::from_str(ymd_string.get(0).unwrap()).expect(&format!(
-            "Year parsing error {}",
-            ymd_string.get(0).unwrap()
-        ));
-        let m = u32::from_str(ymd_string.get(1).unwrap()).expect(&format!(
-            "Month parsing error {}",
-            ymd_string.get(1).unwrap()
-        ));
-        let d = u32::from_str(ymd_string.get(2).unwrap())
-            .expect(&format!("Day parsing error {}", ymd_string.get(1).unwrap()));
-        let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let y = i32::from_str(ymd_string.get(0).unwrap()).map_err(|_| parse_error())?;
+        let m = u32::from_str(ymd_string.get(1).unwrap()).map_err(|_| parse_error())?;
+        let d = u32::from_str(ymd_string.get(2).unwrap()).map_err(|_| parse_error())?;
+        let date = NaiveDate::from_ymd_opt(y, m, d).ok_or_else(parse_error)?;
         let dt = date.and_hms_opt(0, 0, 0).unwrap();
 
         LiteralValue::DateTime(dt.timestamp(), TimeUnit::Milliseconds, None)
     } else if datatype == xsd::DECIMAL {
-        let d = f64::from_str(value).expect("Decimal parsing error");
-        LiteralValue::Float64(d)
+        //Decimals are stored exactly, as in the triplestore
+        let (v, scale) = parse_xsd_decimal(value).ok_or_else(parse_error)?;
+        let s = Series::from_any_values("literal", &[AnyValue::Decimal(v, scale)], false).unwrap();
+        LiteralValue::Series(SpecialEq::new(s))
+    } else if datatype == xsd::TIME {
+        let t = parse_xsd_time(value).ok_or_else(parse_error)?;
+        LiteralValue::Time(
+            t.num_seconds_from_midnight() as i64 * 1_000_000_000 + t.nanosecond() as i64,
+        )
+    } else if datatype == xsd::DURATION {
+        let d = parse_xsd_duration(value).ok_or_else(parse_error)?;
+        LiteralValue::Duration(d, TimeUnit::Nanoseconds)
+    } else if datatype == xsd::BASE_64_BINARY {
+        let b = general_purpose::STANDARD
+            .decode(value)
+            .map_err(|_| parse_error())?;
+        LiteralValue::Binary(b)
     } else {
-        todo!("Not implemented!")
+        return Err(parse_error());
     };
-    literal_value
+    Ok(literal_value)
+}
+
+//Polars does not compare decimals, so decimal terms are matched exactly after rescaling
+pub(crate) fn sparql_decimal_equals(expr: Expr, lit: &Literal) -> Result<Expr, SparqlError> {
+    let (v, scale) = parse_xsd_decimal(lit.value()).ok_or_else(|| {
+        SparqlError::InvalidLiteral(LiteralParsingError {
+            value: lit.value().to_string(),
+            datatype: lit.datatype().to_string(),
+        })
+    })?;
+    Ok(expr.map(
+        move |s| {
+            let mut out: BooleanChunked = if let DataType::Decimal(_, _) = s.dtype() {
+                let decimal = s.decimal().unwrap();
+                let s_scale = decimal.scale();
+                decimal
+                    .0
+                    .into_iter()
+                    .map(|x| x.map(|x| decimal_equals(x, s_scale, v, scale)))
+                    .collect()
+            } else {
+                BooleanChunked::full(s.name(), false, s.len())
+            };
+            out.rename(s.name());
+            Ok(Some(out.into_series()))
+        },
+        GetOutput::from_type(DataType::Boolean),
+    ))
+}
+
+fn decimal_equals(left: i128, left_scale: usize, right: i128, right_scale: usize) -> bool {
+    let rescale = |v: i128, scale: usize| {
+        10i128
+            .checked_pow((left_scale.max(right_scale) - scale) as u32)
+            .and_then(|m| v.checked_mul(m))
+    };
+    match (rescale(left, left_scale), rescale(right, right_scale)) {
+        (Some(l), Some(r)) => l == r,
+        _ => false,
+    }
 }
 
 fn polars_literal_values_to_series(literal_values: Vec<LiteralValue>, name: &str) -> Series {
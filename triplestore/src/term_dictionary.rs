@@ -0,0 +1,298 @@
+use crate::errors::TriplestoreError;
+use hashbrown::hash_map::{DefaultHashBuilder, RawEntryMut};
+use hashbrown::HashMap;
+use parquet_io::{read_parquet, write_parquet};
+use polars::prelude::{Expr, GetOutput};
+use polars_core::datatypes::DataType;
+use polars_core::frame::DataFrame;
+use polars_core::prelude::{IntoSeries, NamedFrom, UInt32Chunked};
+use polars_core::series::Series;
+use representation::RDFNodeType;
+use std::fs::{read_dir, remove_file, rename};
+use std::hash::BuildHasher;
+use std::path::Path;
+
+const TERM_COLUMN: &str = "term";
+const DICTIONARY_FILENAME_PREFIX: &str = "dictionary_";
+const DICTIONARY_FILENAME_SUFFIX: &str = ".parquet";
+
+//Maps the IRIs and blank nodes of the triplestore to u32 ids, so that the triple tables store
+//subjects and IRI or blank node objects as integers. The id of a term is its position in terms,
+//and ids are never reused, as the triples in the caching folder refer to them.
+pub struct TermDictionary {
+    terms: Series,
+    //The id of the first term in each chunk of terms
+    chunk_starts: Vec<usize>,
+    //The ids of the terms by the hashes of the terms, which are only stored in terms
+    ids: HashMap<u32, (), ()>,
+    hasher: DefaultHashBuilder,
+    //The number of terms written to the caching folder
+    n_persisted: usize,
+}
+
+impl Default for TermDictionary {
+    fn default() -> Self {
+        let terms = Series::new_empty(TERM_COLUMN, &DataType::Utf8);
+        TermDictionary {
+            chunk_starts: chunk_starts(&terms),
+            terms,
+            ids: HashMap::default(),
+            hasher: DefaultHashBuilder::default(),
+            n_persisted: 0,
+        }
+    }
+}
+
+impl TermDictionary {
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    //Terms not seen before are given the next ids
+    pub fn encode(&mut self, terms: &Series) -> Series {
+        let terms = terms.cast(&DataType::Utf8).unwrap();
+        let mut new_terms = vec![];
+        let mut ids = Vec::with_capacity(terms.len());
+        for t in terms.utf8().unwrap().into_iter() {
+            ids.push(t.map(|t| {
+                let hash = self.hasher.hash_one(t);
+                let (stored, starts, hasher) = (&self.terms, &self.chunk_starts, &self.hasher);
+                let entry = self.ids.raw_entry_mut().from_hash(hash, |id| {
+                    term_with_id(stored, starts, &new_terms, *id) == t
+                });
+                match entry {
+                    RawEntryMut::Occupied(o) => *o.key(),
+                    RawEntryMut::Vacant(v) => {
+                        let id = u32::try_from(stored.len() + new_terms.len())
+                            .expect("Too many terms for u32 ids");
+                        v.insert_with_hasher(hash, id, (), |id| {
+                            hasher.hash_one(term_with_id(stored, starts, &new_terms, *id))
+                        });
+                        new_terms.push(t);
+                        id
+                    }
+                }
+            }));
+        }
+        if !new_terms.is_empty() {
+            self.terms
+                .append(&Series::new(TERM_COLUMN, new_terms))
+                .unwrap();
+            self.chunk_starts = chunk_starts(&self.terms);
+        }
+        let mut ids = UInt32Chunked::from_iter(ids);
+        ids.rename(terms.name());
+        ids.into_series()
+    }
+
+    pub fn lookup(&self, term: &str) -> Option<u32> {
+        let hash = self.hasher.hash_one(term);
+        self.ids
+            .raw_entry()
+            .from_hash(hash, |id| {
+                term_with_id(&self.terms, &self.chunk_starts, &[], *id) == term
+            })
+            .map(|(id, _)| *id)
+    }
+
+    //Terms that are not in the dictionary are null
+    pub fn lookup_series(&self, terms: &Series) -> Series {
+        let terms = terms.cast(&DataType::Utf8).unwrap();
+        let mut ids: UInt32Chunked = terms
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.and_then(|t| self.lookup(t)))
+            .collect();
+        ids.rename(terms.name());
+        ids.into_series()
+    }
+
+    pub fn decode(&self, ids: &Series) -> Series {
+        let mut terms = self.terms.take(ids.u32().unwrap()).unwrap();
+        terms.rename(ids.name());
+        terms
+    }
+
+    //Decodes the ids in the column of the expression when the expression is evaluated
+    pub fn decode_expr(&self, expr: Expr) -> Expr {
+        let terms = self.terms.clone();
+        expr.map(
+            move |s| {
+                let mut decoded = terms.take(s.u32()?)?;
+                decoded.rename(s.name());
+                Ok(Some(decoded))
+            },
+            GetOutput::from_type(DataType::Utf8),
+        )
+    }
+
+    //The subjects, and objects of IRI or blank node triples, are stored as ids
+    pub fn encode_triples(&mut self, df: &mut DataFrame, object_type: &RDFNodeType) {
+        for c in encoded_triple_columns(object_type) {
+            let ids = self.encode(df.column(c).unwrap());
+            df.with_column(ids).unwrap();
+        }
+    }
+
+    pub fn decode_triples(&self, df: &DataFrame, object_type: &RDFNodeType) -> DataFrame {
+        let mut df = df.clone();
+        for c in encoded_triple_columns(object_type) {
+            let s = df.column(c).unwrap();
+            if s.dtype() == &DataType::UInt32 {
+                let terms = self.decode(s);
+                df.with_column(terms).unwrap();
+            }
+        }
+        df
+    }
+
+    //Several chunks are created by encoding, decoding is faster with a single chunk
+    pub(crate) fn rechunk(&mut self) {
+        if self.terms.n_chunks() > 1 {
+            self.terms = self.terms.rechunk();
+            self.chunk_starts = chunk_starts(&self.terms);
+        }
+    }
+
+    //The terms added since the last write are written to a file named by the id of the first term.
+    //The file is written to a temporary file first, so that an interrupted write is not read.
+    pub(crate) fn write_new_terms(&mut self, folder_path: &Path) -> Result<(), TriplestoreError> {
+        if self.n_persisted == self.len() {
+            return Ok(());
+        }
+        let new_terms = self
+            .terms
+            .slice(self.n_persisted as i64, self.len() - self.n_persisted);
+        let mut df = DataFrame::new(vec![new_terms]).unwrap();
+        let file_name = dictionary_filename(self.n_persisted);
+        let tmp_path = folder_path.join(format!("{}.tmp", file_name));
+        write_parquet(&mut df, &tmp_path).map_err(|x| TriplestoreError::ParquetIOError(x))?;
+        rename(&tmp_path, folder_path.join(file_name))
+            .map_err(|x| TriplestoreError::WriteTermDictionaryError(x))?;
+        self.n_persisted = self.len();
+        Ok(())
+    }
+
+    pub(crate) fn read(folder_path: &Path) -> Result<TermDictionary, TriplestoreError> {
+        let mut starts = vec![];
+        for (start, _) in dictionary_files(folder_path)? {
+            if let Some(start) = start {
+                starts.push(start);
+            }
+        }
+        starts.sort();
+        let mut dictionary = TermDictionary::default();
+        for start in starts {
+            if start != dictionary.len() {
+                return Err(TriplestoreError::InvalidTermDictionary(format!(
+                    "expected a file starting at id {}, found one starting at id {}",
+                    dictionary.len(),
+                    start
+                )));
+            }
+            let path = folder_path.join(dictionary_filename(start));
+            let df = read_parquet(&path.to_str().unwrap().to_string())
+                .map_err(|x| TriplestoreError::ParquetIOError(x))?
+                .collect()
+                .map_err(|x| TriplestoreError::InvalidTermDictionary(x.to_string()))?;
+            let terms = df
+                .column(TERM_COLUMN)
+                .map_err(|x| TriplestoreError::InvalidTermDictionary(x.to_string()))?;
+            if terms.dtype() != &DataType::Utf8 || terms.null_count() > 0 {
+                return Err(TriplestoreError::InvalidTermDictionary(format!(
+                    "file {} should have a term column of strings without nulls",
+                    path.to_str().unwrap()
+                )));
+            }
+            let _ = dictionary.encode(terms);
+            if dictionary.len() != start + terms.len() {
+                return Err(TriplestoreError::InvalidTermDictionary(format!(
+                    "file {} has duplicate terms",
+                    path.to_str().unwrap()
+                )));
+            }
+        }
+        dictionary.n_persisted = dictionary.len();
+        Ok(dictionary)
+    }
+}
+
+pub(crate) fn delete_term_dictionary(folder_path: &Path) -> Result<(), TriplestoreError> {
+    for (_, path) in dictionary_files(folder_path)? {
+        remove_file(path).map_err(|x| TriplestoreError::RemoveParquetFileError(x))?;
+    }
+    Ok(())
+}
+
+fn chunk_starts(terms: &Series) -> Vec<usize> {
+    let mut starts = vec![];
+    let mut start = 0;
+    for len in terms.chunk_lengths() {
+        starts.push(start);
+        start += len;
+    }
+    starts
+}
+
+//Ids after the ones in terms belong to the new terms that are not yet appended to them
+fn term_with_id<'a>(
+    terms: &'a Series,
+    chunk_starts: &[usize],
+    new_terms: &[&'a str],
+    id: u32,
+) -> &'a str {
+    let id = id as usize;
+    if id >= terms.len() {
+        return new_terms[id - terms.len()];
+    }
+    let chunk = chunk_starts.partition_point(|x| *x <= id) - 1;
+    terms
+        .utf8()
+        .unwrap()
+        .downcast_chunks()
+        .get(chunk)
+        .unwrap()
+        .value(id - chunk_starts[chunk])
+}
+
+fn encoded_triple_columns(object_type: &RDFNodeType) -> Vec<&'static str> {
+    match object_type {
+        RDFNodeType::IRI | RDFNodeType::BlankNode => vec!["subject", "object"],
+        _ => vec!["subject"],
+    }
+}
+
+fn dictionary_filename(start: usize) -> String {
+    format!(
+        "{}{}{}",
+        DICTIONARY_FILENAME_PREFIX, start, DICTIONARY_FILENAME_SUFFIX
+    )
+}
+
+//The dictionary files in the folder with the id of their first term, temporary files have no id
+fn dictionary_files(
+    folder_path: &Path,
+) -> Result<Vec<(Option<usize>, std::path::PathBuf)>, TriplestoreError> {
+    let contents =
+        read_dir(folder_path).map_err(|x| TriplestoreError::ReadCachingDirectoryError(x))?;
+    let mut files = vec![];
+    for f in contents {
+        let entry = f.map_err(|x| TriplestoreError::ReadCachingDirectoryEntryError(x))?;
+        let fname = entry.file_name().to_str().unwrap().to_string();
+        if let Some(rest) = fname.strip_prefix(DICTIONARY_FILENAME_PREFIX) {
+            if let Some(start) = rest.strip_suffix(DICTIONARY_FILENAME_SUFFIX) {
+                if let Ok(start) = start.parse::<usize>() {
+                    files.push((Some(start), entry.path()));
+                }
+            } else if rest.ends_with(".parquet.tmp") {
+                files.push((None, entry.path()));
+            }
+        }
+    }
+    Ok(files)
+}